@@ -0,0 +1,244 @@
+use crate::storage::Collection;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+// Journal of every saved change, one JSON object per line in `audit.jsonl` next to the
+// data. Lines are only ever appended; the application never rewrites or trims the file.
+
+const AUDIT_FILE: &str = "audit.jsonl";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+}
+
+impl AuditAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+        }
+    }
+}
+
+// A field whose value changed; `None` where the record did not exist
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Local>,
+    // Login name of whoever ran the application
+    pub user: String,
+    pub action: AuditAction,
+    // Kind of record, e.g. "student" or "enrollment"
+    pub entity: String,
+    pub id: String,
+    // What the user did, e.g. "delete faculty Physics"; every record a cascading delete
+    // touched shares it
+    pub operation: String,
+    pub changes: Vec<FieldChange>,
+}
+
+impl AuditEntry {
+    // Tells entries apart, for keeping one selected in the viewer
+    pub fn key(&self) -> String {
+        format!("{} {}", self.timestamp.to_rfc3339(), self.id)
+    }
+
+    pub fn time(&self) -> String {
+        self.timestamp.format("%Y-%m-%d %H:%M:%S").to_string()
+    }
+
+    // Each changed field, e.g. "age: 20 → 21"; created and deleted records list their values
+    pub fn change_texts(&self) -> Vec<String> {
+        self.changes
+            .iter()
+            .map(|change| match self.action {
+                AuditAction::Create => format!("{}: {}", change.field, display_value(change.after.as_ref())),
+                AuditAction::Delete => format!("{}: {}", change.field, display_value(change.before.as_ref())),
+                AuditAction::Update => format!(
+                    "{}: {} → {}",
+                    change.field,
+                    display_value(change.before.as_ref()),
+                    display_value(change.after.as_ref())
+                ),
+            })
+            .collect()
+    }
+
+    // The changed fields on one line
+    pub fn summary(&self) -> String {
+        self.change_texts().join(", ")
+    }
+
+    // Whether the entry passes a viewer filter: `entity:<kind>` and `date:<prefix>` words
+    // match those fields, any other word the time, user, action, entity, id or operation
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        let time = self.time();
+        let text = [time.as_str(), &self.user, self.action.label(), &self.entity, &self.id, &self.operation]
+            .join(" ")
+            .to_lowercase();
+        query.split_whitespace().all(|word| {
+            if let Some(kind) = word.strip_prefix("entity:") {
+                self.entity.starts_with(kind) || Collection::ALL.iter().any(|c| c.name() == kind && entity_name(*c) == self.entity)
+            } else if let Some(date) = word.strip_prefix("date:") {
+                time.starts_with(date)
+            } else {
+                text.contains(word)
+            }
+        })
+    }
+}
+
+fn display_value(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => "-".to_string(),
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(items)) => items.iter().map(|item| display_value(Some(item))).collect::<Vec<_>>().join(", "),
+        Some(other) => other.to_string(),
+    }
+}
+
+// Singular name of the records in a collection
+fn entity_name(collection: Collection) -> &'static str {
+    match collection {
+        Collection::Students => "student",
+        Collection::Teachers => "teacher",
+        Collection::Faculties => "faculty",
+        Collection::Courses => "course",
+        Collection::Enrollments => "enrollment",
+        Collection::Trash => "trash",
+    }
+}
+
+// Fields that differ between two versions of a record, in the record's field order. The
+// save counter goes up on every save, so it is left out.
+fn diff(before: Option<&Value>, after: Option<&Value>) -> Vec<FieldChange> {
+    let empty = serde_json::Map::new();
+    let fields = |value: Option<&Value>| match value {
+        Some(Value::Object(fields)) => fields.clone(),
+        _ => empty.clone(),
+    };
+    let (before, after) = (fields(before), fields(after));
+    let names = before.keys().chain(after.keys().filter(|name| !before.contains_key(*name)));
+    names
+        .filter(|name| *name != "version" && before.get(*name) != after.get(*name))
+        .map(|name| FieldChange {
+            field: name.clone(),
+            before: before.get(name).cloned(),
+            after: after.get(name).cloned(),
+        })
+        .collect()
+}
+
+pub struct AuditLog {
+    path: PathBuf,
+    user: String,
+    // Everything in the file, oldest first
+    entries: Vec<AuditEntry>,
+}
+
+impl AuditLog {
+    // Read the journal in `data_dir`; lines that don't parse are left in the file but not shown
+    pub fn open(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join(AUDIT_FILE);
+        let entries = read_entries(&path)?;
+        Ok(Self { path, user: current_user(), entries })
+    }
+
+    // Read the journal again, with whatever other instances appended to it
+    pub fn reload(&mut self) -> Result<()> {
+        self.entries = read_entries(&self.path)?;
+        Ok(())
+    }
+
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.entries
+    }
+
+    // Journal one saved change: each touched record with its JSON before and after
+    pub fn record(&mut self, operation: &str, records: Vec<(Collection, String, Option<Value>, Option<Value>)>) -> Result<()> {
+        let timestamp = Local::now();
+        let entries: Vec<AuditEntry> = records
+            .into_iter()
+            .filter_map(|(collection, id, before, after)| {
+                let action = match (&before, &after) {
+                    (None, Some(_)) => AuditAction::Create,
+                    (Some(_), None) => AuditAction::Delete,
+                    (Some(_), Some(_)) => AuditAction::Update,
+                    (None, None) => return None,
+                };
+                let changes = diff(before.as_ref(), after.as_ref());
+                // Saving a record unchanged is not worth a line
+                if changes.is_empty() {
+                    return None;
+                }
+                Some(AuditEntry {
+                    timestamp,
+                    user: self.user.clone(),
+                    action,
+                    entity: entity_name(collection).to_string(),
+                    id,
+                    operation: operation.to_string(),
+                    changes,
+                })
+            })
+            .collect();
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let mut lines = String::new();
+        for entry in &entries {
+            lines.push_str(&serde_json::to_string(entry)?);
+            lines.push('\n');
+        }
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        file.write_all(lines.as_bytes())
+            .and_then(|()| file.sync_data())
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+        self.entries.extend(entries);
+        Ok(())
+    }
+}
+
+fn read_entries(path: &Path) -> Result<Vec<AuditEntry>> {
+    let mut entries = Vec::new();
+    if path.exists() {
+        let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        for line in BufReader::new(file).lines() {
+            let line = line.with_context(|| format!("Failed to read {}", path.display()))?;
+            if let Ok(entry) = serde_json::from_str(&line) {
+                entries.push(entry);
+            }
+        }
+    }
+    Ok(entries)
+}
+
+// The OS account running the application
+fn current_user() -> String {
+    whoami::username().unwrap_or_else(|_| "unknown".to_string())
+}
@@ -0,0 +1,523 @@
+use crate::data_manager::{DataManager, DeletePolicy};
+use crate::form::InputField;
+use crate::modal::{Modal, ModalType};
+use crate::models::{Course, EntityKind, Enrollment, Teacher};
+use crate::storage::StorageKind;
+use anyhow::{bail, Context, Result};
+use clap::{Args, Parser, Subcommand};
+use serde::Serialize;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+// Command-line interface. Without a subcommand the TUI starts.
+#[derive(Parser)]
+#[command(version, about = "Manage university students, teachers, faculties and courses")]
+pub struct Cli {
+    /// Storage backend to use (json or sqlite)
+    #[arg(long, default_value_t = StorageKind::Json, global = true)]
+    pub storage: StorageKind,
+
+    /// Directory holding the data files [default: from the config file, else ./data]
+    #[arg(long, env = "UNIVERSITY_MANAGER_DATA_DIR", global = true)]
+    pub data_dir: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Print every record of one kind, one per line with tab-separated fields
+    List {
+        entity: EntityKind,
+        /// Print the records as JSON instead
+        #[arg(long)]
+        json: bool,
+    },
+    /// Add a record and print its id
+    Add {
+        entity: EntityKind,
+        #[command(flatten)]
+        fields: Fields,
+    },
+    /// Change some fields of a record
+    Update {
+        entity: EntityKind,
+        id: String,
+        #[command(flatten)]
+        fields: Fields,
+    },
+    /// Delete a record. Fails while other records depend on it, unless told what to do with them.
+    Delete {
+        entity: EntityKind,
+        id: String,
+        /// Delete the dependent records too
+        #[arg(long, conflicts_with = "reassign")]
+        cascade: bool,
+        /// Point the dependent records at this record instead
+        #[arg(long, value_name = "ID")]
+        reassign: Option<String>,
+    },
+    /// List, add, change or delete the courses students are enrolled in, with their grades
+    Enrollment {
+        #[command(subcommand)]
+        command: EnrollmentCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum EnrollmentCommand {
+    /// Print every enrollment, one per line with tab-separated fields
+    List {
+        /// Only the enrollments of this student (id or full name)
+        #[arg(long)]
+        student: Option<String>,
+        /// Print the enrollments as JSON instead
+        #[arg(long)]
+        json: bool,
+    },
+    /// Enroll a student in a course and print the enrollment's id
+    Add {
+        /// Student id or full name
+        #[arg(long)]
+        student: String,
+        #[command(flatten)]
+        fields: EnrollmentFields,
+    },
+    /// Change some fields of an enrollment, e.g. to award its grade
+    Update {
+        id: String,
+        #[command(flatten)]
+        fields: EnrollmentFields,
+    },
+    /// Delete an enrollment
+    Delete { id: String },
+}
+
+// Fields of the enrollment form
+#[derive(Args)]
+pub struct EnrollmentFields {
+    /// Course id or code
+    #[arg(long)]
+    course: Option<String>,
+    #[arg(long)]
+    term: Option<String>,
+    /// Grade on the configured grading scale; an empty value removes it
+    #[arg(long)]
+    grade: Option<String>,
+}
+
+// Record fields, named after the form fields; each kind of record uses its own subset
+#[derive(Args)]
+pub struct Fields {
+    #[arg(long)]
+    first_name: Option<String>,
+    #[arg(long)]
+    last_name: Option<String>,
+    #[arg(long)]
+    age: Option<String>,
+    #[arg(long)]
+    major: Option<String>,
+    #[arg(long)]
+    title: Option<String>,
+    /// Faculty id or name
+    #[arg(long)]
+    faculty: Option<String>,
+    #[arg(long)]
+    name: Option<String>,
+    #[arg(long)]
+    building: Option<String>,
+    #[arg(long)]
+    head_name: Option<String>,
+    #[arg(long)]
+    established_year: Option<String>,
+    #[arg(long)]
+    num_staff: Option<String>,
+    #[arg(long)]
+    code: Option<String>,
+    #[arg(long)]
+    credits: Option<String>,
+    /// Comma-separated teacher ids or full names
+    #[arg(long)]
+    teachers: Option<String>,
+    // Accepted only to explain that it can't be set
+    #[arg(long, hide = true)]
+    gpa: Option<String>,
+}
+
+impl Fields {
+    // The given fields with their flag names
+    fn given(&self) -> Vec<(InputField, &'static str, &str)> {
+        [
+            (InputField::FirstName, "first-name", &self.first_name),
+            (InputField::LastName, "last-name", &self.last_name),
+            (InputField::Age, "age", &self.age),
+            (InputField::Major, "major", &self.major),
+            (InputField::Title, "title", &self.title),
+            (InputField::Faculty, "faculty", &self.faculty),
+            (InputField::Name, "name", &self.name),
+            (InputField::Building, "building", &self.building),
+            (InputField::HeadName, "head-name", &self.head_name),
+            (InputField::EstablishedYear, "established-year", &self.established_year),
+            (InputField::NumStaff, "num-staff", &self.num_staff),
+            (InputField::Code, "code", &self.code),
+            (InputField::Credits, "credits", &self.credits),
+            (InputField::Teachers, "teachers", &self.teachers),
+        ]
+        .into_iter()
+        .filter_map(|(field, flag, value)| value.as_deref().map(|value| (field, flag, value)))
+        .collect()
+    }
+}
+
+// Run a subcommand against the data, without touching the terminal
+pub fn run(command: Command, data_manager: &mut DataManager) -> Result<()> {
+    // Never write over data that could not be read; the TUI offers recovery
+    if let Some(error) = data_manager.load_errors().first() {
+        bail!(
+            "{} could not be read ({}); start the TUI to recover it",
+            error.location,
+            error.message
+        );
+    }
+
+    match command {
+        Command::List { entity, json } => list(data_manager, entity, json),
+        Command::Add { entity, fields } => {
+            let modal_type = match entity {
+                EntityKind::Student => ModalType::AddStudent,
+                EntityKind::Teacher => ModalType::AddTeacher,
+                EntityKind::Faculty => ModalType::AddFaculty,
+                EntityKind::Course => ModalType::AddCourse,
+            };
+            let id = save(data_manager, entity, modal_type, &fields)?;
+            println!("{}", id);
+            Ok(())
+        }
+        Command::Update { entity, id, fields } => {
+            let modal_type = match entity {
+                EntityKind::Student => data_manager.get_student_by_id(&id).cloned().map(ModalType::EditStudent),
+                EntityKind::Teacher => data_manager.get_teacher_by_id(&id).cloned().map(ModalType::EditTeacher),
+                EntityKind::Faculty => data_manager.get_faculty_by_id(&id).cloned().map(ModalType::EditFaculty),
+                EntityKind::Course => data_manager.get_course_by_id(&id).cloned().map(ModalType::EditCourse),
+            };
+            let modal_type = modal_type.with_context(|| format!("No {} with id {}", entity, id))?;
+            save(data_manager, entity, modal_type, &fields)?;
+            Ok(())
+        }
+        Command::Delete { entity, id, cascade, reassign } => {
+            let policy = match (cascade, reassign) {
+                (true, _) => DeletePolicy::Cascade,
+                (false, Some(target)) => DeletePolicy::Reassign(target),
+                (false, None) => DeletePolicy::Block,
+            };
+            if policy == DeletePolicy::Block {
+                let dependents = data_manager.dependents(entity, &id);
+                if !dependents.is_empty() {
+                    bail!(
+                        "{} record(s) still depend on this {}:\n  {}\nUse --cascade or --reassign <ID>",
+                        dependents.len(),
+                        entity,
+                        dependents.join("\n  ")
+                    );
+                }
+            }
+
+            let deleted = match entity {
+                EntityKind::Student => data_manager.delete_student(&id, &policy)?,
+                EntityKind::Teacher => data_manager.delete_teacher(&id, &policy)?,
+                EntityKind::Faculty => data_manager.delete_faculty(&id, &policy)?,
+                EntityKind::Course => data_manager.delete_course(&id, &policy)?,
+            };
+            if !deleted {
+                bail!("No {} with id {}", entity, id);
+            }
+            Ok(())
+        }
+        Command::Enrollment { command } => run_enrollment(command, data_manager),
+    }
+}
+
+fn run_enrollment(command: EnrollmentCommand, data_manager: &mut DataManager) -> Result<()> {
+    match command {
+        EnrollmentCommand::List { student, json } => {
+            let student_id = student.map(|student| resolve_student(data_manager, &student)).transpose()?;
+            let enrollments: Vec<WithNames<Enrollment>> = data_manager
+                .get_all_enrollments()
+                .iter()
+                .filter(|e| student_id.as_ref().is_none_or(|id| e.student_id == *id))
+                .map(|e| WithNames {
+                    student: data_manager.get_student_by_id(&e.student_id).map(|s| s.full_name()),
+                    course: data_manager.get_course_by_id(&e.course_id).map(|c| c.code.clone()),
+                    ..WithNames::new(e)
+                })
+                .collect();
+            let scale = data_manager.rules().grading_scale;
+            print_records(&enrollments, json, |view| {
+                let e = view.record;
+                let grade = e.grade.as_ref().map(|g| g.display_in(scale));
+                let (student, course) = (view.student.clone(), view.course.clone());
+                vec![e.id.clone(), student.unwrap_or_default(), course.unwrap_or_default(), e.term.clone(), grade.unwrap_or_default()]
+            })
+        }
+        EnrollmentCommand::Add { student, fields } => {
+            let student_id = resolve_student(data_manager, &student)?;
+            let id = save_enrollment(data_manager, ModalType::AddEnrollment(student_id), &fields)?;
+            println!("{}", id);
+            Ok(())
+        }
+        EnrollmentCommand::Update { id, fields } => {
+            let enrollment = data_manager
+                .get_enrollment_by_id(&id)
+                .cloned()
+                .with_context(|| format!("No enrollment with id {}", id))?;
+            save_enrollment(data_manager, ModalType::EditEnrollment(enrollment), &fields)?;
+            Ok(())
+        }
+        EnrollmentCommand::Delete { id } => {
+            if !data_manager.delete_enrollment(&id)? {
+                bail!("No enrollment with id {}", id);
+            }
+            Ok(())
+        }
+    }
+}
+
+// Records as `list` prints them: the stored fields plus what is computed or looked up for
+// the columns, so the JSON output carries the same data as the tab-separated one
+#[derive(Serialize)]
+struct StudentView<'a> {
+    id: &'a str,
+    first_name: &'a str,
+    last_name: &'a str,
+    age: u32,
+    major: &'a str,
+    gpa: Option<f32>,
+    version: u64,
+}
+
+#[derive(Serialize)]
+struct WithNames<'a, T> {
+    #[serde(flatten)]
+    record: &'a T,
+    // Name of the faculty, student or course the record links to by id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    faculty: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    student: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    course: Option<String>,
+}
+
+impl<'a, T> WithNames<'a, T> {
+    fn new(record: &'a T) -> Self {
+        Self { record, faculty: None, student: None, course: None }
+    }
+}
+
+fn faculty_name(data_manager: &DataManager, faculty_id: &str) -> Option<String> {
+    data_manager.get_faculty_by_id(faculty_id).map(|f| f.name.clone())
+}
+
+fn list(data_manager: &DataManager, entity: EntityKind, json: bool) -> Result<()> {
+    match entity {
+        EntityKind::Student => {
+            let students: Vec<StudentView> = data_manager
+                .get_all_students()
+                .iter()
+                .map(|s| StudentView {
+                    id: &s.id,
+                    first_name: &s.first_name,
+                    last_name: &s.last_name,
+                    age: s.age,
+                    major: &s.major,
+                    gpa: data_manager.student_gpa(&s.id),
+                    version: s.version,
+                })
+                .collect();
+            print_records(&students, json, |s| {
+                let gpa = s.gpa.map(|gpa| format!("{:.2}", gpa));
+                let name = format!("{} {}", s.first_name, s.last_name);
+                vec![s.id.to_string(), name, s.age.to_string(), s.major.to_string(), gpa.unwrap_or_default()]
+            })
+        }
+        EntityKind::Teacher => {
+            let teachers: Vec<WithNames<Teacher>> = data_manager
+                .get_all_teachers()
+                .iter()
+                .map(|t| WithNames { faculty: faculty_name(data_manager, &t.faculty_id), ..WithNames::new(t) })
+                .collect();
+            print_records(&teachers, json, |view| {
+                let t = view.record;
+                let faculty = view.faculty.clone().unwrap_or_default();
+                vec![t.id.clone(), t.full_name(), t.age.to_string(), faculty, t.title.clone()]
+            })
+        }
+        EntityKind::Faculty => print_records(data_manager.get_all_faculties(), json, |f| {
+            vec![
+                f.id.clone(),
+                f.name.clone(),
+                f.building.clone(),
+                f.head_name.clone(),
+                f.established_year.to_string(),
+                f.num_staff.to_string(),
+            ]
+        }),
+        EntityKind::Course => {
+            let courses: Vec<WithNames<Course>> = data_manager
+                .get_all_courses()
+                .iter()
+                .map(|c| WithNames { faculty: faculty_name(data_manager, &c.faculty_id), ..WithNames::new(c) })
+                .collect();
+            print_records(&courses, json, |view| {
+                let c = view.record;
+                let faculty = view.faculty.clone().unwrap_or_default();
+                vec![c.id.clone(), c.code.clone(), c.title.clone(), c.credits.to_string(), faculty]
+            })
+        }
+    }
+}
+
+fn print_records<T: Serialize>(records: &[T], json: bool, columns: impl Fn(&T) -> Vec<String>) -> Result<()> {
+    let mut output = String::new();
+    if json {
+        output = serde_json::to_string_pretty(records)? + "\n";
+    } else {
+        for record in records {
+            output += &columns(record).join("\t");
+            output.push('\n');
+        }
+    }
+
+    // Output piped into e.g. `head` may be cut short; that isn't an error
+    match io::stdout().lock().write_all(output.as_bytes()) {
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+// Fill the same form the TUI uses and validate it the same way; returns the record's id
+fn save(data_manager: &mut DataManager, entity: EntityKind, modal_type: ModalType, fields: &Fields) -> Result<String> {
+    if fields.gpa.is_some() {
+        bail!("A student's GPA is computed from their enrollment grades and can't be set directly; use `enrollment update <id> --grade` instead");
+    }
+
+    let mut modal = Modal::new(modal_type, data_manager);
+    for (field, flag, value) in fields.given() {
+        let value = match field {
+            InputField::Faculty => resolve_faculty(data_manager, value)?,
+            InputField::Teachers => resolve_teachers(data_manager, value)?,
+            _ => value.to_string(),
+        };
+        if !modal.set_input(field, &value) {
+            bail!("--{} does not apply to a {}", flag, entity);
+        }
+    }
+
+    let is_new = matches!(
+        modal.modal_type,
+        ModalType::AddStudent | ModalType::AddTeacher | ModalType::AddFaculty | ModalType::AddCourse
+    );
+    let errors = modal.errors();
+    if !errors.is_empty() {
+        bail!("Invalid {} data:\n  {}", entity, errors.join("\n  "));
+    }
+    let invalid = || format!("Invalid {} data", entity);
+    match entity {
+        EntityKind::Student => {
+            let student = modal.create_student().with_context(invalid)?;
+            let id = student.id.clone();
+            if is_new { data_manager.add_student(student)? } else { data_manager.update_student(student)?; }
+            Ok(id)
+        }
+        EntityKind::Teacher => {
+            let teacher = modal.create_teacher().with_context(invalid)?;
+            let id = teacher.id.clone();
+            if is_new { data_manager.add_teacher(teacher)? } else { data_manager.update_teacher(teacher)?; }
+            Ok(id)
+        }
+        EntityKind::Faculty => {
+            let faculty = modal.create_faculty().with_context(invalid)?;
+            let id = faculty.id.clone();
+            if is_new { data_manager.add_faculty(faculty)? } else { data_manager.update_faculty(faculty)?; }
+            Ok(id)
+        }
+        EntityKind::Course => {
+            let course = modal.create_course().with_context(invalid)?;
+            let id = course.id.clone();
+            if is_new { data_manager.add_course(course)? } else { data_manager.update_course(course)?; }
+            Ok(id)
+        }
+    }
+}
+
+// The enrollment form, filled and checked like `save`; returns the enrollment's id
+fn save_enrollment(data_manager: &mut DataManager, modal_type: ModalType, fields: &EnrollmentFields) -> Result<String> {
+    let mut modal = Modal::new(modal_type, data_manager);
+    if let Some(course) = &fields.course {
+        modal.set_input(InputField::Course, &resolve_course(data_manager, course)?);
+    }
+    if let Some(term) = &fields.term {
+        modal.set_input(InputField::Term, term);
+    }
+    if let Some(grade) = &fields.grade {
+        modal.set_input(InputField::Grade, grade);
+    }
+
+    let errors = modal.errors();
+    if !errors.is_empty() {
+        bail!("Invalid enrollment data:\n  {}", errors.join("\n  "));
+    }
+    let enrollment = modal.create_enrollment().context("Invalid enrollment data")?;
+    let id = enrollment.id.clone();
+    if matches!(modal.modal_type, ModalType::AddEnrollment(_)) {
+        data_manager.add_enrollment(enrollment)?;
+    } else {
+        data_manager.update_enrollment(enrollment)?;
+    }
+    Ok(id)
+}
+
+// Students are given by id or full name, courses by id or code
+fn resolve_student(data_manager: &DataManager, value: &str) -> Result<String> {
+    data_manager
+        .get_all_students()
+        .iter()
+        .find(|s| s.id == value || s.full_name().eq_ignore_ascii_case(value))
+        .map(|s| s.id.clone())
+        .with_context(|| format!("No student with id or name '{}'", value))
+}
+
+fn resolve_course(data_manager: &DataManager, value: &str) -> Result<String> {
+    data_manager
+        .get_all_courses()
+        .iter()
+        .find(|c| c.id == value || c.code.eq_ignore_ascii_case(value))
+        .map(|c| c.id.clone())
+        .with_context(|| format!("No course with id or code '{}'", value))
+}
+
+// Faculties and teachers can be given by id or by name
+fn resolve_faculty(data_manager: &DataManager, value: &str) -> Result<String> {
+    data_manager
+        .get_all_faculties()
+        .iter()
+        .find(|f| f.id == value || f.name.eq_ignore_ascii_case(value))
+        .map(|f| f.id.clone())
+        .with_context(|| format!("No faculty with id or name '{}'", value))
+}
+
+fn resolve_teachers(data_manager: &DataManager, value: &str) -> Result<String> {
+    let ids = crate::widgets::split_values(value)
+        .iter()
+        .map(|teacher| {
+            data_manager
+                .get_all_teachers()
+                .iter()
+                .find(|t| t.id == *teacher || t.full_name().eq_ignore_ascii_case(teacher))
+                .map(|t| t.id.clone())
+                .with_context(|| format!("No teacher with id or name '{}'", teacher))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(ids.join(","))
+}
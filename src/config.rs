@@ -0,0 +1,171 @@
+use crate::keymap::{Keymap, Overrides, Preset};
+use crate::models::{EntityKind, GradingScale};
+use crate::rules::{Bounds, Limit, Rules};
+use crate::ui::ActiveTab;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// Directory under the platform config dir (e.g. `~/.config` on Linux) holding `config.toml`
+const CONFIG_DIR_NAME: &str = "university-manager";
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+// Settings from the config file, with defaults for anything it leaves out
+#[derive(Debug, Clone)]
+pub struct Config {
+    // Data directory; the `--data-dir` flag and its environment variable take precedence
+    pub data_dir: PathBuf,
+    // How often timers such as the notification countdown advance
+    pub tick_rate: Duration,
+    // How long a notification stays on screen
+    pub notification_duration: Duration,
+    // Tab shown on startup
+    pub default_tab: ActiveTab,
+    // Key bindings: the built-in ones, a preset's, then the file's own
+    pub keymap: Keymap,
+    // Validation limits and grading scale
+    pub rules: Rules,
+    // Days deleted records stay in the trash before being purged on startup; 0 keeps them
+    pub trash_retention_days: u32,
+    // Previous versions of each JSON data file kept as `.bak` copies; 0 keeps none
+    pub backup_count: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            data_dir: PathBuf::from("data"),
+            tick_rate: Duration::from_millis(100), // 10 ticks per second
+            notification_duration: Duration::from_secs(3),
+            default_tab: ActiveTab::Students,
+            keymap: Keymap::default(),
+            rules: Rules::default(),
+            trash_retention_days: 30,
+            backup_count: 3,
+        }
+    }
+}
+
+// The file as written by the user; every key is optional
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    data_dir: Option<PathBuf>,
+    tick_rate_ms: Option<u64>,
+    notification_duration_ms: Option<u64>,
+    default_tab: Option<String>,
+    keymap_preset: Option<String>,
+    keymap: Overrides,
+    rules: RulesFile,
+    trash_retention_days: Option<u32>,
+    backup_count: Option<usize>,
+}
+
+// The `[rules]` table; limits left out keep their defaults
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RulesFile {
+    student_age: Option<BoundsFile>,
+    teacher_age: Option<BoundsFile>,
+    established_year: Option<BoundsFile>,
+    credits: Option<BoundsFile>,
+    num_staff: Option<BoundsFile>,
+    grading_scale: Option<String>,
+}
+
+// Either bound can be left out to keep its default
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct BoundsFile {
+    min: Option<u32>,
+    max: Option<u32>,
+}
+
+impl Config {
+    // Location of the config file, if the platform has a config directory
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(CONFIG_DIR_NAME).join(CONFIG_FILE_NAME))
+    }
+
+    // Read the config file; a missing file means all defaults
+    pub fn load() -> Result<Self> {
+        match Self::path() {
+            Some(path) if path.exists() => Self::load_from(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    fn load_from(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).context(format!("Failed to read {}", path.display()))?;
+        let file: ConfigFile = toml::from_str(&text).context(format!("Failed to parse {}", path.display()))?;
+        let mut config = Self::default();
+
+        if let Some(data_dir) = file.data_dir {
+            // Relative paths are taken from the config file's directory, not wherever the app was started
+            config.data_dir = match path.parent() {
+                Some(config_dir) => config_dir.join(data_dir),
+                None => data_dir,
+            };
+        }
+        if let Some(ms) = file.tick_rate_ms {
+            if ms == 0 {
+                bail!("{}: tick_rate_ms must be greater than 0", path.display());
+            }
+            config.tick_rate = Duration::from_millis(ms);
+        }
+        if let Some(ms) = file.notification_duration_ms {
+            config.notification_duration = Duration::from_millis(ms);
+        }
+        if let Some(days) = file.trash_retention_days {
+            config.trash_retention_days = days;
+        }
+        if let Some(count) = file.backup_count {
+            config.backup_count = count;
+        }
+        if let Some(tab) = file.default_tab {
+            let kind: EntityKind = tab
+                .parse()
+                .map_err(|err| anyhow::anyhow!("{}: default_tab: {}", path.display(), err))?;
+            config.default_tab = ActiveTab::for_kind(kind);
+        }
+        let preset: Preset = match file.keymap_preset {
+            Some(name) => name
+                .parse()
+                .map_err(|err| anyhow::anyhow!("{}: keymap_preset: {}", path.display(), err))?,
+            None => Preset::Default,
+        };
+        config.keymap = Keymap::new(preset, &file.keymap).map_err(|err| anyhow::anyhow!("{}: {}", path.display(), err))?;
+
+        let rules = file.rules;
+        let limits = [
+            (Limit::StudentAge, "student_age", rules.student_age),
+            (Limit::TeacherAge, "teacher_age", rules.teacher_age),
+            (Limit::EstablishedYear, "established_year", rules.established_year),
+            (Limit::Credits, "credits", rules.credits),
+            (Limit::NumStaff, "num_staff", rules.num_staff),
+        ];
+        for (limit, name, given) in limits {
+            let Some(given) = given else { continue };
+            let bounds = config.rules.get_mut(limit);
+            *bounds = Bounds::new(given.min.unwrap_or(bounds.min), given.max.or(bounds.max));
+            let (min, max) = config.rules.range(limit);
+            if min > max {
+                bail!("{}: rules.{}: min {} is greater than max {}", path.display(), name, min, max);
+            }
+        }
+        if let Some(scale) = rules.grading_scale {
+            config.rules.grading_scale = scale
+                .parse::<GradingScale>()
+                .map_err(|err| anyhow::anyhow!("{}: rules.grading_scale: {}", path.display(), err))?;
+        }
+        Ok(config)
+    }
+
+    // Number of ticks a notification stays visible, at least one
+    pub fn notification_ticks(&self) -> u16 {
+        let ticks = self.notification_duration.as_millis() / self.tick_rate.as_millis().max(1);
+        ticks.clamp(1, u16::MAX as u128) as u16
+    }
+}
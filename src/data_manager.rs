@@ -1,34 +1,83 @@
-use crate::models::{Faculty, Student, Teacher};
-use anyhow::{Context, Result};
-use serde::{de::DeserializeOwned, Serialize};
-use std::fs::{self, File};
-use std::io::{BufReader, BufWriter};
-use std::path::{Path, PathBuf};
+use crate::audit::{AuditEntry, AuditLog};
+use crate::history::{History, Step};
+use crate::models::{Course, EntityKind, Enrollment, Faculty, Student, Teacher, Versioned};
+use crate::rules::Rules;
+use crate::storage::{Change, Collection, Dataset, LoadError, Storage};
+use crate::sync::{self, Conflict, DataLock, FileWatcher};
+use crate::trash::{TrashItem, TrashedRecords};
+use anyhow::{anyhow, bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
 
+// Keeps every record in memory for the UI and writes changes through a storage backend
 pub struct DataManager {
-    data_dir: PathBuf,
-    students: Vec<Student>,
-    teachers: Vec<Teacher>,
-    faculties: Vec<Faculty>,
+    storage: Box<dyn Storage>,
+    data: Dataset,
+    // Collections that could not be read on startup; their lists start out empty
+    load_errors: Vec<LoadError>,
+    // Set when the user chose to look at damaged data without changing anything
+    read_only: bool,
+    // Validation limits and grading scale from the config file
+    rules: Rules,
+    // The data as last loaded or saved; each change is compared against it to make an undo
+    // step, and a reload merges in whatever differs from it
+    saved: Dataset,
+    history: History,
+    // Journal of every change saved through this manager
+    audit: AuditLog,
+    // Notices when another program changes the data files
+    watcher: FileWatcher,
+    // Held while reading or writing the files, so two instances never save at once
+    lock: DataLock,
 }
 
-impl DataManager {
-    pub fn new(data_dir: Option<PathBuf>) -> Result<Self> {
-        // Use the specified data directory or create a default one
-        let data_dir = match data_dir {
-            Some(dir) => dir,
-            None => PathBuf::from("data"),
-        };
+// Records matching a search query, in storage order
+pub struct SearchResults<'a> {
+    pub students: Vec<&'a Student>,
+    pub teachers: Vec<&'a Teacher>,
+    pub faculties: Vec<&'a Faculty>,
+    pub courses: Vec<&'a Course>,
+    // Newest first
+    pub audit: Vec<&'a AuditEntry>,
+    pub trash: Vec<&'a TrashItem>,
+}
+
+// How the data files changed underneath the running application
+#[derive(Debug, Clone, Default)]
+pub struct DiskChanges {
+    // Names of the changed files
+    pub files: Vec<String>,
+    // Records changed in memory that have not been saved
+    pub unsaved: usize,
+    // Unsaved records that were also changed on disk, e.g. "student Ann Lee"
+    pub conflicts: Vec<String>,
+}
 
-        // Create the data directory if it doesn't exist
-        fs::create_dir_all(&data_dir).context("Failed to create data directory")?;
+// What happens to records that still reference an entity being deleted
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeletePolicy {
+    // Refuse to delete while anything depends on the entity
+    Block,
+    // Delete dependent records too; optional links such as a course's teachers are just dropped
+    Cascade,
+    // Point dependent records at another entity of the same kind (by id)
+    Reassign(String),
+}
 
-        // Initialize an empty data manager
+impl DataManager {
+    pub fn new(storage: Box<dyn Storage>, audit: AuditLog, lock: DataLock, rules: Rules) -> Result<Self> {
+        let watcher = FileWatcher::new(storage.watched_files());
         let mut dm = Self {
-            data_dir,
-            students: Vec::new(),
-            teachers: Vec::new(),
-            faculties: Vec::new(),
+            storage,
+            data: Dataset::default(),
+            load_errors: Vec::new(),
+            read_only: false,
+            rules,
+            saved: Dataset::default(),
+            history: History::default(),
+            audit,
+            watcher,
+            lock,
         };
 
         // Load data
@@ -37,77 +86,380 @@ impl DataManager {
         Ok(dm)
     }
 
-    // Helper method to load data from JSON files
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
     fn load_data(&mut self) -> Result<()> {
-        self.students = self.load_from_file("students.json").unwrap_or_default();
-        self.teachers = self.load_from_file("teachers.json").unwrap_or_default();
-        self.faculties = self.load_from_file("faculties.json").unwrap_or_default();
+        let (data, errors) = {
+            let _guard = self.lock.acquire()?;
+            self.storage.load()?
+        };
+        self.saved = data.clone();
+        self.data = data;
+        self.load_errors = errors;
+        self.history.clear();
+        self.watcher.mark_seen();
         Ok(())
     }
 
-    // Generic method to load entities from a JSON file
-    fn load_from_file<T: DeserializeOwned>(&self, filename: &str) -> Result<Vec<T>> {
-        let file_path = self.data_dir.join(filename);
+    // Write changes through the backend under the data directory lock. When another
+    // instance or program changed the files since they were read, its changes are merged
+    // in first; if it changed any of the same records, nothing is saved and the error is
+    // a `Conflict`.
+    fn write(&mut self, changes: &[Change]) -> Result<()> {
+        let _guard = self.lock.acquire()?;
+        let mut changes = changes.to_vec();
+        if !self.watcher.changed().is_empty() {
+            let on_disk = load_readable(self.storage.as_mut())?;
+            let merge = sync::merge(&self.saved, &self.data, on_disk.clone(), false);
+            if !merge.conflicts.is_empty() {
+                let records = merge.conflicts.iter().map(|(collection, id)| self.record_name(*collection, id)).collect();
+                return Err(Conflict { records }.into());
+            }
+            self.saved = on_disk;
+            self.data = merge.data;
+            // Unsaved records from earlier failed saves go along too
+            for change in merge.changes {
+                if !changes.contains(&change) {
+                    changes.push(change);
+                }
+            }
+        }
+        bump_versions(&mut self.data, &self.saved, &changes);
+        self.storage.commit(&self.data, &changes)?;
+        self.watcher.mark_seen();
+        Ok(())
+    }
 
-        if !file_path.exists() {
-            return Ok(Vec::new());
+    // Persist changes already applied in memory, as an undoable step. If saving fails the
+    // touched records go back to how they were last saved, except after a `Conflict`, where
+    // the edit stays in memory for the reload prompt to merge.
+    fn commit(&mut self, description: String, changes: &[Change]) -> Result<()> {
+        let last_saved = Step::new(String::new(), &self.saved, &self.data, changes);
+        let written = if self.read_only {
+            Err(anyhow!("Data is open read-only; changes were not saved"))
+        } else {
+            self.write(changes)
+        };
+        if let Err(err) = written {
+            if !err.is::<Conflict>() {
+                last_saved.revert(&mut self.data);
+            }
+            return Err(err);
         }
+        let step = Step::new(description, &self.saved, &self.data, changes);
+        step.reapply(&mut self.saved);
+        let logged = self.log(&step.description, &step, false);
+        self.history.record(step);
+        logged
+    }
 
-        let file = File::open(&file_path).context(format!("Failed to open {}", filename))?;
-        let reader = BufReader::new(file);
-        let data = serde_json::from_reader(reader).context(format!("Failed to parse {}", filename))?;
-        Ok(data)
+    // The data is already saved when this runs, so a failure only means the journal is incomplete
+    fn log(&mut self, operation: &str, step: &Step, undone: bool) -> Result<()> {
+        self.audit
+            .record(operation, step.record_states(undone))
+            .context("The change was saved, but could not be added to the audit log")
     }
 
-    // Generic method to save entities to a JSON file
-    fn save_to_file<T: Serialize>(&self, data: &[T], filename: &str) -> Result<()> {
-        let file_path = self.data_dir.join(filename);
-        let file = File::create(&file_path).context(format!("Failed to create {}", filename))?;
-        let writer = BufWriter::new(file);
-        serde_json::to_writer_pretty(writer, data)
-            .context(format!("Failed to write data to {}", filename))?;
+    pub fn audit_entries(&self) -> &[AuditEntry] {
+        self.audit.entries()
+    }
+
+    pub fn search_audit(&self, query: &str) -> Vec<&AuditEntry> {
+        self.audit.entries().iter().rev().filter(|e| e.matches(query)).collect()
+    }
+
+    // What undoing would revert, e.g. "delete student Ann Lee"
+    pub fn undo_description(&self) -> Option<&str> {
+        self.history.next_undo().map(|step| step.description.as_str())
+    }
+
+    // Revert the latest change in memory and storage; returns its description, or None
+    // when there is nothing to undo
+    pub fn undo(&mut self) -> Result<Option<String>> {
+        let Some(step) = self.history.take_undo() else {
+            return Ok(None);
+        };
+        match self.save_step(&step, Step::revert, Step::reapply) {
+            Ok(()) => {
+                let description = step.description.clone();
+                let logged = self.log(&format!("undo {}", description), &step, true);
+                self.history.push_redo(step);
+                logged.map(|()| Some(description))
+            }
+            Err(err) => {
+                self.history.push_undo(step);
+                Err(err)
+            }
+        }
+    }
+
+    // Make the latest undone change again
+    pub fn redo(&mut self) -> Result<Option<String>> {
+        let Some(step) = self.history.take_redo() else {
+            return Ok(None);
+        };
+        match self.save_step(&step, Step::reapply, Step::revert) {
+            Ok(()) => {
+                let description = step.description.clone();
+                let logged = self.log(&format!("redo {}", description), &step, false);
+                self.history.push_undo(step);
+                logged.map(|()| Some(description))
+            }
+            Err(err) => {
+                self.history.push_redo(step);
+                Err(err)
+            }
+        }
+    }
+
+    // Bring memory and storage to one side of a step; if saving fails, memory goes back
+    fn save_step(
+        &mut self,
+        step: &Step,
+        apply: fn(&Step, &mut Dataset) -> Vec<Change>,
+        unapply: fn(&Step, &mut Dataset) -> Vec<Change>,
+    ) -> Result<()> {
+        if self.read_only {
+            bail!("Data is open read-only; changes were not saved");
+        }
+        let changes = apply(step, &mut self.data);
+        if let Err(err) = self.write(&changes) {
+            unapply(step, &mut self.data);
+            return Err(err);
+        }
+        // Taken from memory rather than the step, for the versions the save counted
+        Step::new(String::new(), &self.saved, &self.data, &changes).reapply(&mut self.saved);
+        Ok(())
+    }
+
+    // Names of the data files another program changed since they were loaded or saved,
+    // unless the user chose to keep working without reloading them
+    pub fn changed_files(&self) -> Vec<String> {
+        if self.watcher.is_dismissed() {
+            return Vec::new();
+        }
+        self.watcher.changed()
+    }
+
+    // Compare the files on disk with the data in memory, without changing either
+    pub fn disk_changes(&mut self) -> Result<DiskChanges> {
+        let on_disk = self.load_readable()?;
+        let merge = sync::merge(&self.saved, &self.data, on_disk, false);
+        Ok(DiskChanges {
+            files: self.watcher.changed(),
+            unsaved: sync::unsaved_count(&self.saved, &self.data),
+            conflicts: merge.conflicts.iter().map(|(collection, id)| self.record_name(*collection, id)).collect(),
+        })
+    }
+
+    // Keep the data in memory without asking again until the files change further; saves
+    // still merge in what changed on disk
+    pub fn ignore_disk_changes(&mut self) {
+        self.watcher.dismiss();
+    }
+
+    // Read the data files again, merging in records changed in memory but not saved. Where
+    // a record was changed on both sides, `keep_mine` keeps the one in memory. Returns how
+    // many records were merged and saved.
+    pub fn reload(&mut self, keep_mine: bool) -> Result<usize> {
+        if self.read_only {
+            bail!("Data is open read-only; restart the application to read it again");
+        }
+        let on_disk = self.load_readable()?;
+        let merge = sync::merge(&self.saved, &self.data, on_disk.clone(), keep_mine);
+        self.saved = on_disk;
+        self.data = merge.data;
+        self.history.clear();
+        self.watcher.mark_seen();
+        self.audit.reload()?;
+        if !merge.changes.is_empty() {
+            self.commit("merge unsaved changes after reload".to_string(), &merge.changes)?;
+        }
+        Ok(merge.changes.len())
+    }
+
+    fn load_readable(&mut self) -> Result<Dataset> {
+        let _guard = self.lock.acquire()?;
+        load_readable(self.storage.as_mut())
+    }
+
+    // Kind and name of a record in memory or as last saved, e.g. "faculty Physics"
+    fn record_name(&self, collection: Collection, id: &str) -> String {
+        let name = [&self.data, &self.saved].into_iter().find_map(|data| match collection {
+            Collection::Students => data.students.iter().find(|s| s.id == id).map(|s| format!("student {}", s.full_name())),
+            Collection::Teachers => data.teachers.iter().find(|t| t.id == id).map(|t| format!("teacher {}", t.full_name())),
+            Collection::Faculties => data.faculties.iter().find(|f| f.id == id).map(|f| format!("faculty {}", f.name)),
+            Collection::Courses => data.courses.iter().find(|c| c.id == id).map(|c| format!("course {}", c.code)),
+            Collection::Enrollments => data.enrollments.iter().find(|e| e.id == id).map(|e| {
+                let (student, course) = self.enrollment_names(e);
+                format!("enrollment of {} in {}", student, course)
+            }),
+            Collection::Trash => data.trash.iter().find(|t| t.id == id).map(|t| format!("deleted {} {}", t.kind, t.name)),
+        });
+        name.unwrap_or_else(|| format!("{} {}", collection.name(), id))
+    }
+
+    // Refuse to save an edited copy of a record made before its current version
+    fn check_version<T: Versioned>(&self, collection: Collection, current: &T, edited: &T) -> Result<()> {
+        if edited.version() != current.version() {
+            return Err(Conflict { records: vec![self.record_name(collection, current.id())] }.into());
+        }
         Ok(())
     }
 
+    pub fn load_errors(&self) -> &[LoadError] {
+        &self.load_errors
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    // Show the records of unreadable collections that do parse, without ever saving
+    pub fn open_read_only(&mut self) {
+        self.read_only = true;
+        for collection in self.load_errors.iter().map(|e| e.collection).collect::<Vec<_>>() {
+            if let Ok(records) = self.storage.load_raw(collection) {
+                self.keep_valid_records(collection, records);
+            }
+        }
+    }
+
+    // Replace each unreadable collection with its newest backup that parses
+    pub fn restore_backups(&mut self) -> Result<()> {
+        let collections: Vec<Collection> = self.load_errors.iter().map(|e| e.collection).collect();
+        {
+            let _guard = self.lock.acquire()?;
+            self.storage.restore_backups(&collections)?;
+        }
+        self.load_data()
+    }
+
+    // Keep the records of each unreadable collection that do parse and save them back.
+    // Returns how many were dropped.
+    pub fn skip_invalid_records(&mut self) -> Result<usize> {
+        // Only possible when every collection can still be read record by record
+        let mut collections = Vec::new();
+        for error in &self.load_errors {
+            collections.push((error.collection, self.storage.load_raw(error.collection)?));
+        }
+
+        let mut skipped = 0;
+        let mut changes = Vec::new();
+        for (collection, records) in collections {
+            let total = records.len();
+            skipped += total - self.keep_valid_records(collection, records);
+            changes.extend(self.ids(collection).into_iter().map(|id| Change::Put(collection, id)));
+        }
+        // Saved as an undoable step, so the audit log shows what was kept
+        self.commit(format!("skip {} invalid records", skipped), &changes)?;
+        self.load_errors.clear();
+        Ok(skipped)
+    }
+
+    // Replace a collection with those of `records` that parse; returns how many were kept
+    fn keep_valid_records(&mut self, collection: Collection, records: Vec<Value>) -> usize {
+        fn valid<T: DeserializeOwned>(records: Vec<Value>) -> Vec<T> {
+            records
+                .into_iter()
+                .filter_map(|record| serde_json::from_value(record).ok())
+                .collect()
+        }
+        match collection {
+            Collection::Students => self.data.students = valid(records),
+            Collection::Teachers => self.data.teachers = valid(records),
+            Collection::Faculties => self.data.faculties = valid(records),
+            Collection::Courses => self.data.courses = valid(records),
+            Collection::Enrollments => self.data.enrollments = valid(records),
+            Collection::Trash => self.data.trash = valid(records),
+        }
+        self.ids(collection).len()
+    }
+
+    fn ids(&self, collection: Collection) -> Vec<String> {
+        match collection {
+            Collection::Students => self.data.students.iter().map(|s| s.id.clone()).collect(),
+            Collection::Teachers => self.data.teachers.iter().map(|t| t.id.clone()).collect(),
+            Collection::Faculties => self.data.faculties.iter().map(|f| f.id.clone()).collect(),
+            Collection::Courses => self.data.courses.iter().map(|c| c.id.clone()).collect(),
+            Collection::Enrollments => self.data.enrollments.iter().map(|e| e.id.clone()).collect(),
+            Collection::Trash => self.data.trash.iter().map(|t| t.id.clone()).collect(),
+        }
+    }
+
+    // Search all entity types at once
+    pub fn search(&self, query: &str) -> SearchResults<'_> {
+        SearchResults {
+            students: self.search_students(query),
+            teachers: self.search_teachers(query),
+            faculties: self.search_faculties(query),
+            courses: self.search_courses(query),
+            audit: self.search_audit(query),
+            trash: self.search_trash(query),
+        }
+    }
+
     // Student methods
     pub fn get_all_students(&self) -> &[Student] {
-        &self.students
+        &self.data.students
     }
 
     pub fn add_student(&mut self, student: Student) -> Result<()> {
-        self.students.push(student);
-        self.save_students()
+        let change = Change::Put(Collection::Students, student.id.clone());
+        let description = format!("add student {}", student.full_name());
+        self.data.students.push(student);
+        self.commit(description, &[change])
     }
 
     pub fn get_student_by_id(&self, id: &str) -> Option<&Student> {
-        self.students.iter().find(|s| s.id == id)
+        self.data.students.iter().find(|s| s.id == id)
     }
 
     pub fn update_student(&mut self, updated_student: Student) -> Result<bool> {
-        if let Some(index) = self.students.iter().position(|s| s.id == updated_student.id) {
-            self.students[index] = updated_student;
-            self.save_students()?;
+        if let Some(index) = self.data.students.iter().position(|s| s.id == updated_student.id) {
+            self.check_version(Collection::Students, &self.data.students[index], &updated_student)?;
+            let change = Change::Put(Collection::Students, updated_student.id.clone());
+            let description = format!("edit student {}", updated_student.full_name());
+            self.data.students[index] = updated_student;
+            self.commit(description, &[change])?;
             Ok(true)
         } else {
             Ok(false)
         }
     }
 
-    pub fn delete_student(&mut self, id: &str) -> Result<bool> {
-        let len_before = self.students.len();
-        self.students.retain(|s| s.id != id);
-        
-        if self.students.len() < len_before {
-            self.save_students()?;
-            Ok(true)
-        } else {
-            Ok(false)
+    pub fn delete_student(&mut self, id: &str, policy: &DeletePolicy) -> Result<bool> {
+        let Some(name) = self.get_student_by_id(id).map(|r| r.full_name()) else {
+            return Ok(false);
+        };
+        self.check_delete(EntityKind::Student, id, policy)?;
+
+        let mut changes = vec![Change::Delete(Collection::Students, id.to_string())];
+        match policy {
+            DeletePolicy::Block => {}
+            DeletePolicy::Cascade => {
+                self.remove_enrollments(|e| e.student_id == id, &mut changes);
+            }
+            DeletePolicy::Reassign(target) => {
+                for enrollment in self.data.enrollments.iter_mut().filter(|e| e.student_id == id) {
+                    enrollment.student_id = target.clone();
+                    changes.push(Change::Put(Collection::Enrollments, enrollment.id.clone()));
+                }
+            }
         }
+
+        self.data.students.retain(|r| r.id != id);
+        let description = format!("delete student {}", name);
+        self.move_to_trash(EntityKind::Student, name, &mut changes);
+        self.commit(description, &changes)?;
+        Ok(true)
     }
 
     pub fn search_students(&self, query: &str) -> Vec<&Student> {
         let query = query.to_lowercase();
-        self.students
+        self.data.students
             .iter()
             .filter(|s| {
                 s.first_name.to_lowercase().contains(&query)
@@ -117,102 +469,192 @@ impl DataManager {
             .collect()
     }
 
-    fn save_students(&self) -> Result<()> {
-        self.save_to_file(&self.students, "students.json")
+    // Credit-weighted GPA over the student's graded enrollments
+    pub fn student_gpa(&self, student_id: &str) -> Option<f32> {
+        let (weighted, credits) = self
+            .enrollments_for_student(student_id)
+            .into_iter()
+            .filter_map(|e| {
+                let points = e.grade.as_ref()?.points()?;
+                let credits = self.get_course_by_id(&e.course_id)?.credits as f32;
+                Some((points * credits, credits))
+            })
+            .fold((0.0, 0.0), |(w, c), (pw, pc)| (w + pw, c + pc));
+
+        if credits > 0.0 {
+            return Some(weighted / credits);
+        }
+        // Students from before enrollments keep the GPA they were given
+        self.get_student_by_id(student_id).and_then(|s| s.recorded_gpa)
     }
 
     // Teacher methods
     pub fn get_all_teachers(&self) -> &[Teacher] {
-        &self.teachers
+        &self.data.teachers
     }
 
     pub fn add_teacher(&mut self, teacher: Teacher) -> Result<()> {
-        self.teachers.push(teacher);
-        self.save_teachers()
+        let change = Change::Put(Collection::Teachers, teacher.id.clone());
+        let description = format!("add teacher {}", teacher.full_name());
+        self.data.teachers.push(teacher);
+        self.commit(description, &[change])
     }
 
     pub fn get_teacher_by_id(&self, id: &str) -> Option<&Teacher> {
-        self.teachers.iter().find(|t| t.id == id)
+        self.data.teachers.iter().find(|t| t.id == id)
     }
 
     pub fn update_teacher(&mut self, updated_teacher: Teacher) -> Result<bool> {
-        if let Some(index) = self.teachers.iter().position(|t| t.id == updated_teacher.id) {
-            self.teachers[index] = updated_teacher;
-            self.save_teachers()?;
+        if let Some(index) = self.data.teachers.iter().position(|t| t.id == updated_teacher.id) {
+            self.check_version(Collection::Teachers, &self.data.teachers[index], &updated_teacher)?;
+            let change = Change::Put(Collection::Teachers, updated_teacher.id.clone());
+            let description = format!("edit teacher {}", updated_teacher.full_name());
+            self.data.teachers[index] = updated_teacher;
+            self.commit(description, &[change])?;
             Ok(true)
         } else {
             Ok(false)
         }
     }
 
-    pub fn delete_teacher(&mut self, id: &str) -> Result<bool> {
-        let len_before = self.teachers.len();
-        self.teachers.retain(|t| t.id != id);
-        
-        if self.teachers.len() < len_before {
-            self.save_teachers()?;
-            Ok(true)
-        } else {
-            Ok(false)
+    pub fn delete_teacher(&mut self, id: &str, policy: &DeletePolicy) -> Result<bool> {
+        let Some(name) = self.get_teacher_by_id(id).map(|r| r.full_name()) else {
+            return Ok(false);
+        };
+        self.check_delete(EntityKind::Teacher, id, policy)?;
+
+        let mut changes = vec![Change::Delete(Collection::Teachers, id.to_string())];
+        match policy {
+            DeletePolicy::Block => {}
+            DeletePolicy::Cascade => {
+                for course in self.data.courses.iter_mut().filter(|c| c.teacher_ids.iter().any(|t| t == id)) {
+                    course.teacher_ids.retain(|t| t != id);
+                    changes.push(Change::Put(Collection::Courses, course.id.clone()));
+                }
+            }
+            DeletePolicy::Reassign(target) => {
+                for course in &mut self.data.courses {
+                    if let Some(pos) = course.teacher_ids.iter().position(|t| t == id) {
+                        // Avoid listing the target twice if it already teaches the course
+                        if course.teacher_ids.contains(target) {
+                            course.teacher_ids.remove(pos);
+                        } else {
+                            course.teacher_ids[pos] = target.clone();
+                        }
+                        changes.push(Change::Put(Collection::Courses, course.id.clone()));
+                    }
+                }
+            }
         }
+
+        self.data.teachers.retain(|r| r.id != id);
+        let description = format!("delete teacher {}", name);
+        self.move_to_trash(EntityKind::Teacher, name, &mut changes);
+        self.commit(description, &changes)?;
+        Ok(true)
     }
 
     pub fn search_teachers(&self, query: &str) -> Vec<&Teacher> {
         let query = query.to_lowercase();
-        self.teachers
+        self.data.teachers
             .iter()
             .filter(|t| {
                 t.first_name.to_lowercase().contains(&query)
                     || t.last_name.to_lowercase().contains(&query)
-                    || t.department.to_lowercase().contains(&query)
+                    || self
+                        .get_faculty_by_id(&t.faculty_id)
+                        .is_some_and(|f| f.name.to_lowercase().contains(&query))
                     || t.title.to_lowercase().contains(&query)
             })
             .collect()
     }
 
-    fn save_teachers(&self) -> Result<()> {
-        self.save_to_file(&self.teachers, "teachers.json")
-    }
-
     // Faculty methods
     pub fn get_all_faculties(&self) -> &[Faculty] {
-        &self.faculties
+        &self.data.faculties
     }
 
     pub fn add_faculty(&mut self, faculty: Faculty) -> Result<()> {
-        self.faculties.push(faculty);
-        self.save_faculties()
+        let change = Change::Put(Collection::Faculties, faculty.id.clone());
+        let description = format!("add faculty {}", faculty.name);
+        self.data.faculties.push(faculty);
+        self.commit(description, &[change])
     }
 
     pub fn get_faculty_by_id(&self, id: &str) -> Option<&Faculty> {
-        self.faculties.iter().find(|f| f.id == id)
+        self.data.faculties.iter().find(|f| f.id == id)
     }
 
     pub fn update_faculty(&mut self, updated_faculty: Faculty) -> Result<bool> {
-        if let Some(index) = self.faculties.iter().position(|f| f.id == updated_faculty.id) {
-            self.faculties[index] = updated_faculty;
-            self.save_faculties()?;
+        if let Some(index) = self.data.faculties.iter().position(|f| f.id == updated_faculty.id) {
+            self.check_version(Collection::Faculties, &self.data.faculties[index], &updated_faculty)?;
+            let change = Change::Put(Collection::Faculties, updated_faculty.id.clone());
+            let description = format!("edit faculty {}", updated_faculty.name);
+            self.data.faculties[index] = updated_faculty;
+            self.commit(description, &[change])?;
             Ok(true)
         } else {
             Ok(false)
         }
     }
 
-    pub fn delete_faculty(&mut self, id: &str) -> Result<bool> {
-        let len_before = self.faculties.len();
-        self.faculties.retain(|f| f.id != id);
-        
-        if self.faculties.len() < len_before {
-            self.save_faculties()?;
-            Ok(true)
-        } else {
-            Ok(false)
+    pub fn delete_faculty(&mut self, id: &str, policy: &DeletePolicy) -> Result<bool> {
+        let Some(name) = self.get_faculty_by_id(id).map(|r| r.name.clone()) else {
+            return Ok(false);
+        };
+        self.check_delete(EntityKind::Faculty, id, policy)?;
+
+        let mut changes = vec![Change::Delete(Collection::Faculties, id.to_string())];
+        match policy {
+            DeletePolicy::Block => {}
+            DeletePolicy::Cascade => {
+                let teacher_ids: Vec<String> = self.data.teachers
+                    .iter()
+                    .filter(|t| t.faculty_id == id)
+                    .map(|t| t.id.clone())
+                    .collect();
+                let course_ids: Vec<String> = self.data.courses
+                    .iter()
+                    .filter(|c| c.faculty_id == id)
+                    .map(|c| c.id.clone())
+                    .collect();
+                changes.extend(teacher_ids.iter().map(|t| Change::Delete(Collection::Teachers, t.clone())));
+                changes.extend(course_ids.iter().map(|c| Change::Delete(Collection::Courses, c.clone())));
+                self.data.teachers.retain(|t| t.faculty_id != id);
+                self.data.courses.retain(|c| c.faculty_id != id);
+
+                // Courses of other faculties lose the deleted teachers
+                for course in &mut self.data.courses {
+                    let before = course.teacher_ids.len();
+                    course.teacher_ids.retain(|t| !teacher_ids.contains(t));
+                    if course.teacher_ids.len() < before {
+                        changes.push(Change::Put(Collection::Courses, course.id.clone()));
+                    }
+                }
+                self.remove_enrollments(|e| course_ids.contains(&e.course_id), &mut changes);
+            }
+            DeletePolicy::Reassign(target) => {
+                for teacher in self.data.teachers.iter_mut().filter(|t| t.faculty_id == id) {
+                    teacher.faculty_id = target.clone();
+                    changes.push(Change::Put(Collection::Teachers, teacher.id.clone()));
+                }
+                for course in self.data.courses.iter_mut().filter(|c| c.faculty_id == id) {
+                    course.faculty_id = target.clone();
+                    changes.push(Change::Put(Collection::Courses, course.id.clone()));
+                }
+            }
         }
+
+        self.data.faculties.retain(|r| r.id != id);
+        let description = format!("delete faculty {}", name);
+        self.move_to_trash(EntityKind::Faculty, name, &mut changes);
+        self.commit(description, &changes)?;
+        Ok(true)
     }
 
     pub fn search_faculties(&self, query: &str) -> Vec<&Faculty> {
         let query = query.to_lowercase();
-        self.faculties
+        self.data.faculties
             .iter()
             .filter(|f| {
                 f.name.to_lowercase().contains(&query)
@@ -222,7 +664,530 @@ impl DataManager {
             .collect()
     }
 
-    fn save_faculties(&self) -> Result<()> {
-        self.save_to_file(&self.faculties, "faculties.json")
+    // Course methods
+    pub fn get_all_courses(&self) -> &[Course] {
+        &self.data.courses
+    }
+
+    pub fn add_course(&mut self, course: Course) -> Result<()> {
+        let change = Change::Put(Collection::Courses, course.id.clone());
+        let description = format!("add course {}", course.code);
+        self.data.courses.push(course);
+        self.commit(description, &[change])
+    }
+
+    pub fn get_course_by_id(&self, id: &str) -> Option<&Course> {
+        self.data.courses.iter().find(|c| c.id == id)
+    }
+
+    pub fn update_course(&mut self, updated_course: Course) -> Result<bool> {
+        if let Some(index) = self.data.courses.iter().position(|c| c.id == updated_course.id) {
+            self.check_version(Collection::Courses, &self.data.courses[index], &updated_course)?;
+            let change = Change::Put(Collection::Courses, updated_course.id.clone());
+            let description = format!("edit course {}", updated_course.code);
+            self.data.courses[index] = updated_course;
+            self.commit(description, &[change])?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    pub fn delete_course(&mut self, id: &str, policy: &DeletePolicy) -> Result<bool> {
+        let Some(name) = self.get_course_by_id(id).map(|r| r.code.clone()) else {
+            return Ok(false);
+        };
+        self.check_delete(EntityKind::Course, id, policy)?;
+
+        let mut changes = vec![Change::Delete(Collection::Courses, id.to_string())];
+        match policy {
+            DeletePolicy::Block => {}
+            DeletePolicy::Cascade => {
+                self.remove_enrollments(|e| e.course_id == id, &mut changes);
+            }
+            DeletePolicy::Reassign(target) => {
+                for enrollment in self.data.enrollments.iter_mut().filter(|e| e.course_id == id) {
+                    enrollment.course_id = target.clone();
+                    changes.push(Change::Put(Collection::Enrollments, enrollment.id.clone()));
+                }
+            }
+        }
+
+        self.data.courses.retain(|r| r.id != id);
+        let description = format!("delete course {}", name);
+        self.move_to_trash(EntityKind::Course, name, &mut changes);
+        self.commit(description, &changes)?;
+        Ok(true)
+    }
+
+    pub fn search_courses(&self, query: &str) -> Vec<&Course> {
+        let query = query.to_lowercase();
+        self.data.courses
+            .iter()
+            .filter(|c| {
+                c.code.to_lowercase().contains(&query)
+                    || c.title.to_lowercase().contains(&query)
+            })
+            .collect()
+    }
+
+    // Relationship queries
+    pub fn teachers_in_faculty(&self, faculty_id: &str) -> Vec<&Teacher> {
+        self.data.teachers.iter().filter(|t| t.faculty_id == faculty_id).collect()
+    }
+
+    pub fn courses_in_faculty(&self, faculty_id: &str) -> Vec<&Course> {
+        self.data.courses.iter().filter(|c| c.faculty_id == faculty_id).collect()
+    }
+
+    pub fn courses_taught_by(&self, teacher_id: &str) -> Vec<&Course> {
+        self.data.courses
+            .iter()
+            .filter(|c| c.teacher_ids.iter().any(|t| t == teacher_id))
+            .collect()
+    }
+
+    pub fn enrollments_for_course(&self, course_id: &str) -> Vec<&Enrollment> {
+        self.data.enrollments.iter().filter(|e| e.course_id == course_id).collect()
+    }
+
+    // Students enrolled in at least one of the faculty's courses
+    pub fn students_in_faculty(&self, faculty_id: &str) -> Vec<&Student> {
+        let enrolled: Vec<&str> = self
+            .courses_in_faculty(faculty_id)
+            .iter()
+            .flat_map(|c| self.enrollments_for_course(&c.id))
+            .map(|e| e.student_id.as_str())
+            .collect();
+        self.data.students.iter().filter(|s| enrolled.contains(&s.id.as_str())).collect()
+    }
+
+    // Human-readable descriptions of the records that reference an entity
+    pub fn dependents(&self, kind: EntityKind, id: &str) -> Vec<String> {
+        let course_label = |c: &&Course| format!("Course: {} {}", c.code, c.title);
+        match kind {
+            EntityKind::Student => self
+                .enrollments_for_student(id)
+                .iter()
+                .map(|e| {
+                    let course = self.get_course_by_id(&e.course_id).map(|c| c.code.clone()).unwrap_or_default();
+                    format!("Enrollment: {} ({})", course, e.term)
+                })
+                .collect(),
+            EntityKind::Teacher => self.courses_taught_by(id).iter().map(course_label).collect(),
+            EntityKind::Faculty => {
+                let teachers = self
+                    .teachers_in_faculty(id)
+                    .into_iter()
+                    .map(|t| format!("Teacher: {}", t.full_name()));
+                let courses = self.courses_in_faculty(id);
+                teachers.chain(courses.iter().map(course_label)).collect()
+            }
+            EntityKind::Course => self
+                .enrollments_for_course(id)
+                .iter()
+                .map(|e| {
+                    let student = self.get_student_by_id(&e.student_id).map(|s| s.full_name()).unwrap_or_default();
+                    format!("Enrollment: {} ({})", student, e.term)
+                })
+                .collect(),
+        }
+    }
+
+    fn exists(&self, kind: EntityKind, id: &str) -> bool {
+        match kind {
+            EntityKind::Student => self.get_student_by_id(id).is_some(),
+            EntityKind::Teacher => self.get_teacher_by_id(id).is_some(),
+            EntityKind::Faculty => self.get_faculty_by_id(id).is_some(),
+            EntityKind::Course => self.get_course_by_id(id).is_some(),
+        }
+    }
+
+    // Make sure a delete with the given policy leaves no dangling references
+    fn check_delete(&self, kind: EntityKind, id: &str, policy: &DeletePolicy) -> Result<()> {
+        match policy {
+            DeletePolicy::Block => {
+                let count = self.dependents(kind, id).len();
+                if count > 0 {
+                    bail!("{} record(s) still depend on this {}", count, kind);
+                }
+            }
+            DeletePolicy::Cascade => {}
+            DeletePolicy::Reassign(target) => {
+                if target == id || !self.exists(kind, target) {
+                    bail!("Cannot reassign to a missing {}", kind);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Keep the records deleted by `changes` in a new trash item, whose creation is added to
+    // `changes`. They come from the last saved data, which still holds them.
+    fn move_to_trash(&mut self, kind: EntityKind, name: String, changes: &mut Vec<Change>) {
+        fn saved<T: Clone>(records: &[T], id: &str, key: fn(&T) -> &str) -> Option<T> {
+            records.iter().find(|r| key(r) == id).cloned()
+        }
+        let mut records = TrashedRecords::default();
+        for change in changes.iter() {
+            let Change::Delete(collection, id) = change else {
+                continue;
+            };
+            match collection {
+                Collection::Students => records.students.extend(saved(&self.saved.students, id, |s| &s.id)),
+                Collection::Teachers => records.teachers.extend(saved(&self.saved.teachers, id, |t| &t.id)),
+                Collection::Faculties => records.faculties.extend(saved(&self.saved.faculties, id, |f| &f.id)),
+                Collection::Courses => records.courses.extend(saved(&self.saved.courses, id, |c| &c.id)),
+                Collection::Enrollments => records.enrollments.extend(saved(&self.saved.enrollments, id, |e| &e.id)),
+                Collection::Trash => {}
+            }
+        }
+        let item = TrashItem::new(kind, name, records);
+        changes.push(Change::Put(Collection::Trash, item.id.clone()));
+        self.data.trash.push(item);
+    }
+
+    // Trash methods
+    pub fn trash_items(&self) -> &[TrashItem] {
+        &self.data.trash
+    }
+
+    pub fn get_trash_item(&self, id: &str) -> Option<&TrashItem> {
+        self.data.trash.iter().find(|t| t.id == id)
+    }
+
+    // Newest first
+    pub fn search_trash(&self, query: &str) -> Vec<&TrashItem> {
+        let query = query.to_lowercase();
+        self.data.trash
+            .iter()
+            .rev()
+            .filter(|t| t.name.to_lowercase().contains(&query) || t.kind.to_string().contains(&query))
+            .collect()
+    }
+
+    // Put every record of a trash item back. Fails if a record it needs, such as a
+    // teacher's faculty, has been deleted since; teachers missing from a course are left out.
+    pub fn restore_from_trash(&mut self, id: &str) -> Result<bool> {
+        let Some(index) = self.data.trash.iter().position(|t| t.id == id) else {
+            return Ok(false);
+        };
+        let item = self.data.trash[index].clone();
+        let records = &item.records;
+        let faculty_exists = |id: &str| self.exists(EntityKind::Faculty, id) || records.faculties.iter().any(|f| f.id == id);
+        let student_exists = |id: &str| self.exists(EntityKind::Student, id) || records.students.iter().any(|s| s.id == id);
+        let course_exists = |id: &str| self.exists(EntityKind::Course, id) || records.courses.iter().any(|c| c.id == id);
+        let missing = if !records.teachers.iter().all(|t| faculty_exists(&t.faculty_id))
+            || !records.courses.iter().all(|c| faculty_exists(&c.faculty_id))
+        {
+            Some("faculty")
+        } else if !records.enrollments.iter().all(|e| student_exists(&e.student_id)) {
+            Some("student")
+        } else if !records.enrollments.iter().all(|e| course_exists(&e.course_id)) {
+            Some("course")
+        } else {
+            None
+        };
+        if let Some(missing) = missing {
+            bail!("it needs a {} that has been deleted since; restore that first", missing);
+        }
+
+        let mut changes = vec![Change::Delete(Collection::Trash, item.id.clone())];
+        changes.extend(records.students.iter().map(|s| Change::Put(Collection::Students, s.id.clone())));
+        changes.extend(records.teachers.iter().map(|t| Change::Put(Collection::Teachers, t.id.clone())));
+        changes.extend(records.faculties.iter().map(|f| Change::Put(Collection::Faculties, f.id.clone())));
+        changes.extend(records.courses.iter().map(|c| Change::Put(Collection::Courses, c.id.clone())));
+        changes.extend(records.enrollments.iter().map(|e| Change::Put(Collection::Enrollments, e.id.clone())));
+        self.data.students.extend(records.students.iter().cloned());
+        self.data.teachers.extend(records.teachers.iter().cloned());
+        self.data.faculties.extend(records.faculties.iter().cloned());
+        for course in &records.courses {
+            let mut course = course.clone();
+            course.teacher_ids.retain(|t| self.data.teachers.iter().any(|teacher| teacher.id == *t));
+            self.data.courses.push(course);
+        }
+        self.data.enrollments.extend(records.enrollments.iter().cloned());
+        self.data.trash.remove(index);
+        self.commit(format!("restore {} {}", item.kind, item.name), &changes)?;
+        Ok(true)
+    }
+
+    // Delete a trash item for good
+    pub fn purge_from_trash(&mut self, id: &str) -> Result<bool> {
+        let Some(item) = self.data.trash.iter().find(|t| t.id == id) else {
+            return Ok(false);
+        };
+        let description = format!("purge {} {}", item.kind, item.name);
+        self.data.trash.retain(|t| t.id != id);
+        self.commit(description, &[Change::Delete(Collection::Trash, id.to_string())])?;
+        Ok(true)
+    }
+
+    // Purge trash items older than `days` days (never when 0); returns how many went
+    pub fn purge_expired_trash(&mut self, days: u32) -> Result<usize> {
+        let changes: Vec<Change> = self.data.trash
+            .iter()
+            .filter(|t| t.is_expired(days))
+            .map(|t| Change::Delete(Collection::Trash, t.id.clone()))
+            .collect();
+        if changes.is_empty() {
+            return Ok(0);
+        }
+        self.data.trash.retain(|t| !t.is_expired(days));
+        self.commit(format!("purge {} expired trash item(s)", changes.len()), &changes)?;
+        Ok(changes.len())
+    }
+
+    // Delete the enrollments matching `remove`, recording each deletion
+    fn remove_enrollments(&mut self, remove: impl Fn(&Enrollment) -> bool, changes: &mut Vec<Change>) {
+        changes.extend(
+            self.data.enrollments
+                .iter()
+                .filter(|e| remove(e))
+                .map(|e| Change::Delete(Collection::Enrollments, e.id.clone())),
+        );
+        self.data.enrollments.retain(|e| !remove(e));
+    }
+
+    // Enrollment methods
+    pub fn get_all_enrollments(&self) -> &[Enrollment] {
+        &self.data.enrollments
+    }
+
+    pub fn enrollments_for_student(&self, student_id: &str) -> Vec<&Enrollment> {
+        self.data.enrollments
+            .iter()
+            .filter(|e| e.student_id == student_id)
+            .collect()
+    }
+
+    pub fn add_enrollment(&mut self, enrollment: Enrollment) -> Result<()> {
+        let change = Change::Put(Collection::Enrollments, enrollment.id.clone());
+        let (student, course) = self.enrollment_names(&enrollment);
+        let description = format!("enroll {} in {}", student, course);
+        self.data.enrollments.push(enrollment);
+        self.commit(description, &[change])
     }
-}
\ No newline at end of file
+
+    // Student name and course code of an enrollment, for describing changes to it
+    fn enrollment_names(&self, enrollment: &Enrollment) -> (String, String) {
+        let student = self.get_student_by_id(&enrollment.student_id).map(|s| s.full_name()).unwrap_or_default();
+        let course = self.get_course_by_id(&enrollment.course_id).map(|c| c.code.clone()).unwrap_or_default();
+        (student, course)
+    }
+
+    pub fn get_enrollment_by_id(&self, id: &str) -> Option<&Enrollment> {
+        self.data.enrollments.iter().find(|e| e.id == id)
+    }
+
+    pub fn update_enrollment(&mut self, updated_enrollment: Enrollment) -> Result<bool> {
+        if let Some(index) = self.data.enrollments.iter().position(|e| e.id == updated_enrollment.id) {
+            self.check_version(Collection::Enrollments, &self.data.enrollments[index], &updated_enrollment)?;
+            let change = Change::Put(Collection::Enrollments, updated_enrollment.id.clone());
+            let (student, course) = self.enrollment_names(&updated_enrollment);
+            let description = format!("edit grade of {} in {}", student, course);
+            self.data.enrollments[index] = updated_enrollment;
+            self.commit(description, &[change])?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    pub fn delete_enrollment(&mut self, id: &str) -> Result<bool> {
+        let Some((student, course)) = self.get_enrollment_by_id(id).map(|e| self.enrollment_names(e)) else {
+            return Ok(false);
+        };
+        self.data.enrollments.retain(|e| e.id != id);
+        let description = format!("remove {} from {}", student, course);
+        self.commit(description, &[Change::Delete(Collection::Enrollments, id.to_string())])?;
+        Ok(true)
+    }
+}
+
+// Load every collection, failing instead of replacing the data with empty lists
+fn load_readable(storage: &mut dyn Storage) -> Result<Dataset> {
+    let (data, errors) = storage.load()?;
+    if let Some(error) = errors.first() {
+        bail!("{} could not be read: {}", error.location, error.message);
+    }
+    Ok(data)
+}
+
+// Count one more save of each record being written, on top of the newer of its version
+// in memory and as last saved, which may have come in from another instance
+fn bump_versions(data: &mut Dataset, saved: &Dataset, changes: &[Change]) {
+    fn bump<T: Versioned>(records: &mut [T], saved: &[T], id: &str) {
+        let saved_version = saved.iter().find(|r| r.id() == id).map_or(0, |r| r.version());
+        if let Some(record) = records.iter_mut().find(|r| r.id() == id) {
+            record.set_version(record.version().max(saved_version) + 1);
+        }
+    }
+    for change in changes {
+        let Change::Put(collection, id) = change else {
+            continue;
+        };
+        match collection {
+            Collection::Students => bump(&mut data.students, &saved.students, id),
+            Collection::Teachers => bump(&mut data.teachers, &saved.teachers, id),
+            Collection::Faculties => bump(&mut data.faculties, &saved.faculties, id),
+            Collection::Courses => bump(&mut data.courses, &saved.courses, id),
+            Collection::Enrollments => bump(&mut data.enrollments, &saved.enrollments, id),
+            // Trashed records keep the version they were deleted at
+            Collection::Trash => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Grade;
+    use crate::storage::json::JsonStorage;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    // A data directory of its own for each test, removed when the test ends
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            Self(std::env::temp_dir().join(format!("university-manager-test-{}", uuid::Uuid::new_v4())))
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn open(data_dir: &Path) -> DataManager {
+        open_keeping(data_dir, 3)
+    }
+
+    // Open with `backup_count` backups kept of each file
+    fn open_keeping(data_dir: &Path, backup_count: usize) -> DataManager {
+        let storage = Box::new(JsonStorage::new(data_dir, backup_count).unwrap());
+        let audit = AuditLog::open(data_dir).unwrap();
+        let lock = DataLock::open(data_dir).unwrap();
+        DataManager::new(storage, audit, lock, Rules::default()).unwrap()
+    }
+
+    fn course(dm: &mut DataManager, code: &str, credits: u32) -> String {
+        let course = Course::new(code.to_string(), code.to_string(), credits, String::new(), Vec::new());
+        let id = course.id.clone();
+        dm.add_course(course).unwrap();
+        id
+    }
+
+    fn student(dm: &mut DataManager) -> String {
+        let student = Student::new("Ann".to_string(), "Lee".to_string(), 20, "Physics".to_string());
+        let id = student.id.clone();
+        dm.add_student(student).unwrap();
+        id
+    }
+
+    #[test]
+    fn gpa_is_the_credit_weighted_average_of_graded_enrollments() {
+        let dir = TempDir::new();
+        let mut dm = open(&dir.0);
+        let student_id = student(&mut dm);
+        let physics = course(&mut dm, "PHY1", 3);
+        let history = course(&mut dm, "HIS1", 1);
+        let biology = course(&mut dm, "BIO1", 5);
+        assert_eq!(dm.student_gpa(&student_id), None);
+
+        dm.add_enrollment(Enrollment::new(student_id.clone(), physics, "2026F".to_string(), Grade::parse("A"))).unwrap();
+        dm.add_enrollment(Enrollment::new(student_id.clone(), history, "2026F".to_string(), Grade::parse("2.0"))).unwrap();
+        // Ungraded enrollments don't count
+        dm.add_enrollment(Enrollment::new(student_id.clone(), biology, "2026F".to_string(), None)).unwrap();
+
+        assert_eq!(dm.student_gpa(&student_id), Some(3.5));
+    }
+
+    #[test]
+    fn recorded_gpa_is_used_until_a_grade_is_awarded() {
+        let dir = TempDir::new();
+        let mut dm = open(&dir.0);
+        let student = Student { recorded_gpa: Some(3.2), ..Student::new("Ann".to_string(), "Lee".to_string(), 20, "Physics".to_string()) };
+        let student_id = student.id.clone();
+        dm.add_student(student).unwrap();
+        let physics = course(&mut dm, "PHY1", 3);
+        assert_eq!(dm.student_gpa(&student_id), Some(3.2));
+
+        let mut enrollment = Enrollment::new(student_id.clone(), physics, "2026F".to_string(), None);
+        dm.add_enrollment(enrollment.clone()).unwrap();
+        assert_eq!(dm.student_gpa(&student_id), Some(3.2));
+
+        enrollment.version = dm.get_enrollment_by_id(&enrollment.id).unwrap().version;
+        enrollment.grade = Grade::parse("B");
+        assert!(dm.update_enrollment(enrollment).unwrap());
+        assert_eq!(dm.student_gpa(&student_id), Some(3.0));
+    }
+
+    #[test]
+    fn enrollments_are_saved_and_loaded_again() {
+        let dir = TempDir::new();
+        let mut dm = open(&dir.0);
+        let student_id = student(&mut dm);
+        let physics = course(&mut dm, "PHY1", 3);
+        let history = course(&mut dm, "HIS1", 1);
+        let kept = Enrollment::new(student_id.clone(), physics, "2026F".to_string(), Grade::parse("B+"));
+        let removed = Enrollment::new(student_id.clone(), history, "2026F".to_string(), None);
+        dm.add_enrollment(kept.clone()).unwrap();
+        dm.add_enrollment(removed.clone()).unwrap();
+        assert!(dm.delete_enrollment(&removed.id).unwrap());
+        assert!(!dm.delete_enrollment(&removed.id).unwrap());
+        drop(dm);
+
+        let dm = open(&dir.0);
+        let enrollments = dm.enrollments_for_student(&student_id);
+        assert_eq!(enrollments.len(), 1);
+        assert_eq!(enrollments[0].id, kept.id);
+        assert_eq!(enrollments[0].grade, Some(Grade::Letter("B+".to_string())));
+    }
+
+    #[test]
+    fn older_files_keep_the_typed_in_gpa() {
+        let dir = TempDir::new();
+        fs::create_dir_all(&dir.0).unwrap();
+        let json = r#"[{"id": "s1", "first_name": "Ann", "last_name": "Lee", "age": 20, "major": "Physics", "gpa": 3.7}]"#;
+        fs::write(dir.0.join("students.json"), json).unwrap();
+
+        let mut dm = open(&dir.0);
+        assert_eq!(dm.student_gpa("s1"), Some(3.7));
+        let mut student = dm.get_student_by_id("s1").unwrap().clone();
+        student.age = 21;
+        dm.update_student(student).unwrap();
+        drop(dm);
+
+        let saved = fs::read_to_string(dir.0.join("students.json")).unwrap();
+        assert!(saved.contains("\"gpa\": 3.7"));
+    }
+
+    #[test]
+    fn restores_the_newest_of_the_configured_backups() {
+        let dir = TempDir::new();
+        let mut dm = open_keeping(&dir.0, 2);
+        for code in ["PHY1", "HIS1", "BIO1", "ART1"] {
+            course(&mut dm, code, 3);
+        }
+        drop(dm);
+        assert!(dir.0.join("courses.json.2.bak").exists());
+        assert!(!dir.0.join("courses.json.3.bak").exists());
+
+        fs::write(dir.0.join("courses.json"), "not json").unwrap();
+        let mut dm = open_keeping(&dir.0, 2);
+        assert_eq!(dm.load_errors().len(), 1);
+        dm.restore_backups().unwrap();
+
+        // The newest backup is from before the last course was added
+        assert!(dm.load_errors().is_empty());
+        assert_eq!(dm.get_all_courses().len(), 3);
+        assert_eq!(fs::read_to_string(dir.0.join("courses.json.corrupt")).unwrap(), "not json");
+        assert!(!dir.0.join("courses.json.tmp").exists());
+        // Restoring doesn't rotate the damaged file into the backups
+        assert_ne!(fs::read_to_string(dir.0.join("courses.json.1.bak")).unwrap(), "not json");
+    }
+}
@@ -0,0 +1,278 @@
+use crate::data_manager::DataManager;
+use crate::rules::{Limit, Rules};
+use crate::keymap::Action;
+use crate::widgets::{self, DropdownState, TextInput};
+use ratatui::style::Color;
+
+// Form schemas: which fields each record's form has, in order, and how every field is
+// labelled, edited and checked. Rendering, mouse hit-testing, input filtering and
+// parsing in modal.rs all work from these tables.
+
+// Identifies a field across forms, e.g. for setting values from the command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputField {
+    FirstName,
+    LastName,
+    Age,
+    Major,
+    Title,
+    Name,
+    Building,
+    HeadName,
+    EstablishedYear,
+    NumStaff,
+    Code,
+    Credits,
+    Faculty,
+    Teachers,
+    Course,
+    Term,
+    Grade,
+}
+
+// How a field is edited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Widget {
+    // Any text
+    Text,
+    // Digits only
+    Number,
+    // A grade on the configured scale: letters with +/-, or digits with one decimal point
+    Grade,
+    // One option picked from a dropdown
+    Choice(Options),
+    // Several options toggled in a dropdown, stored comma-separated
+    MultiChoice(Options),
+}
+
+// Where a dropdown's options come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Options {
+    Majors,
+    Faculties,
+    Teachers,
+    Courses,
+}
+
+impl Options {
+    // Faculties, teachers and courses are stored by id but shown by name
+    pub fn dropdown(&self, data_manager: &DataManager, multi: bool) -> DropdownState {
+        let (labels, values): (Vec<String>, Vec<String>) = match self {
+            Options::Majors => widgets::MAJORS.iter().map(|&m| (m.to_string(), m.to_string())).unzip(),
+            Options::Faculties => data_manager.get_all_faculties().iter()
+                .map(|f| (f.name.clone(), f.id.clone())).unzip(),
+            Options::Teachers => data_manager.get_all_teachers().iter()
+                .map(|t| (t.full_name(), t.id.clone())).unzip(),
+            Options::Courses => data_manager.get_all_courses().iter()
+                .map(|c| (format!("{} {}", c.code, c.title), c.id.clone())).unzip(),
+        };
+        if multi {
+            DropdownState::multi(labels, values)
+        } else {
+            DropdownState::with_values(labels, values)
+        }
+    }
+}
+
+// What a field's value must be for the form to save
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    Required,
+    Optional,
+    // A whole number within the configured bounds, inclusive
+    Range(Limit),
+    // Empty, or a grade on the configured grading scale
+    OptionalGrade,
+}
+
+impl Rule {
+    pub fn check(&self, value: &str, rules: &Rules) -> Result<(), String> {
+        match self {
+            Rule::Required if value.is_empty() => Err("Required".to_string()),
+            Rule::Required | Rule::Optional => Ok(()),
+            Rule::Range(limit) => {
+                let (min, max) = rules.range(*limit);
+                match value.parse::<u32>() {
+                    _ if value.is_empty() => Err("Required".to_string()),
+                    Ok(n) if (min..=max).contains(&n) => Ok(()),
+                    _ if max == u32::MAX => Err(format!("Must be at least {}", min)),
+                    _ => Err(format!("Must be between {} and {}", min, max)),
+                }
+            }
+            Rule::OptionalGrade => {
+                let scale = rules.grading_scale;
+                match value.is_empty() || scale.parse(value).is_some() {
+                    true => Ok(()),
+                    false => Err(format!("Must be {}", scale.expected())),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FieldSpec {
+    pub field: InputField,
+    pub label: &'static str,
+    pub widget: Widget,
+    pub rule: Rule,
+}
+
+const fn field(field: InputField, label: &'static str, widget: Widget, rule: Rule) -> FieldSpec {
+    FieldSpec { field, label, widget, rule }
+}
+
+#[derive(Debug)]
+pub struct FormSchema {
+    // Record name in the title, as in "Add Student"
+    pub name: &'static str,
+    pub color: Color,
+    pub fields: &'static [FieldSpec],
+}
+
+pub const STUDENT_FORM: FormSchema = FormSchema {
+    name: "Student",
+    color: Color::Green,
+    fields: &[
+        field(InputField::FirstName, "First Name", Widget::Text, Rule::Required),
+        field(InputField::LastName, "Last Name", Widget::Text, Rule::Required),
+        field(InputField::Age, "Age", Widget::Number, Rule::Range(Limit::StudentAge)),
+        field(InputField::Major, "Major", Widget::Choice(Options::Majors), Rule::Required),
+    ],
+};
+
+pub const TEACHER_FORM: FormSchema = FormSchema {
+    name: "Teacher",
+    color: Color::Blue,
+    fields: &[
+        field(InputField::FirstName, "First Name", Widget::Text, Rule::Required),
+        field(InputField::LastName, "Last Name", Widget::Text, Rule::Required),
+        field(InputField::Age, "Age", Widget::Number, Rule::Range(Limit::TeacherAge)),
+        field(InputField::Faculty, "Faculty", Widget::Choice(Options::Faculties), Rule::Required),
+        field(InputField::Title, "Title", Widget::Text, Rule::Required),
+    ],
+};
+
+pub const FACULTY_FORM: FormSchema = FormSchema {
+    name: "Faculty",
+    color: Color::Magenta,
+    fields: &[
+        field(InputField::Name, "Name", Widget::Text, Rule::Required),
+        field(InputField::Building, "Building", Widget::Text, Rule::Required),
+        field(InputField::HeadName, "Head Name", Widget::Text, Rule::Required),
+        field(InputField::EstablishedYear, "Established Year", Widget::Number, Rule::Range(Limit::EstablishedYear)),
+        field(InputField::NumStaff, "Number of Staff", Widget::Number, Rule::Range(Limit::NumStaff)),
+    ],
+};
+
+pub const COURSE_FORM: FormSchema = FormSchema {
+    name: "Course",
+    color: Color::LightYellow,
+    fields: &[
+        field(InputField::Code, "Code", Widget::Text, Rule::Required),
+        field(InputField::Title, "Title", Widget::Text, Rule::Required),
+        field(InputField::Credits, "Credits", Widget::Number, Rule::Range(Limit::Credits)),
+        field(InputField::Faculty, "Faculty", Widget::Choice(Options::Faculties), Rule::Required),
+        field(InputField::Teachers, "Teachers", Widget::MultiChoice(Options::Teachers), Rule::Optional),
+    ],
+};
+
+pub const ENROLLMENT_FORM: FormSchema = FormSchema {
+    name: "Enrollment",
+    color: Color::Green,
+    fields: &[
+        field(InputField::Course, "Course", Widget::Choice(Options::Courses), Rule::Required),
+        field(InputField::Term, "Term", Widget::Text, Rule::Required),
+        field(InputField::Grade, "Grade", Widget::Grade, Rule::OptionalGrade),
+    ],
+};
+
+// A field of an open form: its schema entry, current value and dropdown if it has one
+pub struct FormField {
+    pub spec: &'static FieldSpec,
+    pub input: TextInput,
+    pub dropdown: Option<DropdownState>,
+    // Set once the user has edited or left the field; errors only show from then on
+    pub touched: bool,
+}
+
+impl FormField {
+    pub fn new(spec: &'static FieldSpec, value: String, data_manager: &DataManager) -> Self {
+        let mut dropdown = match spec.widget {
+            Widget::Choice(options) => Some(options.dropdown(data_manager, false)),
+            Widget::MultiChoice(options) => Some(options.dropdown(data_manager, true)),
+            _ => None,
+        };
+        // Start a single-choice dropdown on the current value
+        if let Some(dropdown) = &mut dropdown
+            && !dropdown.multi_select
+        {
+            dropdown.select_by_value(&value);
+        }
+        Self { spec, input: TextInput::new(value), dropdown, touched: false }
+    }
+
+    pub fn value(&self) -> &str {
+        self.input.value()
+    }
+
+    // Type a character at the cursor, if the widget takes it
+    pub fn type_char(&mut self, c: char) {
+        self.touched = true;
+        match self.spec.widget {
+            Widget::Text => self.input.insert(c),
+            Widget::Number => {
+                if c.is_ascii_digit() {
+                    self.input.insert(c);
+                }
+            }
+            Widget::Grade => {
+                if c.is_ascii_alphanumeric() || c == '+' || c == '-' || (c == '.' && !self.value().contains('.')) {
+                    self.input.insert(c.to_ascii_uppercase());
+                }
+            }
+            // These hold record ids and can only be picked from their dropdown
+            Widget::Choice(_) | Widget::MultiChoice(_) => {}
+        }
+    }
+
+    // Pasted text goes through the same filter as typed characters
+    pub fn paste(&mut self, text: &str) {
+        match self.spec.widget {
+            Widget::Text => {
+                self.touched = true;
+                self.input.insert_str(text);
+            }
+            _ => text.chars().for_each(|c| self.type_char(c)),
+        }
+    }
+
+    // Cursor movement and deletion; false if the action isn't a text edit
+    pub fn edit(&mut self, action: Action) -> bool {
+        if !action.edits_text() {
+            return false;
+        }
+        let deletes = matches!(action, Action::DeleteChar | Action::DeleteNextChar | Action::DeleteWord);
+        match self.dropdown {
+            // Picked values are cleared as a whole
+            Some(_) if deletes => self.input.clear(),
+            Some(_) => return false,
+            None => {
+                self.input.apply(action);
+            }
+        }
+        if deletes {
+            self.touched = true;
+        }
+        true
+    }
+
+    pub fn check(&self, rules: &Rules) -> Result<(), String> {
+        self.spec.rule.check(self.value(), rules)
+    }
+
+    // Message shown under the field
+    pub fn error(&self, rules: &Rules) -> Option<String> {
+        if self.touched { self.check(rules).err() } else { None }
+    }
+}
@@ -0,0 +1,214 @@
+use crate::models::{Course, Enrollment, Faculty, Student, Teacher};
+use crate::storage::{Change, Collection, Dataset};
+use crate::trash::TrashItem;
+use serde_json::Value;
+
+// Undo and redo for the session. Every saved change becomes a step holding each touched
+// record as it was before and after, so undoing or redoing puts those records back in
+// memory and saves them again, without replaying any of the logic that made the change.
+
+// Steps kept for undo; the oldest are forgotten first
+const MAX_STEPS: usize = 100;
+
+// A stored record of any kind
+#[derive(Debug, Clone)]
+enum Record {
+    Student(Student),
+    Teacher(Teacher),
+    Faculty(Faculty),
+    Course(Course),
+    Enrollment(Enrollment),
+    Trash(TrashItem),
+}
+
+impl Record {
+    fn to_value(&self) -> Value {
+        let value = match self {
+            Record::Student(s) => serde_json::to_value(s),
+            Record::Teacher(t) => serde_json::to_value(t),
+            Record::Faculty(f) => serde_json::to_value(f),
+            Record::Course(c) => serde_json::to_value(c),
+            Record::Enrollment(e) => serde_json::to_value(e),
+            Record::Trash(t) => serde_json::to_value(t),
+        };
+        // Plain data structs always serialize
+        value.unwrap_or_default()
+    }
+}
+
+// A record's position in its list and its contents
+type Slot = (usize, Record);
+
+// One record touched by a step; `None` where it did not exist
+#[derive(Debug, Clone)]
+struct RecordChange {
+    collection: Collection,
+    id: String,
+    before: Option<Slot>,
+    after: Option<Slot>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Step {
+    // What the step did, e.g. "delete student Ann Lee"
+    pub description: String,
+    records: Vec<RecordChange>,
+}
+
+impl Step {
+    // The records named in `changes`, as found in `before` and `after` the change
+    pub fn new(description: String, before: &Dataset, after: &Dataset, changes: &[Change]) -> Self {
+        let records = changes
+            .iter()
+            .map(|change| {
+                let (Change::Put(collection, id) | Change::Delete(collection, id)) = change;
+                RecordChange {
+                    collection: *collection,
+                    id: id.clone(),
+                    before: find(before, *collection, id),
+                    after: find(after, *collection, id),
+                }
+            })
+            .collect();
+        Self { description, records }
+    }
+
+    // Each touched record as JSON, from before to after the step, or the other way
+    // round when it is being undone
+    pub fn record_states(&self, undone: bool) -> Vec<(Collection, String, Option<Value>, Option<Value>)> {
+        self.records
+            .iter()
+            .map(|r| {
+                let before = r.before.as_ref().map(|(_, record)| record.to_value());
+                let after = r.after.as_ref().map(|(_, record)| record.to_value());
+                let (from, to) = if undone { (after, before) } else { (before, after) };
+                (r.collection, r.id.clone(), from, to)
+            })
+            .collect()
+    }
+
+    // Put the records back as they were before the step; returns the changes to save
+    pub fn revert(&self, data: &mut Dataset) -> Vec<Change> {
+        restore(data, self.records.iter().map(|r| (r.collection, r.id.as_str(), r.before.as_ref())))
+    }
+
+    // Make the step again; returns the changes to save
+    pub fn reapply(&self, data: &mut Dataset) -> Vec<Change> {
+        restore(data, self.records.iter().map(|r| (r.collection, r.id.as_str(), r.after.as_ref())))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+}
+
+impl History {
+    // A new change makes the undone steps unreachable
+    pub fn record(&mut self, step: Step) {
+        self.redo.clear();
+        self.undo.push(step);
+        if self.undo.len() > MAX_STEPS {
+            self.undo.remove(0);
+        }
+    }
+
+    pub fn next_undo(&self) -> Option<&Step> {
+        self.undo.last()
+    }
+
+    pub fn take_undo(&mut self) -> Option<Step> {
+        self.undo.pop()
+    }
+
+    pub fn take_redo(&mut self) -> Option<Step> {
+        self.redo.pop()
+    }
+
+    // Keep a step that was just undone, so it can be redone
+    pub fn push_redo(&mut self, step: Step) {
+        self.redo.push(step);
+    }
+
+    // Keep a step that was just redone, so it can be undone again
+    pub fn push_undo(&mut self, step: Step) {
+        self.undo.push(step);
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+// Bring each record to the given state: removed where it is `None`, otherwise replaced in
+// place or inserted at its old position
+fn restore<'a>(
+    data: &mut Dataset,
+    states: impl Iterator<Item = (Collection, &'a str, Option<&'a Slot>)>,
+) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let mut inserts = Vec::new();
+    for (collection, id, state) in states {
+        match state {
+            Some(slot) => {
+                changes.push(Change::Put(collection, id.to_string()));
+                inserts.push(slot);
+            }
+            None => {
+                changes.push(Change::Delete(collection, id.to_string()));
+                remove(data, collection, id);
+            }
+        }
+    }
+    // Lowest positions first, so each one is counted among records already back in place
+    inserts.sort_by_key(|(index, _)| *index);
+    for (index, record) in inserts {
+        put(data, *index, record.clone());
+    }
+    changes
+}
+
+fn find(data: &Dataset, collection: Collection, id: &str) -> Option<Slot> {
+    fn slot<T: Clone>(records: &[T], id: &str, key: fn(&T) -> &str, wrap: fn(T) -> Record) -> Option<Slot> {
+        let index = records.iter().position(|r| key(r) == id)?;
+        Some((index, wrap(records[index].clone())))
+    }
+    match collection {
+        Collection::Students => slot(&data.students, id, |s| &s.id, Record::Student),
+        Collection::Teachers => slot(&data.teachers, id, |t| &t.id, Record::Teacher),
+        Collection::Faculties => slot(&data.faculties, id, |f| &f.id, Record::Faculty),
+        Collection::Courses => slot(&data.courses, id, |c| &c.id, Record::Course),
+        Collection::Enrollments => slot(&data.enrollments, id, |e| &e.id, Record::Enrollment),
+        Collection::Trash => slot(&data.trash, id, |t| &t.id, Record::Trash),
+    }
+}
+
+fn remove(data: &mut Dataset, collection: Collection, id: &str) {
+    match collection {
+        Collection::Students => data.students.retain(|s| s.id != id),
+        Collection::Teachers => data.teachers.retain(|t| t.id != id),
+        Collection::Faculties => data.faculties.retain(|f| f.id != id),
+        Collection::Courses => data.courses.retain(|c| c.id != id),
+        Collection::Enrollments => data.enrollments.retain(|e| e.id != id),
+        Collection::Trash => data.trash.retain(|t| t.id != id),
+    }
+}
+
+fn put(data: &mut Dataset, index: usize, record: Record) {
+    fn put_in<T>(records: &mut Vec<T>, index: usize, record: T, key: fn(&T) -> &str) {
+        match records.iter().position(|r| key(r) == key(&record)) {
+            Some(existing) => records[existing] = record,
+            None => records.insert(index.min(records.len()), record),
+        }
+    }
+    match record {
+        Record::Student(s) => put_in(&mut data.students, index, s, |s| &s.id),
+        Record::Teacher(t) => put_in(&mut data.teachers, index, t, |t| &t.id),
+        Record::Faculty(f) => put_in(&mut data.faculties, index, f, |f| &f.id),
+        Record::Course(c) => put_in(&mut data.courses, index, c, |c| &c.id),
+        Record::Enrollment(e) => put_in(&mut data.enrollments, index, e, |e| &e.id),
+        Record::Trash(t) => put_in(&mut data.trash, index, t, |t| &t.id),
+    }
+}
@@ -0,0 +1,49 @@
+use crate::ui::{ActionButton, ActiveTab, ModalButton};
+use ratatui::layout::{Position, Rect};
+
+// Clickable areas of the last frame, recorded by the render functions as they lay
+// things out, so mouse handling never has to work out where anything was drawn.
+
+// What a click on a recorded area means
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hit {
+    Tab(ActiveTab),
+    TableHeader(usize),
+    // Index into the table's visible records, scroll offset included
+    TableRow(usize),
+    ActionButton(ActionButton),
+    // Everything behind an open modal; clicks there do nothing
+    Backdrop,
+    ModalButton(ModalButton),
+    FormField(usize),
+    DropdownItem(usize),
+    EnrollmentRow(usize),
+}
+
+#[derive(Debug, Default)]
+pub struct HitMap {
+    // In drawing order, so later areas are on top
+    areas: Vec<(Rect, Hit)>,
+}
+
+impl HitMap {
+    // Forget the previous frame; called before every render
+    pub fn clear(&mut self) {
+        self.areas.clear();
+    }
+
+    pub fn add(&mut self, area: Rect, hit: Hit) {
+        if !area.is_empty() {
+            self.areas.push((area, hit));
+        }
+    }
+
+    // The topmost area under a position
+    pub fn at(&self, (x, y): (u16, u16)) -> Option<Hit> {
+        self.areas
+            .iter()
+            .rev()
+            .find(|(area, _)| area.contains(Position::new(x, y)))
+            .map(|(_, hit)| *hit)
+    }
+}
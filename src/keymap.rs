@@ -0,0 +1,681 @@
+use crate::ui::ActiveTab;
+use anyhow::{anyhow, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+// Everything a key can do. Key handling in main.rs matches on these, never on raw keys,
+// so the bindings below are the single source for both dispatch and the help overlay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    Help,
+    CloseHelp,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    First,
+    Last,
+    NextTab,
+    PreviousTab,
+    ShowTab(ActiveTab),
+    Search,
+    ApplySearch,
+    CancelSearch,
+    Add,
+    Edit,
+    Delete,
+    OpenDetails,
+    Refresh,
+    ToggleDetailPane,
+    SortNextColumn,
+    ReverseSort,
+    Undo,
+    Redo,
+    Restore,
+    Save,
+    Cancel,
+    NextField,
+    PreviousField,
+    DeleteChar,
+    DeleteNextChar,
+    DeleteWord,
+    CursorLeft,
+    CursorRight,
+    WordLeft,
+    WordRight,
+    LineStart,
+    LineEnd,
+    ToggleDropdown,
+    ChooseItem,
+    NextChoice,
+    PreviousChoice,
+    Confirm,
+    OpenReadOnly,
+    RestoreBackups,
+    SkipInvalidRecords,
+    Reload,
+    ReloadKeepingMine,
+}
+
+// Names used for actions in the config file
+const ACTION_NAMES: &[(&str, Action)] = &[
+    ("quit", Action::Quit),
+    ("help", Action::Help),
+    ("close_help", Action::CloseHelp),
+    ("up", Action::Up),
+    ("down", Action::Down),
+    ("page_up", Action::PageUp),
+    ("page_down", Action::PageDown),
+    ("first", Action::First),
+    ("last", Action::Last),
+    ("next_tab", Action::NextTab),
+    ("previous_tab", Action::PreviousTab),
+    ("students_tab", Action::ShowTab(ActiveTab::Students)),
+    ("teachers_tab", Action::ShowTab(ActiveTab::Teachers)),
+    ("faculties_tab", Action::ShowTab(ActiveTab::Faculties)),
+    ("courses_tab", Action::ShowTab(ActiveTab::Courses)),
+    ("audit_tab", Action::ShowTab(ActiveTab::Audit)),
+    ("trash_tab", Action::ShowTab(ActiveTab::Trash)),
+    ("search", Action::Search),
+    ("apply_search", Action::ApplySearch),
+    ("cancel_search", Action::CancelSearch),
+    ("add", Action::Add),
+    ("edit", Action::Edit),
+    ("delete", Action::Delete),
+    ("open_details", Action::OpenDetails),
+    ("refresh", Action::Refresh),
+    ("toggle_detail_pane", Action::ToggleDetailPane),
+    ("sort_next_column", Action::SortNextColumn),
+    ("reverse_sort", Action::ReverseSort),
+    ("undo", Action::Undo),
+    ("redo", Action::Redo),
+    ("restore", Action::Restore),
+    ("save", Action::Save),
+    ("cancel", Action::Cancel),
+    ("next_field", Action::NextField),
+    ("previous_field", Action::PreviousField),
+    ("delete_char", Action::DeleteChar),
+    ("delete_next_char", Action::DeleteNextChar),
+    ("delete_word", Action::DeleteWord),
+    ("cursor_left", Action::CursorLeft),
+    ("cursor_right", Action::CursorRight),
+    ("word_left", Action::WordLeft),
+    ("word_right", Action::WordRight),
+    ("line_start", Action::LineStart),
+    ("line_end", Action::LineEnd),
+    ("toggle_dropdown", Action::ToggleDropdown),
+    ("choose_item", Action::ChooseItem),
+    ("next_choice", Action::NextChoice),
+    ("previous_choice", Action::PreviousChoice),
+    ("confirm", Action::Confirm),
+    ("open_read_only", Action::OpenReadOnly),
+    ("restore_backups", Action::RestoreBackups),
+    ("skip_invalid_records", Action::SkipInvalidRecords),
+    ("reload", Action::Reload),
+    ("reload_keeping_mine", Action::ReloadKeepingMine),
+];
+
+impl Action {
+    // Cursor movement and deletion in a text field
+    pub fn edits_text(&self) -> bool {
+        matches!(
+            self,
+            Action::DeleteChar
+                | Action::DeleteNextChar
+                | Action::DeleteWord
+                | Action::CursorLeft
+                | Action::CursorRight
+                | Action::WordLeft
+                | Action::WordRight
+                | Action::LineStart
+                | Action::LineEnd
+        )
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ACTION_NAMES
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, action)| *action)
+            .ok_or_else(|| format!("unknown action '{}'", s))
+    }
+}
+
+// Which set of bindings applies, following what has the user's attention
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyContext {
+    Normal,
+    Search,
+    Form,
+    Dropdown,
+    DeleteConfirmation,
+    StudentDetail,
+    Recovery,
+    Reload,
+    Help,
+}
+
+// Names used for contexts in the config file, as `[keymap.<name>]` tables
+const CONTEXT_NAMES: &[(&str, KeyContext)] = &[
+    ("normal", KeyContext::Normal),
+    ("search", KeyContext::Search),
+    ("form", KeyContext::Form),
+    ("dropdown", KeyContext::Dropdown),
+    ("delete_confirmation", KeyContext::DeleteConfirmation),
+    ("student_detail", KeyContext::StudentDetail),
+    ("recovery", KeyContext::Recovery),
+    ("reload", KeyContext::Reload),
+    ("help", KeyContext::Help),
+];
+
+impl KeyContext {
+    pub fn title(&self) -> &'static str {
+        match self {
+            KeyContext::Normal => "Tables",
+            KeyContext::Search => "Search",
+            KeyContext::Form => "Form",
+            KeyContext::Dropdown => "Open dropdown",
+            KeyContext::DeleteConfirmation => "Delete confirmation",
+            KeyContext::StudentDetail => "Student details",
+            KeyContext::Recovery => "Recovery",
+            KeyContext::Reload => "Files changed on disk",
+            KeyContext::Help => "Help",
+        }
+    }
+
+    // In contexts with a text field, characters without a binding are typed into it
+    pub fn accepts_text(&self) -> bool {
+        matches!(self, KeyContext::Search | KeyContext::Form)
+    }
+}
+
+impl FromStr for KeyContext {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CONTEXT_NAMES
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, context)| *context)
+            .ok_or_else(|| format!("unknown key context '{}'", s))
+    }
+}
+
+// One key press with its modifiers, such as `Ctrl+S`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // Shift is already in the character ('G') or the key (BackTab), and terminals
+        // disagree on whether to report it as well
+        let modifiers = match code {
+            KeyCode::Char(_) | KeyCode::BackTab => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        Self { code, modifiers }
+    }
+
+    // The character this key types into a text field, if any
+    pub fn typed_char(&self) -> Option<char> {
+        match self.code {
+            KeyCode::Char(c) if self.modifiers.is_empty() => Some(c),
+            _ => None,
+        }
+    }
+}
+
+impl From<KeyEvent> for KeyChord {
+    fn from(event: KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+}
+
+// Parses names such as "j", "G", "enter", "ctrl+s" or "shift+tab", ignoring case except for single characters
+impl FromStr for KeyChord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s;
+        loop {
+            let lower = rest.to_lowercase();
+            let (modifier, prefix_len) = if lower.starts_with("ctrl+") {
+                (KeyModifiers::CONTROL, 5)
+            } else if lower.starts_with("alt+") {
+                (KeyModifiers::ALT, 4)
+            } else if lower.starts_with("shift+") {
+                (KeyModifiers::SHIFT, 6)
+            } else {
+                break;
+            };
+            if rest.len() == prefix_len {
+                break;
+            }
+            modifiers |= modifier;
+            rest = &rest[prefix_len..];
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::Char(c.to_ascii_uppercase()),
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_lowercase().as_str() {
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                "space" => KeyCode::Char(' '),
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pgup" | "pageup" => KeyCode::PageUp,
+                "pgdn" | "pagedown" => KeyCode::PageDown,
+                name => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                    Some(n) if (1..=12).contains(&n) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key '{}'", s)),
+                },
+            },
+        };
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) if self.modifiers.is_empty() => write!(f, "{}", c),
+            KeyCode::Char(c) => write!(f, "{}", c.to_ascii_uppercase()),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            KeyCode::BackTab => write!(f, "Shift+Tab"),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+// Keys pressed one after another, written with spaces in between ("g g")
+fn parse_keys(s: &str) -> Result<Vec<KeyChord>, String> {
+    let keys = s.split_whitespace().map(KeyChord::from_str).collect::<Result<Vec<_>, _>>()?;
+    if keys.is_empty() {
+        return Err("empty key".to_string());
+    }
+    Ok(keys)
+}
+
+// Plain letters read better run together ("gg"), anything else is spaced ("Ctrl+W q")
+fn keys_label(keys: &[KeyChord]) -> String {
+    let labels: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+    if keys.len() > 1 && keys.iter().all(|key| key.typed_char().is_some_and(|c| c != ' ')) {
+        labels.concat()
+    } else {
+        labels.join(" ")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub keys: Vec<KeyChord>,
+    pub action: Action,
+    pub description: &'static str,
+}
+
+// Built-in bindings: keys, action, description
+type Table = &'static [(&'static str, Action, &'static str)];
+
+const NORMAL: Table = &[
+    ("q", Action::Quit, "Quit the application"),
+    ("H", Action::Help, "Show this help"),
+    ("f1", Action::Help, "Show this help"),
+    ("tab", Action::NextTab, "Next tab"),
+    ("shift+tab", Action::PreviousTab, "Previous tab"),
+    ("1", Action::ShowTab(ActiveTab::Students), "Students tab"),
+    ("2", Action::ShowTab(ActiveTab::Teachers), "Teachers tab"),
+    ("3", Action::ShowTab(ActiveTab::Faculties), "Faculties tab"),
+    ("4", Action::ShowTab(ActiveTab::Courses), "Courses tab"),
+    ("5", Action::ShowTab(ActiveTab::Audit), "Audit log tab"),
+    ("6", Action::ShowTab(ActiveTab::Trash), "Trash tab"),
+    ("up", Action::Up, "Previous row"),
+    ("down", Action::Down, "Next row"),
+    ("pgup", Action::PageUp, "Page up"),
+    ("pgdn", Action::PageDown, "Page down"),
+    ("home", Action::First, "First row"),
+    ("end", Action::Last, "Last row"),
+    ("a", Action::Add, "Add a record"),
+    ("e", Action::Edit, "Edit the selected record"),
+    ("d", Action::Delete, "Delete the selected record (in the trash, purge it)"),
+    ("R", Action::Restore, "Restore the selected record from the trash"),
+    ("enter", Action::OpenDetails, "Open the selected student's enrollments"),
+    ("f", Action::Search, "Search the current table"),
+    ("r", Action::Refresh, "Reload the data from disk"),
+    ("p", Action::ToggleDetailPane, "Show or hide the detail pane"),
+    ("s", Action::SortNextColumn, "Sort by the next column"),
+    ("S", Action::ReverseSort, "Reverse the sort direction"),
+    ("ctrl+z", Action::Undo, "Undo the last change"),
+    ("ctrl+y", Action::Redo, "Redo the last undone change"),
+];
+
+const SEARCH: Table = &[
+    ("enter", Action::ApplySearch, "Keep the filter and go back to the table"),
+    ("esc", Action::CancelSearch, "Clear the filter"),
+    ("backspace", Action::DeleteChar, "Delete the character before the cursor"),
+    ("delete", Action::DeleteNextChar, "Delete the character under the cursor"),
+    ("ctrl+w", Action::DeleteWord, "Delete the word before the cursor"),
+    ("alt+backspace", Action::DeleteWord, "Delete the word before the cursor"),
+    ("left", Action::CursorLeft, "Move the cursor left"),
+    ("right", Action::CursorRight, "Move the cursor right"),
+    ("ctrl+left", Action::WordLeft, "Move the cursor to the previous word"),
+    ("ctrl+right", Action::WordRight, "Move the cursor to the next word"),
+    ("home", Action::LineStart, "Move the cursor to the start"),
+    ("ctrl+a", Action::LineStart, "Move the cursor to the start"),
+    ("end", Action::LineEnd, "Move the cursor to the end"),
+    ("ctrl+e", Action::LineEnd, "Move the cursor to the end"),
+    ("f1", Action::Help, "Show this help"),
+];
+
+const FORM: Table = &[
+    ("enter", Action::Confirm, "Save (on a dropdown field, open its list)"),
+    ("ctrl+s", Action::Save, "Save from any field"),
+    ("esc", Action::Cancel, "Close without saving"),
+    ("tab", Action::NextField, "Next field"),
+    ("down", Action::NextField, "Next field"),
+    ("shift+tab", Action::PreviousField, "Previous field"),
+    ("up", Action::PreviousField, "Previous field"),
+    ("backspace", Action::DeleteChar, "Delete the character before the cursor (clears a dropdown field)"),
+    ("delete", Action::DeleteNextChar, "Delete the character under the cursor"),
+    ("ctrl+w", Action::DeleteWord, "Delete the word before the cursor"),
+    ("alt+backspace", Action::DeleteWord, "Delete the word before the cursor"),
+    ("left", Action::CursorLeft, "Move the cursor left"),
+    ("right", Action::CursorRight, "Move the cursor right"),
+    ("ctrl+left", Action::WordLeft, "Move the cursor to the previous word"),
+    ("ctrl+right", Action::WordRight, "Move the cursor to the next word"),
+    ("home", Action::LineStart, "Move the cursor to the start"),
+    ("ctrl+a", Action::LineStart, "Move the cursor to the start"),
+    ("end", Action::LineEnd, "Move the cursor to the end"),
+    ("ctrl+e", Action::LineEnd, "Move the cursor to the end"),
+    ("space", Action::ToggleDropdown, "Open a dropdown field's list"),
+    ("f1", Action::Help, "Show this help"),
+];
+
+const DROPDOWN: Table = &[
+    ("up", Action::Up, "Previous item"),
+    ("down", Action::Down, "Next item"),
+    ("enter", Action::ChooseItem, "Choose the highlighted item"),
+    ("space", Action::ToggleDropdown, "Close the list"),
+    ("esc", Action::Cancel, "Close the list"),
+    ("f1", Action::Help, "Show this help"),
+];
+
+const DELETE_CONFIRMATION: Table = &[
+    ("enter", Action::Confirm, "Delete with the chosen option"),
+    ("esc", Action::Cancel, "Keep the record"),
+    ("left", Action::PreviousChoice, "Previous option for dependent records"),
+    ("shift+tab", Action::PreviousChoice, "Previous option for dependent records"),
+    ("right", Action::NextChoice, "Next option for dependent records"),
+    ("tab", Action::NextChoice, "Next option for dependent records"),
+    ("up", Action::Up, "Previous record to reassign to"),
+    ("down", Action::Down, "Next record to reassign to"),
+    ("f1", Action::Help, "Show this help"),
+];
+
+const STUDENT_DETAIL: Table = &[
+    ("up", Action::Up, "Previous enrollment"),
+    ("down", Action::Down, "Next enrollment"),
+    ("a", Action::Add, "Enroll in a course"),
+    ("e", Action::Edit, "Edit the selected enrollment"),
+    ("enter", Action::Edit, "Edit the selected enrollment"),
+    ("d", Action::Delete, "Remove the selected enrollment"),
+    ("esc", Action::Cancel, "Close"),
+    ("H", Action::Help, "Show this help"),
+    ("f1", Action::Help, "Show this help"),
+];
+
+const RECOVERY: Table = &[
+    ("r", Action::OpenReadOnly, "Open the readable data read-only"),
+    ("b", Action::RestoreBackups, "Restore the newest readable backups"),
+    ("s", Action::SkipInvalidRecords, "Skip unreadable records and save the rest"),
+    ("q", Action::Quit, "Quit without changing anything"),
+    ("esc", Action::Quit, "Quit without changing anything"),
+    ("f1", Action::Help, "Show this help"),
+];
+
+const RELOAD: Table = &[
+    ("enter", Action::Reload, "Reload, keeping the disk's version of records changed on both sides"),
+    ("m", Action::ReloadKeepingMine, "Reload, keeping your version of records changed on both sides"),
+    ("esc", Action::Cancel, "Keep working; saves still merge in the changed files"),
+    ("f1", Action::Help, "Show this help"),
+];
+
+const HELP: Table = &[
+    ("up", Action::Up, "Scroll up"),
+    ("down", Action::Down, "Scroll down"),
+    ("pgup", Action::PageUp, "Scroll a page up"),
+    ("pgdn", Action::PageDown, "Scroll a page down"),
+    ("home", Action::First, "Scroll to the top"),
+    ("end", Action::Last, "Scroll to the bottom"),
+    ("esc", Action::CloseHelp, "Close the help"),
+    ("q", Action::CloseHelp, "Close the help"),
+    ("H", Action::CloseHelp, "Close the help"),
+    ("f1", Action::CloseHelp, "Close the help"),
+];
+
+fn default_table(context: KeyContext) -> Table {
+    match context {
+        KeyContext::Normal => NORMAL,
+        KeyContext::Search => SEARCH,
+        KeyContext::Form => FORM,
+        KeyContext::Dropdown => DROPDOWN,
+        KeyContext::DeleteConfirmation => DELETE_CONFIRMATION,
+        KeyContext::StudentDetail => STUDENT_DETAIL,
+        KeyContext::Recovery => RECOVERY,
+        KeyContext::Reload => RELOAD,
+        KeyContext::Help => HELP,
+    }
+}
+
+// The vim preset rebinds these actions on top of the built-in keys; text fields are left alone
+const VIM: &[(KeyContext, Action, &[&str])] = &[
+    (KeyContext::Normal, Action::Down, &["down", "j"]),
+    (KeyContext::Normal, Action::Up, &["up", "k"]),
+    (KeyContext::Normal, Action::First, &["home", "g g"]),
+    (KeyContext::Normal, Action::Last, &["end", "G"]),
+    (KeyContext::Normal, Action::PageDown, &["pgdn", "ctrl+d"]),
+    (KeyContext::Normal, Action::PageUp, &["pgup", "ctrl+u"]),
+    (KeyContext::Normal, Action::NextTab, &["tab", "l"]),
+    (KeyContext::Normal, Action::PreviousTab, &["shift+tab", "h"]),
+    (KeyContext::Normal, Action::Search, &["/", "f"]),
+    (KeyContext::Normal, Action::Delete, &["d d"]),
+    (KeyContext::Normal, Action::Undo, &["ctrl+z", "u"]),
+    (KeyContext::Normal, Action::Redo, &["ctrl+y", "ctrl+r"]),
+    (KeyContext::Dropdown, Action::Down, &["down", "j"]),
+    (KeyContext::Dropdown, Action::Up, &["up", "k"]),
+    (KeyContext::DeleteConfirmation, Action::Down, &["down", "j"]),
+    (KeyContext::DeleteConfirmation, Action::Up, &["up", "k"]),
+    (KeyContext::DeleteConfirmation, Action::NextChoice, &["right", "tab", "l"]),
+    (KeyContext::DeleteConfirmation, Action::PreviousChoice, &["left", "shift+tab", "h"]),
+    (KeyContext::StudentDetail, Action::Down, &["down", "j"]),
+    (KeyContext::StudentDetail, Action::Up, &["up", "k"]),
+    (KeyContext::StudentDetail, Action::Delete, &["d d"]),
+    (KeyContext::Help, Action::Down, &["down", "j"]),
+    (KeyContext::Help, Action::Up, &["up", "k"]),
+    (KeyContext::Help, Action::First, &["home", "g g"]),
+    (KeyContext::Help, Action::Last, &["end", "G"]),
+    (KeyContext::Help, Action::PageDown, &["pgdn", "ctrl+d"]),
+    (KeyContext::Help, Action::PageUp, &["pgup", "ctrl+u"]),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Preset {
+    #[default]
+    Default,
+    Vim,
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "default" => Ok(Preset::Default),
+            "vim" => Ok(Preset::Vim),
+            other => Err(format!("unknown keymap preset '{}' (expected default or vim)", other)),
+        }
+    }
+}
+
+// Keys from the config file: context name -> action name -> keys
+pub type Overrides = BTreeMap<String, BTreeMap<String, Vec<String>>>;
+
+// Result of looking up the keys pressed so far
+pub enum Lookup {
+    Action(Action),
+    // The keys start a longer binding, such as the first `g` of `gg`
+    Pending,
+    None,
+}
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    contexts: HashMap<KeyContext, Vec<Binding>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(Preset::Default, &Overrides::new()).expect("built-in key bindings are valid")
+    }
+}
+
+impl Keymap {
+    // Built-in bindings, then the preset's, then the user's from the config file
+    pub fn new(preset: Preset, overrides: &Overrides) -> Result<Self> {
+        let mut keymap = Self { contexts: HashMap::new() };
+        for &(_, context) in CONTEXT_NAMES {
+            let bindings = default_table(context)
+                .iter()
+                .map(|&(keys, action, description)| {
+                    Ok(Binding { keys: parse_keys(keys).map_err(|err| anyhow!(err))?, action, description })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            keymap.contexts.insert(context, bindings);
+        }
+
+        if preset == Preset::Vim {
+            for &(context, action, keys) in VIM {
+                let keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+                keymap.rebind(context, action, &keys).map_err(|err| anyhow!(err))?;
+            }
+        }
+
+        for (context_name, actions) in overrides {
+            let context: KeyContext = context_name.parse().map_err(|err| anyhow!("[keymap.{}]: {}", context_name, err))?;
+            for (action_name, keys) in actions {
+                let action: Action = action_name
+                    .parse()
+                    .map_err(|err| anyhow!("[keymap.{}] {}: {}", context_name, action_name, err))?;
+                keymap
+                    .rebind(context, action, keys)
+                    .map_err(|err| anyhow!("[keymap.{}] {}: {}", context_name, action_name, err))?;
+            }
+        }
+        Ok(keymap)
+    }
+
+    // Replace the keys of an action that the context supports, where it was listed. Keys it takes
+    // over, and bindings that would shadow it or be shadowed by it (`d` and `d d`), stop working.
+    fn rebind(&mut self, context: KeyContext, action: Action, keys: &[String]) -> Result<(), String> {
+        let bindings = self.contexts.get_mut(&context).expect("every context has bindings");
+        let position = bindings
+            .iter()
+            .position(|binding| binding.action == action)
+            .ok_or_else(|| "no such action in this context".to_string())?;
+        let description = bindings[position].description;
+        let keys = keys.iter().map(|keys| parse_keys(keys)).collect::<Result<Vec<_>, _>>()?;
+
+        let mut rebound = Vec::new();
+        for (index, binding) in bindings.drain(..).enumerate() {
+            if index == position {
+                rebound.extend(keys.iter().map(|keys| Binding { keys: keys.clone(), action, description }));
+            }
+            let conflicts = keys.iter().any(|keys| binding.keys.starts_with(keys) || keys.starts_with(&binding.keys));
+            if binding.action != action && !conflicts {
+                rebound.push(binding);
+            }
+        }
+        *bindings = rebound;
+        Ok(())
+    }
+
+    pub fn bindings(&self, context: KeyContext) -> &[Binding] {
+        self.contexts.get(&context).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn lookup(&self, context: KeyContext, keys: &[KeyChord]) -> Lookup {
+        let bindings = self.bindings(context);
+        if let Some(binding) = bindings.iter().find(|binding| binding.keys == keys) {
+            Lookup::Action(binding.action)
+        } else if bindings.iter().any(|binding| binding.keys.starts_with(keys)) {
+            Lookup::Pending
+        } else {
+            Lookup::None
+        }
+    }
+
+    // The first keys of an action, for labels such as "a: Add"; "?" when it has none
+    pub fn key_label(&self, context: KeyContext, action: Action) -> String {
+        self.bindings(context)
+            .iter()
+            .find(|binding| binding.action == action)
+            .map(|binding| keys_label(&binding.keys))
+            .unwrap_or_else(|| "?".to_string())
+    }
+
+    // Help entries for a context: every key of an action on one line, in table order
+    pub fn help_entries(&self, context: KeyContext) -> Vec<(String, &'static str)> {
+        let mut entries: Vec<(Vec<String>, Action, &'static str)> = Vec::new();
+        for binding in self.bindings(context) {
+            let label = keys_label(&binding.keys);
+            match entries.iter_mut().find(|(_, action, description)| {
+                *action == binding.action && *description == binding.description
+            }) {
+                Some((keys, ..)) => keys.push(label),
+                None => entries.push((vec![label], binding.action, binding.description)),
+            }
+        }
+        entries
+            .into_iter()
+            .map(|(keys, _, description)| (keys.join(", "), description))
+            .collect()
+    }
+}
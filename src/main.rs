@@ -1,31 +1,59 @@
+mod audit;
+mod cli;
+mod config;
 mod data_manager;
+mod form;
+mod history;
+mod hit_map;
+mod keymap;
 mod modal;
 mod models;
+mod rules;
+mod storage;
+mod sync;
+mod trash;
 mod ui;
 mod widgets;
 
-use crate::data_manager::DataManager;
-use crate::modal::{Modal, ModalType};
-use crate::models::{Faculty, Student, Teacher};
-use crate::ui::{AppState, ActiveTab, render, get_element_at_position};
+use crate::audit::AuditLog;
+use crate::cli::Cli;
+use crate::config::Config;
+use clap::Parser;
+use crate::data_manager::{DataManager, DiskChanges};
+use crate::keymap::{Action, KeyChord, KeyContext, Keymap, Lookup};
+use crate::modal::{DeleteChoice, Modal, ModalType};
+use crate::models::EntityKind;
+use crate::sync::{Conflict, DataLock};
+use crate::hit_map::Hit;
+use crate::ui::{AppState, ActiveTab, render};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Result};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, MouseEventKind, MouseButton},
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event, KeyEvent,
+        KeyEventKind, MouseEventKind, MouseButton,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use ratatui::{prelude::*, widgets::*};
-use ui::centered_rect;
+use ratatui::prelude::*;
 use std::{
     io,
     time::{Duration, Instant},
 };
 
+// Lines the help overlay scrolls on PageUp/PageDown
+const HELP_PAGE: u16 = 10;
+
 enum AppMode {
     Normal,
     Search,
-    Modal(Modal),
+    Modal(Box<Modal>),
+    // Some data files could not be read; holds the outcome of the last recovery attempt
+    Recovery(Option<String>),
+    // Asking whether to reload data files changed on disk; holds how they changed and the
+    // outcome of the last reload attempt
+    Reload(DiskChanges, Option<String>),
 }
 
 struct App {
@@ -33,22 +61,37 @@ struct App {
     data_manager: DataManager,
     mode: AppMode,
     should_quit: bool,
+    keymap: Keymap,
+    // Keys of a binding typed so far, such as the first `g` of `gg`
+    pending_keys: Vec<KeyChord>,
     tick_rate: Duration,
     last_tick: Instant,
 }
 
 impl App {
-    fn new() -> Result<Self> {
-        let data_manager = DataManager::new(None)?;
+    fn new(data_manager: DataManager, config: &Config) -> Self {
+        let mode = if data_manager.load_errors().is_empty() {
+            AppMode::Normal
+        } else {
+            AppMode::Recovery(None)
+        };
         
-        Ok(Self {
-            state: AppState::default(),
+        let state = AppState {
+            active_tab: config.default_tab,
+            notification_ticks: config.notification_ticks(),
+            ..AppState::default()
+        };
+
+        Self {
+            state,
             data_manager,
-            mode: AppMode::Normal,
+            mode,
             should_quit: false,
-            tick_rate: Duration::from_millis(100), // 10 ticks per second
+            keymap: config.keymap.clone(),
+            pending_keys: Vec::new(),
+            tick_rate: config.tick_rate,
             last_tick: Instant::now(),
-        })
+        }
     }
 
     fn run(&mut self, terminal: &mut Terminal<impl Backend>) -> Result<()> {
@@ -62,39 +105,53 @@ impl App {
     }
 
     fn render(&mut self, frame: &mut Frame) {
+        // Only the rows matching the current search query are shown, in each table's sort order
+        let results = ui::visible_records(&self.data_manager, &self.state);
+        let searching = matches!(self.mode, AppMode::Search);
+        self.state.hit_map.clear();
+
         match &mut self.mode {
             AppMode::Normal | AppMode::Search => {
-                let students = self.data_manager.get_all_students();
-                let teachers = self.data_manager.get_all_teachers();
-                let faculties = self.data_manager.get_all_faculties();
-                
-                render(frame, &mut self.state, students, teachers, faculties);
+                render(frame, &mut self.state, &self.data_manager, &self.keymap, &results, searching);
             }
             AppMode::Modal(modal) => {
                 // Render the base UI first
-                let students = self.data_manager.get_all_students();
-                let teachers = self.data_manager.get_all_teachers();
-                let faculties = self.data_manager.get_all_faculties();
-                
-                render(frame, &mut self.state, students, teachers, faculties);
+                render(frame, &mut self.state, &self.data_manager, &self.keymap, &results, searching);
                 
-                // Then render the modal on top
-                modal::render_modal(frame, modal);
+                // Then render the modal on top, keeping notifications visible above it
+                modal::render_modal(frame, modal, &self.data_manager, &self.keymap, &mut self.state.hit_map);
+                if let Some(notification) = &self.state.notification {
+                    ui::render_notification(frame, notification);
+                }
+            }
+            AppMode::Recovery(message) => {
+                ui::render_recovery(frame, self.data_manager.load_errors(), &self.keymap, message.as_deref());
             }
+            AppMode::Reload(changes, message) => {
+                render(frame, &mut self.state, &self.data_manager, &self.keymap, &results, searching);
+                ui::render_reload_prompt(frame, changes, &self.keymap, message.as_deref());
+            }
+        }
+
+        // Help for whatever has the user's attention goes over everything else
+        if self.state.show_help {
+            let context = self.key_context();
+            ui::render_help(frame, &self.keymap, context, &mut self.state.help_scroll);
         }
     }
 
     fn handle_events(&mut self) -> Result<()> {
-        if event::poll(std::time::Duration::from_millis(50))? {
+        // Wait for input no longer than the next tick is due
+        let timeout = self.tick_rate.saturating_sub(self.last_tick.elapsed());
+        if event::poll(timeout)? {
             match event::read()? {
-                Event::Key(key) => {
-                    if key.kind == KeyEventKind::Press {
-                        self.handle_key_event(key.code)?;
-                    }
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    self.handle_key_event(key)?;
                 },
                 Event::Mouse(mouse) => {
                     self.handle_mouse_event(mouse)?;
                 },
+                Event::Paste(text) => self.handle_paste(&text),
                 _ => {}
             }
         }
@@ -102,178 +159,372 @@ impl App {
         Ok(())
     }
 
+    // Pasted text arrives in one piece and goes into the focused text field as typed,
+    // instead of each character being taken as a key binding
+    fn handle_paste(&mut self, text: &str) {
+        if self.state.show_help {
+            return;
+        }
+        match &mut self.mode {
+            AppMode::Search => {
+                self.state.search_query.insert_str(text);
+                self.refresh_data();
+            }
+            AppMode::Modal(modal) => modal.paste(text),
+            _ => {}
+        }
+    }
+
     fn handle_mouse_event(&mut self, mouse: event::MouseEvent) -> Result<()> {
-        // Only handle mouse press events
-        if mouse.kind != MouseEventKind::Down(MouseButton::Left) {
+        // The recovery screen and the reload prompt are keyboard-only
+        if let AppMode::Recovery(_) | AppMode::Reload(..) = self.mode {
             return Ok(());
         }
 
-        // Get the position of the mouse click
-        let position = (mouse.column, mouse.row);
+        // Only handle left clicks and the scroll wheel
+        match mouse.kind {
+            // Clicks are ignored while the help overlay covers the screen
+            MouseEventKind::Down(MouseButton::Left) if self.state.show_help => return Ok(()),
+            MouseEventKind::Down(MouseButton::Left) => {}
+            MouseEventKind::ScrollDown => return self.handle_scroll(Action::Down),
+            MouseEventKind::ScrollUp => return self.handle_scroll(Action::Up),
+            _ => return Ok(()),
+        }
+
+        // Whatever the last frame drew on top at this position
+        let hit = self.state.hit_map.at((mouse.column, mouse.row));
 
-        // Check if this is a click in a modal
         if let AppMode::Modal(modal) = &mut self.mode {
-            // IMPORTANT: Check for button clicks FIRST before any dropdown interaction
-            // This ensures button clicks take priority, especially for the Save button
-            if let Some(button) = modal::get_modal_element_at_position(position, modal, terminal_size()) {
-                match button {
-                    ui::ModalButton::Confirm => {
-                        // Close any open dropdown and handle save
-                        modal.major_dropdown.is_open = false;
-                        self.handle_modal_key_event(KeyCode::Enter)?;
-                        return Ok(());
-                    },
-                    ui::ModalButton::Cancel => {
-                        // Close any open dropdown and handle cancel
-                        modal.major_dropdown.is_open = false;
-                        self.handle_modal_key_event(KeyCode::Esc)?;
-                        return Ok(());
-                    },
+            match hit {
+                Some(Hit::ModalButton(button)) => {
+                    modal.close_dropdowns();
+                    let action = match button {
+                        ui::ModalButton::Confirm => Action::Confirm,
+                        ui::ModalButton::Cancel => Action::Cancel,
+                    };
+                    self.run_modal_action(Some(action), None);
+                    return Ok(());
                 }
-            }
-            
-            // After checking buttons and not finding any match, handle dropdown interactions
-            if matches!(modal.modal_type, ModalType::AddStudent | ModalType::EditStudent(_)) && 
-               modal.active_field == 3 { // Major field is active
-                
-                if modal.major_dropdown.is_open {
-                    // Check if click is in the dropdown list area
-                    if let Some(selected_item) = modal::is_dropdown_item_clicked(position, &modal.major_dropdown, modal) {
-                        // Update the major field with the selected item
-                        modal.inputs[3].1 = selected_item;
-                        modal.major_dropdown.is_open = false;
-                        return Ok(());
-                    } else {
-                        // If click is outside dropdown area and not on a button, close the dropdown
-                        modal.major_dropdown.is_open = false;
-                        return Ok(());
+                Some(Hit::DropdownItem(index)) => {
+                    if let Some(dropdown) = modal.active_dropdown() {
+                        dropdown.list_state.select(Some(index));
                     }
-                } else {
-                    // If dropdown is closed and clicking on the Major field, open it
-                    // First check if click is on the Major field itself
-                    let area = centered_rect(60, 60, terminal_size());
-                    let inner_area = area.inner(Margin::new(1, 1));
-                    let chunks = Layout::default()
-                        .direction(Direction::Vertical)
-                        .margin(1)
-                        .constraints([
-                            Constraint::Length(2), // First Name
-                            Constraint::Length(2), // Last Name
-                            Constraint::Length(2), // Age
-                            Constraint::Length(2), // Major
-                            Constraint::Length(2), // GPA
-                            Constraint::Length(1), // Separator
-                            Constraint::Length(3), // Buttons
-                        ])
-                        .split(inner_area);
-                    
-                    // If click is on the major field, open dropdown
-                    if modal::is_position_in_rect(position, chunks[3]) {
-                        modal.major_dropdown.is_open = true;
-                        return Ok(());
+                    modal.choose_dropdown_item();
+                }
+                // A click anywhere else closes an open dropdown
+                _ if modal.is_dropdown_open() => modal.close_dropdowns(),
+                // Clicking a field focuses it, and opens the list of a dropdown field
+                Some(Hit::FormField(index)) => {
+                    modal.focus_field(index);
+                    if let Some(dropdown) = modal.active_dropdown() {
+                        dropdown.is_open = true;
                     }
                 }
+                Some(Hit::EnrollmentRow(index)) => modal.list_state.select(Some(index)),
+                _ => {}
             }
+            return Ok(());
         }
 
-        // Get the UI element at the position for normal mode
-        let element = get_element_at_position(
-            position,
-            self.state.active_tab,
-            &self.data_manager,
-            &mut self.state
-        );
-
-        // Handle the click based on the element
-        match element {
-            ui::UiElement::Tab(tab) => {
+        match hit {
+            Some(Hit::Tab(tab)) => {
                 self.state.active_tab = tab;
                 self.refresh_data();
+            }
+            Some(Hit::TableHeader(column)) => {
+                self.change_sort(|sort, _| sort.toggle(column));
+            }
+            Some(Hit::TableRow(index)) => {
+                self.state.get_current_table_state().select(Some(index));
+            }
+            Some(Hit::ActionButton(action)) => match action {
+                ui::ActionButton::Add => self.show_add_modal(),
+                ui::ActionButton::Edit => self.show_edit_modal(),
+                ui::ActionButton::Delete => self.show_delete_modal(),
+                ui::ActionButton::Search => self.mode = AppMode::Search,
+                ui::ActionButton::Refresh => self.reload_from_disk(),
             },
-            ui::UiElement::TableRow(index) => {
-                match self.state.active_tab {
-                    ActiveTab::Students => self.state.student_list_state.select(Some(index)),
-                    ActiveTab::Teachers => self.state.teacher_list_state.select(Some(index)),
-                    ActiveTab::Faculties => self.state.faculty_list_state.select(Some(index)),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    // The wheel moves through whatever list is under the user's attention, like the arrow keys
+    fn handle_scroll(&mut self, action: Action) -> Result<()> {
+        if self.state.show_help {
+            self.handle_help_action(action);
+            return Ok(());
+        }
+        match &mut self.mode {
+            AppMode::Normal | AppMode::Search => {
+                let delta = if action == Action::Down { 1 } else { -1 };
+                self.state.move_selection(delta, self.visible_row_count());
+                Ok(())
+            }
+            // In forms the arrow keys move between fields, so only an open dropdown scrolls
+            AppMode::Modal(modal) => {
+                if matches!(modal.modal_type, ModalType::StudentDetail(_)) || modal.is_dropdown_open() {
+                    self.handle_modal_action(Some(action), None)?;
+                }
+                Ok(())
+            }
+            AppMode::Recovery(_) | AppMode::Reload(..) => Ok(()),
+        }
+    }
+
+    // Bindings that apply right now; the help overlay lists the same ones
+    fn key_context(&mut self) -> KeyContext {
+        match &mut self.mode {
+            AppMode::Normal => KeyContext::Normal,
+            AppMode::Search => KeyContext::Search,
+            AppMode::Recovery(_) => KeyContext::Recovery,
+            AppMode::Reload(..) => KeyContext::Reload,
+            AppMode::Modal(modal) => match modal.modal_type {
+                ModalType::StudentDetail(_) => KeyContext::StudentDetail,
+                ModalType::DeleteConfirmation(..) | ModalType::PurgeConfirmation(..) => KeyContext::DeleteConfirmation,
+                _ => {
+                    if modal.is_dropdown_open() { KeyContext::Dropdown } else { KeyContext::Form }
                 }
             },
-            ui::UiElement::ActionButton(action) => {
-                match action {
-                    ui::ActionButton::Add => self.show_add_modal(),
-                    ui::ActionButton::Edit => self.show_edit_modal(),
-                    ui::ActionButton::Delete => self.show_delete_modal(),
-                    ui::ActionButton::Search => self.mode = AppMode::Search,
-                    ui::ActionButton::Refresh => {
-                        self.refresh_data();
-                        self.state.show_notification("Data refreshed".to_string());
-                    },
+        }
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) -> Result<()> {
+        // The help overlay takes every key while it is open
+        let context = if self.state.show_help { KeyContext::Help } else { self.key_context() };
+        let chord = KeyChord::from(key);
+        self.pending_keys.push(chord);
+        let action = match self.keymap.lookup(context, &self.pending_keys) {
+            Lookup::Pending => return Ok(()),
+            Lookup::Action(action) => {
+                self.pending_keys.clear();
+                Some(action)
+            }
+            Lookup::None => {
+                // A sequence that went nowhere is dropped and the last key counts on its own
+                let abandoned = std::mem::take(&mut self.pending_keys);
+                if abandoned.len() > 1 {
+                    return self.handle_key_event(key);
                 }
+                None
+            }
+        };
+
+        if self.state.show_help {
+            if let Some(action) = action {
+                self.handle_help_action(action);
+            }
+            return Ok(());
+        }
+        if action == Some(Action::Help) {
+            self.state.show_help = true;
+            self.state.help_scroll = 0;
+            return Ok(());
+        }
+
+        // Characters without a binding are typed into the focused text field
+        let typed = chord.typed_char().filter(|_| action.is_none() && context.accepts_text());
+
+        match self.mode {
+            AppMode::Normal => match action {
+                Some(action) => self.handle_normal_action(action),
+                None => Ok(()),
+            },
+            AppMode::Search => self.handle_search_action(action, typed),
+            AppMode::Modal(_) => {
+                self.run_modal_action(action, typed);
+                Ok(())
+            }
+            AppMode::Recovery(_) => match action {
+                Some(action) => self.handle_recovery_action(action),
+                None => Ok(()),
+            },
+            AppMode::Reload(..) => match action {
+                Some(action) => self.handle_reload_action(action),
+                None => Ok(()),
             },
-            ui::UiElement::None => {},
-            _ => {},
         }
+    }
 
-        Ok(())
+    fn handle_help_action(&mut self, action: Action) {
+        // Scrolling past the end is clamped when the overlay is drawn
+        let scroll = &mut self.state.help_scroll;
+        match action {
+            Action::CloseHelp => self.state.show_help = false,
+            Action::Up => *scroll = scroll.saturating_sub(1),
+            Action::Down => *scroll = scroll.saturating_add(1),
+            Action::PageUp => *scroll = scroll.saturating_sub(HELP_PAGE),
+            Action::PageDown => *scroll = scroll.saturating_add(HELP_PAGE),
+            Action::First => *scroll = 0,
+            Action::Last => *scroll = u16::MAX,
+            _ => {}
+        }
     }
 
-    fn handle_key_event(&mut self, key: KeyCode) -> Result<()> {
-        // First determine what type of mode we're in and handle accordingly
-        match self.mode {
-            AppMode::Normal => {
-                return self.handle_normal_mode(key);
+    fn handle_recovery_action(&mut self, action: Action) -> Result<()> {
+        match action {
+            Action::Quit => {
+                self.should_quit = true;
             }
-            AppMode::Search => {
-                return self.handle_search_mode(key);
+            Action::OpenReadOnly => {
+                self.data_manager.open_read_only();
+                self.refresh_data();
+                self.mode = AppMode::Normal;
+                self.state.show_notification("Opened read-only: changes are disabled".to_string());
             }
-            AppMode::Modal(_) => {
-                // For modal mode, we need a different approach to avoid borrow conflicts
-                return self.handle_modal_key_event(key);
+            Action::RestoreBackups => match self.data_manager.restore_backups() {
+                Ok(()) => self.finish_recovery("Restored data from backups".to_string()),
+                Err(err) => self.mode = AppMode::Recovery(Some(format!("{:#}", err))),
+            },
+            Action::SkipInvalidRecords => match self.data_manager.skip_invalid_records() {
+                Ok(skipped) => self.finish_recovery(format!("Skipped {} unreadable record(s)", skipped)),
+                Err(err) => self.mode = AppMode::Recovery(Some(format!("{:#}", err))),
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // Leave the recovery screen once every file reads cleanly
+    fn finish_recovery(&mut self, message: String) {
+        if self.data_manager.load_errors().is_empty() {
+            self.mode = AppMode::Normal;
+            self.refresh_data();
+            self.state.show_notification(message);
+        } else {
+            self.mode = AppMode::Recovery(Some("Some files still could not be read".to_string()));
+        }
+    }
+
+    fn handle_reload_action(&mut self, action: Action) -> Result<()> {
+        match action {
+            Action::Reload => self.reload(false),
+            Action::ReloadKeepingMine => self.reload(true),
+            Action::Cancel => {
+                self.data_manager.ignore_disk_changes();
+                self.mode = AppMode::Normal;
+                self.state.show_notification("Kept the data in memory".to_string());
             }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // Read the data files again. Unsaved changes are merged in, after asking first.
+    fn reload_from_disk(&mut self) {
+        match self.data_manager.disk_changes() {
+            Ok(changes) if changes.unsaved > 0 => self.mode = AppMode::Reload(changes, None),
+            Ok(_) => self.reload(false),
+            Err(err) => self.state.show_notification(format!("Cannot reload: {:#}", err)),
         }
     }
 
-    fn handle_normal_mode(&mut self, key: KeyCode) -> Result<()> {
-        match key {
-            KeyCode::Char('q') => {
+    fn reload(&mut self, keep_mine: bool) {
+        match self.data_manager.reload(keep_mine) {
+            Ok(merged) => {
+                self.mode = AppMode::Normal;
+                self.refresh_data();
+                let message = match merged {
+                    0 => "Reloaded from disk".to_string(),
+                    merged => format!("Reloaded from disk and merged {} unsaved change(s)", merged),
+                };
+                self.state.show_notification(message);
+            }
+            Err(err) => match &mut self.mode {
+                AppMode::Reload(_, message) => *message = Some(format!("{:#}", err)),
+                _ => self.state.show_notification(format!("Cannot reload: {:#}", err)),
+            },
+        }
+    }
+
+    // Ask about data files another program changed, once the user is back at the tables
+    fn check_disk_changes(&mut self) {
+        if !matches!(self.mode, AppMode::Normal) || self.data_manager.is_read_only() {
+            return;
+        }
+        let files = self.data_manager.changed_files();
+        if !files.is_empty() {
+            self.open_reload_prompt(files);
+        }
+    }
+
+    // Ask whether to reload, showing what changed on disk and which unsaved records clash
+    fn open_reload_prompt(&mut self, files: Vec<String>) {
+        // A file caught halfway through being written is read again on Enter
+        self.mode = match self.data_manager.disk_changes() {
+            Ok(changes) => AppMode::Reload(changes, None),
+            Err(err) => AppMode::Reload(DiskChanges { files, ..DiskChanges::default() }, Some(format!("{:#}", err))),
+        };
+    }
+
+    fn handle_normal_action(&mut self, action: Action) -> Result<()> {
+        let row_count = self.visible_row_count();
+        match action {
+            Action::Quit => {
                 self.should_quit = true;
             }
-            KeyCode::Char('f') => {
+            Action::Search => {
                 self.mode = AppMode::Search;
             }
-            KeyCode::Char('a') => {
+            Action::Add => {
                 self.show_add_modal();
             }
-            KeyCode::Char('e') => {
+            Action::Edit => {
                 self.show_edit_modal();
             }
-            KeyCode::Char('d') => {
+            Action::Delete => {
                 self.show_delete_modal();
             }
-            KeyCode::Char('r') => {
+            Action::OpenDetails => {
+                self.show_detail_modal();
+            }
+            Action::Refresh => self.reload_from_disk(),
+            Action::Undo => {
+                let message = match self.data_manager.undo() {
+                    Ok(Some(description)) => format!("Undone: {}", description),
+                    Ok(None) => "Nothing to undo".to_string(),
+                    Err(err) => format!("Cannot undo: {:#}", err),
+                };
                 self.refresh_data();
-                self.state.show_notification("Data refreshed".to_string());
+                self.state.show_notification(message);
             }
-            KeyCode::Tab => {
-                self.state.active_tab = self.state.active_tab.next();
+            Action::Redo => {
+                let message = match self.data_manager.redo() {
+                    Ok(Some(description)) => format!("Redone: {}", description),
+                    Ok(None) => "Nothing to redo".to_string(),
+                    Err(err) => format!("Cannot redo: {:#}", err),
+                };
                 self.refresh_data();
+                self.state.show_notification(message);
             }
-            KeyCode::Char('1') => {
-                self.state.active_tab = ActiveTab::Students;
+            Action::Restore => self.restore_from_trash(),
+            Action::NextTab => {
+                self.state.active_tab = self.state.active_tab.next();
                 self.refresh_data();
             }
-            KeyCode::Char('2') => {
-                self.state.active_tab = ActiveTab::Teachers;
+            Action::PreviousTab => {
+                self.state.active_tab = self.state.active_tab.previous();
                 self.refresh_data();
             }
-            KeyCode::Char('3') => {
-                self.state.active_tab = ActiveTab::Faculties;
+            Action::ShowTab(tab) => {
+                self.state.active_tab = tab;
                 self.refresh_data();
             }
-            KeyCode::Up => {
-                self.state.select_previous();
+            Action::Up => self.state.select_previous(row_count),
+            Action::Down => self.state.select_next(row_count),
+            Action::PageUp => self.state.page_up(row_count),
+            Action::PageDown => self.state.page_down(row_count),
+            Action::First => self.state.select_first(row_count),
+            Action::Last => self.state.select_last(row_count),
+            Action::ToggleDetailPane => {
+                self.state.show_detail_pane = !self.state.show_detail_pane;
             }
-            KeyCode::Down => {
-                self.state.select_next();
+            Action::SortNextColumn => {
+                self.change_sort(|sort, column_count| sort.next_column(column_count));
+            }
+            Action::ReverseSort => {
+                self.change_sort(|sort, _| sort.reverse());
             }
             _ => {}
         }
@@ -281,24 +532,26 @@ impl App {
         Ok(())
     }
 
-    fn handle_search_mode(&mut self, key: KeyCode) -> Result<()> {
-        match key {
-            KeyCode::Esc => {
+    fn handle_search_action(&mut self, action: Option<Action>, typed: Option<char>) -> Result<()> {
+        match (action, typed) {
+            (Some(Action::CancelSearch), _) => {
+                // Cancel the search and show all records again
+                self.state.search_query.clear();
                 self.mode = AppMode::Normal;
                 self.refresh_data();
             }
-            KeyCode::Enter => {
+            (Some(Action::ApplySearch), _) => {
+                // Keep the filter applied and return to table navigation
                 self.perform_search();
                 self.mode = AppMode::Normal;
             }
-            KeyCode::Backspace => {
-                if !self.state.search_query.is_empty() {
-                    let new_len = self.state.search_query.len() - 1;
-                    self.state.search_query.truncate(new_len);
-                }
+            // Moving the cursor leaves the filter and selection alone
+            (Some(action), _) if action.edits_text() && self.state.search_query.apply(action) => {
+                self.refresh_data();
             }
-            KeyCode::Char(c) => {
-                self.state.search_query.push(c);
+            (_, Some(c)) => {
+                self.state.search_query.insert(c);
+                self.refresh_data();
             }
             _ => {}
         }
@@ -306,44 +559,105 @@ impl App {
         Ok(())
     }
 
-    fn handle_modal_key_event(&mut self, key: KeyCode) -> Result<()> {
+    // A save that fails keeps the form open with the reason instead of quitting. When
+    // another instance changed the same record, the edit stays in memory and the reload
+    // prompt offers to merge it. Keys and mouse clicks both go through here.
+    fn run_modal_action(&mut self, action: Option<Action>, typed: Option<char>) {
+        match self.handle_modal_action(action, typed) {
+            Err(err) if err.is::<Conflict>() => {
+                self.state.show_notification(format!("Not saved: {:#}", err));
+                self.mode = AppMode::Normal;
+                self.open_reload_prompt(Vec::new());
+            }
+            Err(err) => self.state.show_notification(format!("Not saved: {:#}", err)),
+            Ok(()) => {}
+        }
+    }
+
+    // Mouse clicks on modal buttons arrive here as actions too, with nothing typed
+    fn handle_modal_action(&mut self, action: Option<Action>, typed: Option<char>) -> Result<()> {
+        // The student detail view has its own list navigation and actions
+        if let AppMode::Modal(modal) = &self.mode
+            && let ModalType::StudentDetail(student_id) = &modal.modal_type
+        {
+            let student_id = student_id.clone();
+            return match action {
+                Some(action) => self.handle_student_detail_action(action, &student_id),
+                None => Ok(()),
+            };
+        }
+
+        // The delete confirmation chooses what happens to dependent records
+        if let AppMode::Modal(modal) = &self.mode
+            && let ModalType::DeleteConfirmation(kind, id, name) = &modal.modal_type
+        {
+            let (kind, id, name) = (*kind, id.clone(), name.clone());
+            return match action {
+                Some(action) => self.handle_delete_confirmation_action(action, kind, &id, &name),
+                None => Ok(()),
+            };
+        }
+
+        // Purging a trash item only needs a yes or no
+        if let AppMode::Modal(modal) = &self.mode
+            && let ModalType::PurgeConfirmation(id, name) = &modal.modal_type
+        {
+            let (id, name) = (id.clone(), name.clone());
+            match action {
+                Some(Action::Confirm) => {
+                    self.mode = AppMode::Normal;
+                    self.purge_from_trash(&id, &name);
+                }
+                Some(Action::Cancel) => self.mode = AppMode::Normal,
+                _ => {}
+            }
+            return Ok(());
+        }
+
         // Handle common modal actions that don't require direct modal access
-        if key == KeyCode::Esc {
+        if action == Some(Action::Cancel) {
+            let mut return_to = None;
             if let AppMode::Modal(modal) = &mut self.mode {
                 // If dropdown is open, close it instead of closing the modal
-                if modal.active_field == 3 && // Major field
-                   matches!(modal.modal_type, ModalType::AddStudent | ModalType::EditStudent(_)) &&
-                   modal.major_dropdown.is_open {
-                    modal.major_dropdown.is_open = false;
+                if modal.is_dropdown_open() {
+                    modal.close_dropdowns();
                     return Ok(());
                 }
+                // Enrollment forms go back to the student they were opened from
+                return_to = modal.enrollment_student_id();
+            }
+            match return_to {
+                Some(student_id) => self.open_student_detail(student_id),
+                None => self.mode = AppMode::Normal,
             }
-            self.mode = AppMode::Normal;
             return Ok(());
         }
 
-        // For Enter key, we need special handling to avoid borrowing conflicts
-        if key == KeyCode::Enter {
-            // Special handling for dropdowns
-            if let AppMode::Modal(modal) = &mut self.mode {
-                // If this is a student form and major field is active
-                if modal.active_field == 3 && // Major field 
-                   matches!(modal.modal_type, ModalType::AddStudent | ModalType::EditStudent(_)) {
-                    if modal.major_dropdown.is_open {
-                        // If dropdown is open, select current item and close dropdown
-                        if let Some(selected) = modal.major_dropdown.selected_item() {
-                            modal.inputs[3].1 = selected.clone();
-                            modal.major_dropdown.is_open = false;
-                        }
-                        return Ok(());
-                    } else {
-                        // Open dropdown when Enter is pressed on the major field
-                        modal.major_dropdown.is_open = true;
-                        return Ok(());
+        // Saving needs special handling to avoid borrowing conflicts
+        if matches!(action, Some(Action::Confirm | Action::ChooseItem | Action::Save)) {
+            // Special handling for dropdowns; Save saves from any field
+            if action != Some(Action::Save) && let AppMode::Modal(modal) = &mut self.mode {
+                // If a dropdown field is active
+                if modal.active_dropdown().is_some() {
+                    if modal.is_dropdown_open() {
+                        // If dropdown is open, apply the current item
+                        modal.choose_dropdown_item();
+                    } else if let Some(dropdown) = modal.active_dropdown() {
+                        // Open dropdown when Enter is pressed on the field
+                        dropdown.is_open = true;
                     }
+                    return Ok(());
                 }
             }
 
+            // Save is disabled while a field is invalid; show every field's error instead
+            if let AppMode::Modal(modal) = &mut self.mode
+                && !modal.is_valid()
+            {
+                modal.reveal_errors();
+                return Ok(());
+            }
+
             // Clone modal data that we need before changing borrowing
             let modal_type = if let AppMode::Modal(modal) = &self.mode {
                 modal.modal_type.clone()
@@ -368,7 +682,9 @@ impl App {
                 ModalType::EditStudent(_) => {
                     if let AppMode::Modal(modal) = &mut self.mode {
                         if let Some(student) = modal.create_student() {
-                            self.data_manager.update_student(student.clone())?;
+                            if !self.data_manager.update_student(student.clone())? {
+                                bail!("record no longer exists");
+                            }
                             self.state.show_notification(format!("Updated student: {}", student.full_name()));
                             self.mode = AppMode::Normal;
                             self.refresh_data();
@@ -392,7 +708,9 @@ impl App {
                 ModalType::EditTeacher(_) => {
                     if let AppMode::Modal(modal) = &mut self.mode {
                         if let Some(teacher) = modal.create_teacher() {
-                            self.data_manager.update_teacher(teacher.clone())?;
+                            if !self.data_manager.update_teacher(teacher.clone())? {
+                                bail!("record no longer exists");
+                            }
                             self.state.show_notification(format!("Updated teacher: {}", teacher.full_name()));
                             self.mode = AppMode::Normal;
                             self.refresh_data();
@@ -416,7 +734,9 @@ impl App {
                 ModalType::EditFaculty(_) => {
                     if let AppMode::Modal(modal) = &mut self.mode {
                         if let Some(faculty) = modal.create_faculty() {
-                            self.data_manager.update_faculty(faculty.clone())?;
+                            if !self.data_manager.update_faculty(faculty.clone())? {
+                                bail!("record no longer exists");
+                            }
                             self.state.show_notification(format!("Updated faculty: {}", faculty.name));
                             self.mode = AppMode::Normal;
                             self.refresh_data();
@@ -425,74 +745,93 @@ impl App {
                         }
                     }
                 }
-                ModalType::DeleteConfirmation(id, name) => {
-                    let success = match self.state.active_tab {
-                        ActiveTab::Students => self.data_manager.delete_student(&id)?,
-                        ActiveTab::Teachers => self.data_manager.delete_teacher(&id)?,
-                        ActiveTab::Faculties => self.data_manager.delete_faculty(&id)?,
-                    };
-                    
-                    if success {
-                        self.state.show_notification(format!("Deleted: {}", name));
-                    } else {
-                        self.state.show_notification(format!("Failed to delete: {}", name));
+                ModalType::AddCourse => {
+                    if let AppMode::Modal(modal) = &mut self.mode {
+                        if let Some(course) = modal.create_course() {
+                            self.data_manager.add_course(course.clone())?;
+                            self.state.show_notification(format!("Added course: {}", course.title));
+                            self.mode = AppMode::Normal;
+                            self.refresh_data();
+                        } else {
+                            self.state.show_notification("Invalid course data".to_string());
+                        }
                     }
-                    
-                    self.mode = AppMode::Normal;
-                    self.refresh_data();
                 }
-                ModalType::Message(_) => {
-                    self.mode = AppMode::Normal;
+                ModalType::EditCourse(_) => {
+                    if let AppMode::Modal(modal) = &mut self.mode {
+                        if let Some(course) = modal.create_course() {
+                            if !self.data_manager.update_course(course.clone())? {
+                                bail!("record no longer exists");
+                            }
+                            self.state.show_notification(format!("Updated course: {}", course.title));
+                            self.mode = AppMode::Normal;
+                            self.refresh_data();
+                        } else {
+                            self.state.show_notification("Invalid course data".to_string());
+                        }
+                    }
+                }
+                ModalType::AddEnrollment(_) | ModalType::EditEnrollment(_) => {
+                    if let AppMode::Modal(modal) = &mut self.mode {
+                        if let Some(enrollment) = modal.create_enrollment() {
+                            let is_edit = matches!(modal.modal_type, ModalType::EditEnrollment(_));
+                            let student_id = enrollment.student_id.clone();
+                            let course = self.data_manager.get_course_by_id(&enrollment.course_id)
+                                .map(|c| c.code.clone())
+                                .unwrap_or_default();
+                            if is_edit {
+                                if !self.data_manager.update_enrollment(enrollment)? {
+                                    bail!("record no longer exists");
+                                }
+                                self.state.show_notification(format!("Updated enrollment: {}", course));
+                            } else {
+                                self.data_manager.add_enrollment(enrollment)?;
+                                self.state.show_notification(format!("Enrolled in: {}", course));
+                            }
+                            self.open_student_detail(student_id);
+                        } else {
+                            self.state.show_notification("Invalid enrollment data".to_string());
+                        }
+                    }
                 }
+                ModalType::StudentDetail(_) | ModalType::DeleteConfirmation(..) | ModalType::PurgeConfirmation(..) => {}
             }
-            
+
             return Ok(());
         }
         
         // Handle other modal actions
         if let AppMode::Modal(modal) = &mut self.mode {
-            match key {
-                KeyCode::Up => {
-                    // If dropdown is open, navigate dropdown
-                    if modal.active_field == 3 && 
-                       matches!(modal.modal_type, ModalType::AddStudent | ModalType::EditStudent(_)) &&
-                       modal.major_dropdown.is_open {
-                        modal.major_dropdown.select_prev();
-                    } else {
-                        modal.prev_field();
+            match (action, typed) {
+                (Some(Action::Up), _) => {
+                    if let Some(dropdown) = modal.active_dropdown() {
+                        dropdown.select_prev();
                     }
                 }
-                KeyCode::Down => {
-                    // If dropdown is open, navigate dropdown
-                    if modal.active_field == 3 && 
-                       matches!(modal.modal_type, ModalType::AddStudent | ModalType::EditStudent(_)) &&
-                       modal.major_dropdown.is_open {
-                        modal.major_dropdown.select_next();
-                    } else {
-                        modal.next_field();
+                (Some(Action::Down), _) => {
+                    if let Some(dropdown) = modal.active_dropdown() {
+                        dropdown.select_next();
                     }
                 }
-                KeyCode::Tab => {
+                (Some(Action::NextField), _) => {
                     modal.next_field();
                 }
-                KeyCode::BackTab => {
+                (Some(Action::PreviousField), _) => {
                     modal.prev_field();
                 }
-                KeyCode::Backspace => {
-                    modal.backspace();
+                (Some(action), _) if action.edits_text() => {
+                    modal.edit(action);
                 }
-                KeyCode::Char(' ') => {
-                    // Special handling for Space key on Major field - toggle dropdown
-                    if modal.active_field == 3 && 
-                       matches!(modal.modal_type, ModalType::AddStudent | ModalType::EditStudent(_)) {
-                        modal.major_dropdown.toggle_open();
-                    } else {
-                        modal.input(' ');
+                (Some(Action::ToggleDropdown), _) => {
+                    // Space toggles dropdown fields and is typed into text fields
+                    match modal.active_dropdown() {
+                        Some(dropdown) => dropdown.toggle_open(),
+                        None => modal.type_char(' '),
                     }
                 }
-                KeyCode::Char(c) => {
+                (_, Some(c)) => {
                     // Handle regular character input (including 'j' and 'k')
-                    modal.input(c);
+                    modal.type_char(c);
                 }
                 _ => {}
             }
@@ -501,202 +840,398 @@ impl App {
         Ok(())
     }
 
-    fn perform_search(&mut self) {
-        if self.state.search_query.is_empty() {
-            self.refresh_data();
-            return;
-        }
+    fn handle_delete_confirmation_action(&mut self, action: Action, kind: EntityKind, id: &str, name: &str) -> Result<()> {
+        let AppMode::Modal(modal) = &mut self.mode else {
+            return Ok(());
+        };
 
-        let query = &self.state.search_query;
-        
-        match self.state.active_tab {
-            ActiveTab::Students => {
-                let results = self.data_manager.search_students(query);
-                self.state.show_notification(format!("Found {} matching students", results.len()));
+        match action {
+            Action::Cancel => {
+                self.mode = AppMode::Normal;
+            }
+            Action::PreviousChoice => {
+                modal.delete_choice = modal.delete_choice.previous();
             }
-            ActiveTab::Teachers => {
-                let results = self.data_manager.search_teachers(query);
-                self.state.show_notification(format!("Found {} matching teachers", results.len()));
+            Action::NextChoice => {
+                modal.delete_choice = modal.delete_choice.next();
             }
-            ActiveTab::Faculties => {
-                let results = self.data_manager.search_faculties(query);
-                self.state.show_notification(format!("Found {} matching faculties", results.len()));
+            Action::Up if modal.delete_choice == DeleteChoice::Reassign => {
+                modal.reassign_dropdown.select_prev();
             }
+            Action::Down if modal.delete_choice == DeleteChoice::Reassign => {
+                modal.reassign_dropdown.select_next();
+            }
+            Action::Confirm => {
+                let Some(policy) = modal.delete_policy() else {
+                    self.state.show_notification(format!("No other {} to reassign to", kind));
+                    return Ok(());
+                };
+                let result = match kind {
+                    EntityKind::Student => self.data_manager.delete_student(id, &policy),
+                    EntityKind::Teacher => self.data_manager.delete_teacher(id, &policy),
+                    EntityKind::Faculty => self.data_manager.delete_faculty(id, &policy),
+                    EntityKind::Course => self.data_manager.delete_course(id, &policy),
+                };
+
+                match result {
+                    Ok(true) => self.state.show_notification(format!("Deleted: {}", name)),
+                    Ok(false) => self.state.show_notification(format!("Failed to delete: {}", name)),
+                    // A blocked delete leaves every record untouched
+                    Err(err) => self.state.show_notification(format!("Cannot delete {}: {}", name, err)),
+                }
+                
+                self.mode = AppMode::Normal;
+                self.refresh_data();
+            }
+            _ => {}
         }
+        Ok(())
     }
 
-    fn refresh_data(&mut self) {
-        // Reset table selection if needed
-        match self.state.active_tab {
-            ActiveTab::Students => {
-                if self.data_manager.get_all_students().is_empty() {
-                    self.state.student_list_state.select(None);
-                } else {
-                    self.state.student_list_state.select(Some(0));
-                }
+    fn handle_student_detail_action(&mut self, action: Action, student_id: &str) -> Result<()> {
+        let enrollment_ids: Vec<String> = self.data_manager
+            .enrollments_for_student(student_id)
+            .iter()
+            .map(|e| e.id.clone())
+            .collect();
+        let AppMode::Modal(modal) = &mut self.mode else {
+            return Ok(());
+        };
+        let selected = modal.list_state.selected()
+            .and_then(|index| enrollment_ids.get(index))
+            .cloned();
+
+        match action {
+            Action::Cancel => {
+                self.mode = AppMode::Normal;
+            }
+            Action::Add | Action::Edit | Action::Delete if self.data_manager.is_read_only() => {
+                self.state.show_notification("Read-only: changes are disabled".to_string());
+            }
+            Action::Up => {
+                let index = modal.list_state.selected().unwrap_or(0);
+                modal.list_state.select(Some(index.saturating_sub(1)));
+            }
+            Action::Down => {
+                let index = modal.list_state.selected().map_or(0, |i| i + 1);
+                modal.list_state.select(Some(index.min(enrollment_ids.len().saturating_sub(1))));
+            }
+            Action::Add => {
+                let modal_type = ModalType::AddEnrollment(student_id.to_string());
+                self.mode = AppMode::Modal(Box::new(Modal::new(modal_type, &self.data_manager)));
             }
-            ActiveTab::Teachers => {
-                if self.data_manager.get_all_teachers().is_empty() {
-                    self.state.teacher_list_state.select(None);
-                } else {
-                    self.state.teacher_list_state.select(Some(0));
+            Action::Edit => {
+                match selected.and_then(|id| self.data_manager.get_enrollment_by_id(&id)) {
+                    Some(enrollment) => {
+                        let modal_type = ModalType::EditEnrollment(enrollment.clone());
+                        self.mode = AppMode::Modal(Box::new(Modal::new(modal_type, &self.data_manager)));
+                    }
+                    None => self.state.show_notification("No enrollment selected".to_string()),
                 }
             }
-            ActiveTab::Faculties => {
-                if self.data_manager.get_all_faculties().is_empty() {
-                    self.state.faculty_list_state.select(None);
-                } else {
-                    self.state.faculty_list_state.select(Some(0));
+            Action::Delete => {
+                match selected {
+                    Some(id) => {
+                        self.data_manager.delete_enrollment(&id)?;
+                        self.state.show_notification("Enrollment removed".to_string());
+                        if let AppMode::Modal(modal) = &mut self.mode {
+                            let index = modal.list_state.selected().unwrap_or(0);
+                            modal.list_state.select(Some(index.min(enrollment_ids.len().saturating_sub(2))));
+                        }
+                    }
+                    None => self.state.show_notification("No enrollment selected".to_string()),
                 }
             }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn perform_search(&mut self) {
+        if self.state.search_query.is_empty() {
+            self.refresh_data();
+            return;
+        }
+
+        let count = self.visible_row_count();
+        let (total, label) = match self.state.active_tab {
+            ActiveTab::Students => (self.data_manager.get_all_students().len(), "students"),
+            ActiveTab::Teachers => (self.data_manager.get_all_teachers().len(), "teachers"),
+            ActiveTab::Faculties => (self.data_manager.get_all_faculties().len(), "faculties"),
+            ActiveTab::Courses => (self.data_manager.get_all_courses().len(), "courses"),
+            ActiveTab::Audit => (self.data_manager.audit_entries().len(), "audit entries"),
+            ActiveTab::Trash => (self.data_manager.trash_items().len(), "deleted records"),
+        };
+        self.state.show_notification(format!("Found {} of {} matching {}", count, total, label));
+    }
+
+    // Number of rows shown in the current tab after applying the search filter
+    fn visible_row_count(&self) -> usize {
+        let query = self.state.search_query.value();
+        match self.state.active_tab {
+            ActiveTab::Students => self.data_manager.search_students(query).len(),
+            ActiveTab::Teachers => self.data_manager.search_teachers(query).len(),
+            ActiveTab::Faculties => self.data_manager.search_faculties(query).len(),
+            ActiveTab::Courses => self.data_manager.search_courses(query).len(),
+            ActiveTab::Audit => self.data_manager.search_audit(query).len(),
+            ActiveTab::Trash => self.data_manager.search_trash(query).len(),
         }
     }
 
+    // Ids of the rows in the current table, in display order
+    fn visible_ids(&self) -> Vec<String> {
+        let results = ui::visible_records(&self.data_manager, &self.state);
+        match self.state.active_tab {
+            ActiveTab::Students => results.students.iter().map(|s| s.id.clone()).collect(),
+            ActiveTab::Teachers => results.teachers.iter().map(|t| t.id.clone()).collect(),
+            ActiveTab::Faculties => results.faculties.iter().map(|f| f.id.clone()).collect(),
+            ActiveTab::Courses => results.courses.iter().map(|c| c.id.clone()).collect(),
+            ActiveTab::Audit => results.audit.iter().map(|e| e.key()).collect(),
+            ActiveTab::Trash => results.trash.iter().map(|t| t.id.clone()).collect(),
+        }
+    }
+
+    // Id of the record under the selection in the current (filtered, sorted) table
+    fn selected_id(&mut self) -> Option<String> {
+        let index = self.state.get_current_table_state().selected()?;
+        self.visible_ids().into_iter().nth(index)
+    }
+
+    // Change the current table's sort order, keeping the same record selected
+    fn change_sort(&mut self, change: impl FnOnce(&mut ui::TableSort, usize)) {
+        let selected = self.selected_id();
+        let column_count = self.state.active_tab.columns().len();
+        change(self.state.get_current_sort(), column_count);
+
+        if let Some(id) = selected {
+            let index = self.visible_ids().iter().position(|visible| *visible == id);
+            self.state.get_current_table_state().select(index);
+        }
+    }
+
+    fn refresh_data(&mut self) {
+        // Reset table selection to the first visible row, if any
+        let selection = if self.visible_row_count() == 0 { None } else { Some(0) };
+        self.state.get_current_table_state().select(selection);
+    }
+
     fn tick(&mut self) -> Result<()> {
         let now = Instant::now();
         if now.duration_since(self.last_tick) >= self.tick_rate {
             self.last_tick = now;
             self.state.update_notification_timer();
+            self.check_disk_changes();
         }
         
         Ok(())
     }
 
+    // Editing is disabled when the data was opened read-only from the recovery screen,
+    // and the audit log is never edited
+    fn ensure_writable(&mut self) -> bool {
+        if self.data_manager.is_read_only() {
+            self.state.show_notification("Read-only: changes are disabled".to_string());
+            return false;
+        }
+        if self.state.active_tab == ActiveTab::Audit {
+            self.state.show_notification("The audit log can't be changed".to_string());
+            return false;
+        }
+        true
+    }
+
     fn show_add_modal(&mut self) {
+        if !self.ensure_writable() {
+            return;
+        }
         let modal_type = match self.state.active_tab {
             ActiveTab::Students => ModalType::AddStudent,
             ActiveTab::Teachers => ModalType::AddTeacher,
             ActiveTab::Faculties => ModalType::AddFaculty,
+            ActiveTab::Courses => ModalType::AddCourse,
+            ActiveTab::Audit => return,
+            ActiveTab::Trash => {
+                self.state.show_notification("Records can't be added to the trash".to_string());
+                return;
+            }
         };
         
-        self.mode = AppMode::Modal(Modal::new(modal_type));
+        self.mode = AppMode::Modal(Box::new(Modal::new(modal_type, &self.data_manager)));
     }
 
     fn show_edit_modal(&mut self) {
-        match self.state.active_tab {
-            ActiveTab::Students => {
-                let state = &mut self.state.student_list_state;
-                if let Some(index) = state.selected() {
-                    let students = self.data_manager.get_all_students();
-                    if index < students.len() {
-                        let student = students[index].clone();
-                        self.mode = AppMode::Modal(Modal::new(ModalType::EditStudent(student)));
-                    } else {
-                        self.state.show_notification("No student selected".to_string());
-                    }
-                } else {
-                    self.state.show_notification("No student selected".to_string());
-                }
-            }
-            ActiveTab::Teachers => {
-                let state = &mut self.state.teacher_list_state;
-                if let Some(index) = state.selected() {
-                    let teachers = self.data_manager.get_all_teachers();
-                    if index < teachers.len() {
-                        let teacher = teachers[index].clone();
-                        self.mode = AppMode::Modal(Modal::new(ModalType::EditTeacher(teacher)));
-                    } else {
-                        self.state.show_notification("No teacher selected".to_string());
-                    }
-                } else {
-                    self.state.show_notification("No teacher selected".to_string());
-                }
-            }
-            ActiveTab::Faculties => {
-                let state = &mut self.state.faculty_list_state;
-                if let Some(index) = state.selected() {
-                    let faculties = self.data_manager.get_all_faculties();
-                    if index < faculties.len() {
-                        let faculty = faculties[index].clone();
-                        self.mode = AppMode::Modal(Modal::new(ModalType::EditFaculty(faculty)));
-                    } else {
-                        self.state.show_notification("No faculty selected".to_string());
-                    }
-                } else {
-                    self.state.show_notification("No faculty selected".to_string());
-                }
+        if !self.ensure_writable() {
+            return;
+        }
+        let id = self.selected_id().unwrap_or_default();
+        let modal_type = match self.state.active_tab {
+            ActiveTab::Students => self.data_manager.get_student_by_id(&id)
+                .map(|student| ModalType::EditStudent(student.clone())),
+            ActiveTab::Teachers => self.data_manager.get_teacher_by_id(&id)
+                .map(|teacher| ModalType::EditTeacher(teacher.clone())),
+            ActiveTab::Faculties => self.data_manager.get_faculty_by_id(&id)
+                .map(|faculty| ModalType::EditFaculty(faculty.clone())),
+            ActiveTab::Courses => self.data_manager.get_course_by_id(&id)
+                .map(|course| ModalType::EditCourse(course.clone())),
+            ActiveTab::Audit => return,
+            ActiveTab::Trash => {
+                self.state.show_notification("Restore the record before editing it".to_string());
+                return;
             }
+        };
+
+        match modal_type {
+            Some(modal_type) => self.mode = AppMode::Modal(Box::new(Modal::new(modal_type, &self.data_manager))),
+            None => self.notify_nothing_selected(),
         }
     }
 
     fn show_delete_modal(&mut self) {
-        match self.state.active_tab {
-            ActiveTab::Students => {
-                let state = &mut self.state.student_list_state;
-                if let Some(index) = state.selected() {
-                    let students = self.data_manager.get_all_students();
-                    if index < students.len() {
-                        let student = &students[index];
-                        let modal_type = ModalType::DeleteConfirmation(
-                            student.id.clone(),
-                            student.full_name(),
-                        );
-                        self.mode = AppMode::Modal(Modal::new(modal_type));
-                    } else {
-                        self.state.show_notification("No student selected".to_string());
-                    }
-                } else {
-                    self.state.show_notification("No student selected".to_string());
-                }
-            }
-            ActiveTab::Teachers => {
-                let state = &mut self.state.teacher_list_state;
-                if let Some(index) = state.selected() {
-                    let teachers = self.data_manager.get_all_teachers();
-                    if index < teachers.len() {
-                        let teacher = &teachers[index];
-                        let modal_type = ModalType::DeleteConfirmation(
-                            teacher.id.clone(),
-                            teacher.full_name(),
-                        );
-                        self.mode = AppMode::Modal(Modal::new(modal_type));
-                    } else {
-                        self.state.show_notification("No teacher selected".to_string());
-                    }
-                } else {
-                    self.state.show_notification("No teacher selected".to_string());
-                }
+        if !self.ensure_writable() {
+            return;
+        }
+        if self.state.active_tab == ActiveTab::Trash {
+            self.show_purge_modal();
+            return;
+        }
+        let Some(kind) = self.state.active_tab.entity_kind() else {
+            return;
+        };
+        let id = self.selected_id().unwrap_or_default();
+        let name = match self.state.active_tab {
+            ActiveTab::Students => self.data_manager.get_student_by_id(&id).map(|s| s.full_name()),
+            ActiveTab::Teachers => self.data_manager.get_teacher_by_id(&id).map(|t| t.full_name()),
+            ActiveTab::Faculties => self.data_manager.get_faculty_by_id(&id).map(|f| f.name.clone()),
+            ActiveTab::Courses => self.data_manager.get_course_by_id(&id).map(|c| c.title.clone()),
+            ActiveTab::Audit | ActiveTab::Trash => None,
+        };
+
+        match name {
+            Some(name) => {
+                let modal_type = ModalType::DeleteConfirmation(kind, id, name);
+                self.mode = AppMode::Modal(Box::new(Modal::new(modal_type, &self.data_manager)));
             }
-            ActiveTab::Faculties => {
-                let state = &mut self.state.faculty_list_state;
-                if let Some(index) = state.selected() {
-                    let faculties = self.data_manager.get_all_faculties();
-                    if index < faculties.len() {
-                        let faculty = &faculties[index];
-                        let modal_type = ModalType::DeleteConfirmation(
-                            faculty.id.clone(),
-                            faculty.name.clone(),
-                        );
-                        self.mode = AppMode::Modal(Modal::new(modal_type));
-                    } else {
-                        self.state.show_notification("No faculty selected".to_string());
-                    }
-                } else {
-                    self.state.show_notification("No faculty selected".to_string());
-                }
+            None => self.notify_nothing_selected(),
+        }
+    }
+
+    fn show_detail_modal(&mut self) {
+        // Only students have a detail view with their enrollments
+        if self.state.active_tab != ActiveTab::Students {
+            return;
+        }
+        match self.selected_id() {
+            Some(id) => self.open_student_detail(id),
+            None => self.notify_nothing_selected(),
+        }
+    }
+
+    fn open_student_detail(&mut self, student_id: String) {
+        let modal_type = ModalType::StudentDetail(student_id);
+        self.mode = AppMode::Modal(Box::new(Modal::new(modal_type, &self.data_manager)));
+    }
+
+    fn notify_nothing_selected(&mut self) {
+        let message = match self.state.active_tab {
+            ActiveTab::Students => "No student selected",
+            ActiveTab::Teachers => "No teacher selected",
+            ActiveTab::Faculties => "No faculty selected",
+            ActiveTab::Courses => "No course selected",
+            ActiveTab::Audit => "No audit entry selected",
+            ActiveTab::Trash => "No deleted record selected",
+        };
+        self.state.show_notification(message.to_string());
+    }
+
+    // Put the selected trash item's records back where they were deleted from
+    fn restore_from_trash(&mut self) {
+        if self.state.active_tab != ActiveTab::Trash {
+            self.state.show_notification("Switch to the trash tab to restore deleted records".to_string());
+            return;
+        }
+        if !self.ensure_writable() {
+            return;
+        }
+        let Some(item) = self.selected_id().and_then(|id| self.data_manager.get_trash_item(&id)) else {
+            self.notify_nothing_selected();
+            return;
+        };
+        let (id, name) = (item.id.clone(), item.name.clone());
+        let message = match self.data_manager.restore_from_trash(&id) {
+            Ok(true) => format!("Restored: {}", name),
+            Ok(false) => format!("Failed to restore: {}", name),
+            Err(err) => format!("Cannot restore {}: {:#}", name, err),
+        };
+        self.refresh_data();
+        self.state.show_notification(message);
+    }
+
+    // Delete trash items older than the retention period, once when the application starts.
+    // Damaged data is left alone until it has been recovered.
+    fn purge_expired_trash(&mut self, days: u32) {
+        if !self.data_manager.load_errors().is_empty() {
+            return;
+        }
+        match self.data_manager.purge_expired_trash(days) {
+            Ok(0) => {}
+            Ok(purged) => {
+                self.refresh_data();
+                self.state.show_notification(format!("Purged {} expired trash item(s)", purged));
             }
+            Err(err) => self.state.show_notification(format!("Could not purge expired trash: {:#}", err)),
         }
     }
-}
 
-// Helper function to get terminal dimensions
-fn terminal_size() -> Rect {
-    let size = crossterm::terminal::size()
-        .unwrap_or((80, 24));
-    Rect::new(0, 0, size.0, size.1)
+    fn show_purge_modal(&mut self) {
+        let Some(item) = self.selected_id().and_then(|id| self.data_manager.get_trash_item(&id)) else {
+            self.notify_nothing_selected();
+            return;
+        };
+        let modal_type = ModalType::PurgeConfirmation(item.id.clone(), item.name.clone());
+        self.mode = AppMode::Modal(Box::new(Modal::new(modal_type, &self.data_manager)));
+    }
+
+    // Delete a trash item for good; undo can still bring it back this session
+    fn purge_from_trash(&mut self, id: &str, name: &str) {
+        let message = match self.data_manager.purge_from_trash(id) {
+            Ok(true) => format!("Purged: {}", name),
+            Ok(false) => format!("Failed to purge: {}", name),
+            Err(err) => format!("Cannot purge {}: {:#}", name, err),
+        };
+        self.refresh_data();
+        self.state.show_notification(message);
+    }
 }
 
 fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let config = Config::load()?;
+    let data_dir = cli.data_dir.unwrap_or(config.data_dir.clone());
+
+    // Open the data before taking over the terminal, so startup errors print normally
+    let lock = DataLock::open(&data_dir)?;
+    let storage = {
+        let _guard = lock.acquire()?;
+        storage::open(cli.storage, &data_dir, &config.rules, config.backup_count)?
+    };
+    let audit = AuditLog::open(&data_dir)?;
+    let mut data_manager = DataManager::new(storage, audit, lock, config.rules.clone())?;
+    if let Some(command) = cli.command {
+        return cli::run(command, &mut data_manager);
+    }
+    let mut app = App::new(data_manager, &config);
+    app.purge_expired_trash(config.trash_retention_days);
+
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture, EnableBracketedPaste)?;
     
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
     
-    // Create and run app
-    let mut app = App::new()?;
+    // Run app
     let result = app.run(&mut terminal);
     
     // Restore terminal
@@ -704,7 +1239,8 @@ fn main() -> Result<()> {
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableBracketedPaste
     )?;
     terminal.show_cursor()?;
     
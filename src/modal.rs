@@ -1,16 +1,20 @@
-use crate::models::{Faculty, Student, Teacher};
-use crate::terminal_size;
+use crate::data_manager::{DataManager, DeletePolicy};
+use crate::form::{self, FormField, FormSchema, InputField};
+use crate::keymap::{Action, KeyContext, Keymap};
+use crate::models::{Course, EntityKind, Enrollment, Faculty, GradingScale, Student, Teacher};
+use crate::ui::ModalButton;
+use crate::rules::Rules;
+use crate::hit_map::{Hit, HitMap};
 use crate::widgets::{self, DropdownState};
 use crate::ui::centered_rect; // Import centered_rect from ui.rs
-use anyhow::Result;
 use ratatui::{
     layout::{Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Modifier, Style},
-    text::{Line, Span, Text},
-    widgets::{Block, BorderType, Borders, Clear, List, ListItem, Padding, Paragraph},
+    text::{Line, Span},
+    widgets::{Block, BorderType, Borders, Clear, Padding, Paragraph, Row, Table, TableState},
     Frame,
 };
-use std::fmt;
+use std::rc::Rc;
 
 #[derive(Debug, Clone)]
 pub enum ModalType {
@@ -20,95 +24,52 @@ pub enum ModalType {
     EditTeacher(Teacher),
     AddFaculty,
     EditFaculty(Faculty),
-    DeleteConfirmation(String, String), // (id, name) for entity to delete
-    Message(String),                     // General message display
+    AddCourse,
+    EditCourse(Course),
+    StudentDetail(String),              // id of the student whose enrollments are shown
+    AddEnrollment(String),              // id of the student to enroll
+    EditEnrollment(Enrollment),
+    DeleteConfirmation(EntityKind, String, String), // (kind, id, name) for entity to delete
+    PurgeConfirmation(String, String), // (id, name) of the trash item to delete for good
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum InputField {
-    FirstName,
-    LastName,
-    Age,
-    Major,
-    Gpa,
-    Department,
-    Title,
-    Name,
-    Building,
-    HeadName,
-    EstablishedYear,
-    NumStaff,
-    None,
-}
-
-impl fmt::Display for InputField {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let label = match self {
-            InputField::FirstName => "First Name",
-            InputField::LastName => "Last Name",
-            InputField::Age => "Age",
-            InputField::Major => "Major",
-            InputField::Gpa => "GPA",
-            InputField::Department => "Department",
-            InputField::Title => "Title",
-            InputField::Name => "Name",
-            InputField::Building => "Building",
-            InputField::HeadName => "Head Name",
-            InputField::EstablishedYear => "Established Year",
-            InputField::NumStaff => "Number of Staff",
-            InputField::None => "",
-        };
-        write!(f, "{}", label)
+impl ModalType {
+    // Schema of the form this modal shows, if it is a form
+    pub fn form(&self) -> Option<&'static FormSchema> {
+        match self {
+            ModalType::AddStudent | ModalType::EditStudent(_) => Some(&form::STUDENT_FORM),
+            ModalType::AddTeacher | ModalType::EditTeacher(_) => Some(&form::TEACHER_FORM),
+            ModalType::AddFaculty | ModalType::EditFaculty(_) => Some(&form::FACULTY_FORM),
+            ModalType::AddCourse | ModalType::EditCourse(_) => Some(&form::COURSE_FORM),
+            ModalType::AddEnrollment(_) | ModalType::EditEnrollment(_) => Some(&form::ENROLLMENT_FORM),
+            ModalType::StudentDetail(_) | ModalType::DeleteConfirmation(..) | ModalType::PurgeConfirmation(..) => None,
+        }
     }
-}
 
-pub struct Modal {
-    pub modal_type: ModalType,
-    pub active: bool,
-    pub inputs: Vec<(InputField, String)>,
-    pub active_field: usize,
-    pub confirm: bool,
-    pub major_dropdown: DropdownState,
-}
+    pub fn is_edit(&self) -> bool {
+        matches!(
+            self,
+            ModalType::EditStudent(_) | ModalType::EditTeacher(_) | ModalType::EditFaculty(_)
+                | ModalType::EditCourse(_) | ModalType::EditEnrollment(_)
+        )
+    }
 
-impl Modal {
-    pub fn new(modal_type: ModalType) -> Self {
-        let inputs = match &modal_type {
-            ModalType::AddStudent => vec![
-                (InputField::FirstName, String::new()),
-                (InputField::LastName, String::new()),
-                (InputField::Age, String::new()),
-                (InputField::Major, String::new()),
-                (InputField::Gpa, String::new()),
-            ],
+    // Form values of the record being edited, with grades on `scale`
+    fn record_values(&self, scale: GradingScale) -> Vec<(InputField, String)> {
+        match self {
             ModalType::EditStudent(student) => vec![
                 (InputField::FirstName, student.first_name.clone()),
                 (InputField::LastName, student.last_name.clone()),
                 (InputField::Age, student.age.to_string()),
                 (InputField::Major, student.major.clone()),
-                (InputField::Gpa, student.gpa.to_string()),
-            ],
-            ModalType::AddTeacher => vec![
-                (InputField::FirstName, String::new()),
-                (InputField::LastName, String::new()),
-                (InputField::Age, String::new()),
-                (InputField::Department, String::new()),
-                (InputField::Title, String::new()),
             ],
             ModalType::EditTeacher(teacher) => vec![
                 (InputField::FirstName, teacher.first_name.clone()),
                 (InputField::LastName, teacher.last_name.clone()),
                 (InputField::Age, teacher.age.to_string()),
-                (InputField::Department, teacher.department.clone()),
+                (InputField::Faculty, teacher.faculty_id.clone()),
                 (InputField::Title, teacher.title.clone()),
             ],
-            ModalType::AddFaculty => vec![
-                (InputField::Name, String::new()),
-                (InputField::Building, String::new()),
-                (InputField::HeadName, String::new()),
-                (InputField::EstablishedYear, String::new()),
-                (InputField::NumStaff, String::new()),
-            ],
             ModalType::EditFaculty(faculty) => vec![
                 (InputField::Name, faculty.name.clone()),
                 (InputField::Building, faculty.building.clone()),
@@ -116,225 +77,410 @@ impl Modal {
                 (InputField::EstablishedYear, faculty.established_year.to_string()),
                 (InputField::NumStaff, faculty.num_staff.to_string()),
             ],
-            ModalType::DeleteConfirmation(_, _) => vec![],
-            ModalType::Message(_) => vec![],
+            ModalType::EditCourse(course) => vec![
+                (InputField::Code, course.code.clone()),
+                (InputField::Title, course.title.clone()),
+                (InputField::Credits, course.credits.to_string()),
+                (InputField::Faculty, course.faculty_id.clone()),
+                (InputField::Teachers, course.teacher_ids.join(",")),
+            ],
+            ModalType::EditEnrollment(enrollment) => vec![
+                (InputField::Course, enrollment.course_id.clone()),
+                (InputField::Term, enrollment.term.clone()),
+                (InputField::Grade, enrollment.grade.as_ref().map(|g| g.display_in(scale)).unwrap_or_default()),
+            ],
+            _ => Vec::new(),
+        }
+    }
+}
+
+// How to treat dependent records, chosen in the delete confirmation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeleteChoice {
+    Block,
+    Cascade,
+    Reassign,
+}
+
+impl DeleteChoice {
+    pub const ALL: [DeleteChoice; 3] = [DeleteChoice::Block, DeleteChoice::Cascade, DeleteChoice::Reassign];
+
+    pub fn next(&self) -> Self {
+        match self {
+            DeleteChoice::Block => DeleteChoice::Cascade,
+            DeleteChoice::Cascade => DeleteChoice::Reassign,
+            DeleteChoice::Reassign => DeleteChoice::Block,
+        }
+    }
+
+    pub fn previous(&self) -> Self {
+        match self {
+            DeleteChoice::Block => DeleteChoice::Reassign,
+            DeleteChoice::Cascade => DeleteChoice::Block,
+            DeleteChoice::Reassign => DeleteChoice::Cascade,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            DeleteChoice::Block => "Block",
+            DeleteChoice::Cascade => "Cascade",
+            DeleteChoice::Reassign => "Reassign",
+        }
+    }
+}
+
+pub struct Modal {
+    pub modal_type: ModalType,
+    pub active: bool,
+    // Fields of a form modal, in the order of its schema
+    pub fields: Vec<FormField>,
+    pub active_field: usize,
+    // Selected row in list-style modals such as the student detail view
+    pub list_state: TableState,
+    // Records referencing the entity in a delete confirmation, and what to do with them
+    pub dependents: Vec<String>,
+    pub delete_choice: DeleteChoice,
+    pub reassign_dropdown: DropdownState,
+    // Limits the form's fields are checked against
+    pub rules: Rules,
+}
+
+impl Modal {
+    pub fn new(modal_type: ModalType, data_manager: &DataManager) -> Self {
+        let fields = match modal_type.form() {
+            Some(schema) => {
+                let values = modal_type.record_values(data_manager.rules().grading_scale);
+                schema
+                    .fields
+                    .iter()
+                    .map(|spec| {
+                        let value = values.iter().find(|(field, _)| *field == spec.field).map(|(_, value)| value.clone());
+                        FormField::new(spec, value.unwrap_or_default(), data_manager)
+                    })
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+
+        let mut list_state = TableState::default();
+        list_state.select(Some(0));
+
+        // Deleting offers to move dependents to any other record of the same kind
+        let (dependents, reassign_dropdown) = match &modal_type {
+            ModalType::DeleteConfirmation(kind, id, _) => {
+                let targets: Vec<(String, String)> = match kind {
+                    EntityKind::Student => data_manager.get_all_students().iter()
+                        .map(|s| (s.full_name(), s.id.clone())).collect(),
+                    EntityKind::Teacher => data_manager.get_all_teachers().iter()
+                        .map(|t| (t.full_name(), t.id.clone())).collect(),
+                    EntityKind::Faculty => data_manager.get_all_faculties().iter()
+                        .map(|f| (f.name.clone(), f.id.clone())).collect(),
+                    EntityKind::Course => data_manager.get_all_courses().iter()
+                        .map(|c| (format!("{} {}", c.code, c.title), c.id.clone())).collect(),
+                };
+                let (labels, ids) = targets.into_iter().filter(|(_, target)| target != id).unzip();
+                (data_manager.dependents(*kind, id), DropdownState::with_values(labels, ids))
+            }
+            _ => (Vec::new(), DropdownState::new(Vec::new())),
         };
 
         Self {
             modal_type,
             active: true,
-            inputs,
+            fields,
             active_field: 0,
-            confirm: false,
-            major_dropdown: DropdownState::new(widgets::MAJORS.iter().map(|&s| s.into()).collect()), // Initialize with predefined majors
+            list_state,
+            dependents,
+            delete_choice: DeleteChoice::Block,
+            reassign_dropdown,
+            rules: data_manager.rules().clone(),
+        }
+    }
+
+    // Dropdown of the currently active field, if any
+    pub fn active_dropdown(&mut self) -> Option<&mut DropdownState> {
+        self.fields.get_mut(self.active_field)?.dropdown.as_mut()
+    }
+
+    pub fn is_dropdown_open(&mut self) -> bool {
+        self.active_dropdown().is_some_and(|dropdown| dropdown.is_open)
+    }
+
+    pub fn close_dropdowns(&mut self) {
+        for dropdown in self.fields.iter_mut().filter_map(|field| field.dropdown.as_mut()) {
+            dropdown.is_open = false;
+        }
+    }
+
+    // Store the highlighted dropdown option in the active field. Single-select
+    // dropdowns close afterwards, multi-select ones stay open for more toggles.
+    pub fn choose_dropdown_item(&mut self) {
+        let Some(field) = self.fields.get_mut(self.active_field) else {
+            return;
+        };
+        let Some(dropdown) = field.dropdown.as_mut() else {
+            return;
+        };
+        let chosen = dropdown.apply_selection(field.input.value());
+        if !dropdown.multi_select {
+            dropdown.is_open = false;
+        }
+        if let Some(value) = chosen {
+            field.input.set(value);
+        }
+        field.touched = true;
+    }
+
+    // Leaving a field shows whether it is valid
+    fn leave_field(&mut self) {
+        if let Some(field) = self.fields.get_mut(self.active_field) {
+            field.touched = true;
         }
     }
 
     pub fn next_field(&mut self) {
-        if self.inputs.is_empty() {
+        if self.fields.is_empty() {
             return;
         }
-        self.active_field = (self.active_field + 1) % self.inputs.len();
+        self.leave_field();
+        self.active_field = (self.active_field + 1) % self.fields.len();
     }
 
     pub fn prev_field(&mut self) {
-        if self.inputs.is_empty() {
+        if self.fields.is_empty() {
             return;
         }
+        self.leave_field();
         self.active_field = if self.active_field == 0 {
-            self.inputs.len() - 1
+            self.fields.len() - 1
         } else {
             self.active_field - 1
         };
     }
 
-    pub fn input(&mut self, c: char) {
-        if self.inputs.is_empty() || self.active_field >= self.inputs.len() {
-            return;
+    // Focus a field, e.g. when it is clicked
+    pub fn focus_field(&mut self, index: usize) {
+        if index < self.fields.len() && index != self.active_field {
+            self.close_dropdowns();
+            self.leave_field();
+            self.active_field = index;
         }
-        
-        match self.inputs[self.active_field].0 {
-            InputField::Age | InputField::EstablishedYear | InputField::NumStaff => {
-                // Only allow digits for numerical fields
-                if c.is_digit(10) {
-                    self.inputs[self.active_field].1.push(c);
-                }
-            }
-            InputField::Gpa => {
-                // Allow digits and one decimal point for GPA
-                if c.is_digit(10) || (c == '.' && !self.inputs[self.active_field].1.contains('.')) {
-                    self.inputs[self.active_field].1.push(c);
-                }
-            }
-            _ => {
-                // Allow any character for text fields
-                self.inputs[self.active_field].1.push(c);
-            }
+    }
+
+    // Typed and pasted characters are filtered by the active field's widget
+    pub fn type_char(&mut self, c: char) {
+        if let Some(field) = self.fields.get_mut(self.active_field) {
+            field.type_char(c);
         }
     }
 
-    pub fn backspace(&mut self) {
-        if self.inputs.is_empty() || self.active_field >= self.inputs.len() {
+    pub fn paste(&mut self, text: &str) {
+        if self.is_dropdown_open() {
             return;
         }
-        self.inputs[self.active_field].1.pop();
+        if let Some(field) = self.fields.get_mut(self.active_field) {
+            field.paste(text);
+        }
     }
 
-    pub fn create_student(&self) -> Option<Student> {
-        if self.inputs.len() < 5 {
-            return None;
+    // Cursor movement and deletion in the active field; false if the action isn't one
+    pub fn edit(&mut self, action: Action) -> bool {
+        self.fields.get_mut(self.active_field).is_some_and(|field| field.edit(action))
+    }
+
+    // Set a field's value directly, e.g. from the command line; false if the form has no such field
+    pub fn set_input(&mut self, field: InputField, value: &str) -> bool {
+        match self.fields.iter_mut().find(|f| f.spec.field == field) {
+            Some(f) => {
+                f.input.set(value.to_string());
+                true
+            }
+            None => false,
         }
+    }
 
-        // Extract values
-        let first_name = &self.inputs[0].1;
-        let last_name = &self.inputs[1].1;
-        let age_str = &self.inputs[2].1;
-        let major = &self.inputs[3].1;
-        let gpa_str = &self.inputs[4].1;
+    // True when every field passes its schema rule
+    pub fn is_valid(&self) -> bool {
+        !self.fields.is_empty() && self.fields.iter().all(|field| field.check(&self.rules).is_ok())
+    }
 
-        // Basic validation
-        if first_name.is_empty() || last_name.is_empty() || major.is_empty() || 
-           age_str.is_empty() || gpa_str.is_empty() {
-            return None;
+    // Show the errors of fields the user hasn't reached yet, e.g. when saving is attempted
+    pub fn reveal_errors(&mut self) {
+        for field in &mut self.fields {
+            field.touched = true;
         }
+    }
 
-        // Parse numeric values
-        let age = match age_str.parse::<u32>() {
-            Ok(a) if a >= 16 && a <= 99 => a,
-            _ => return None,
-        };
+    // Every failing field as "Label: message"
+    pub fn errors(&self) -> Vec<String> {
+        self.fields
+            .iter()
+            .filter_map(|field| field.check(&self.rules).err().map(|err| format!("{}: {}", field.spec.label, err)))
+            .collect()
+    }
 
-        let gpa = match gpa_str.parse::<f32>() {
-            Ok(g) if g >= 0.0 && g <= 4.0 => g,
-            _ => return None,
-        };
+    fn value(&self, field: InputField) -> String {
+        self.fields
+            .iter()
+            .find(|f| f.spec.field == field)
+            .map(|f| f.value().to_string())
+            .unwrap_or_default()
+    }
 
-        // Create Student
-        match &self.modal_type {
-            ModalType::EditStudent(student) => Some(Student::with_id(
-                student.id.clone(),
-                first_name.clone(),
-                last_name.clone(),
-                age,
-                major.clone(),
-                gpa,
-            )),
-            _ => Some(Student::new(
-                first_name.clone(),
-                last_name.clone(),
-                age,
-                major.clone(),
-                gpa,
-            )),
-        }
+    // Numeric fields are only read after `is_valid`, so they parse
+    fn number(&self, field: InputField) -> u32 {
+        self.value(field).parse().unwrap_or_default()
     }
 
-    pub fn create_teacher(&self) -> Option<Teacher> {
-        if self.inputs.len() < 5 {
+    pub fn create_student(&self) -> Option<Student> {
+        if !self.is_valid() {
             return None;
         }
+        let first_name = self.value(InputField::FirstName);
+        let last_name = self.value(InputField::LastName);
+        let age = self.number(InputField::Age);
+        let major = self.value(InputField::Major);
 
-        // Extract values
-        let first_name = &self.inputs[0].1;
-        let last_name = &self.inputs[1].1;
-        let age_str = &self.inputs[2].1;
-        let department = &self.inputs[3].1;
-        let title = &self.inputs[4].1;
+        match &self.modal_type {
+            // An edit keeps the version it started from, so saving can tell whether the
+            // record changed in the meantime
+            ModalType::EditStudent(student) => Some(Student {
+                version: student.version,
+                recorded_gpa: student.recorded_gpa,
+                ..Student::with_id(student.id.clone(), first_name, last_name, age, major)
+            }),
+            _ => Some(Student::new(first_name, last_name, age, major)),
+        }
+    }
 
-        // Basic validation
-        if first_name.is_empty() || last_name.is_empty() || department.is_empty() || 
-           title.is_empty() || age_str.is_empty() {
+    pub fn create_teacher(&self) -> Option<Teacher> {
+        if !self.is_valid() {
             return None;
         }
+        let first_name = self.value(InputField::FirstName);
+        let last_name = self.value(InputField::LastName);
+        let age = self.number(InputField::Age);
+        let faculty_id = self.value(InputField::Faculty);
+        let title = self.value(InputField::Title);
 
-        // Parse numeric values
-        let age = match age_str.parse::<u32>() {
-            Ok(a) if a >= 18 && a <= 99 => a,
-            _ => return None,
-        };
-
-        // Create Teacher
         match &self.modal_type {
-            ModalType::EditTeacher(teacher) => Some(Teacher::with_id(
-                teacher.id.clone(),
-                first_name.clone(),
-                last_name.clone(),
-                age,
-                department.clone(),
-                title.clone(),
-            )),
-            _ => Some(Teacher::new(
-                first_name.clone(),
-                last_name.clone(),
-                age,
-                department.clone(),
-                title.clone(),
-            )),
+            ModalType::EditTeacher(teacher) => Some(Teacher {
+                version: teacher.version,
+                ..Teacher::with_id(teacher.id.clone(), first_name, last_name, age, faculty_id, title)
+            }),
+            _ => Some(Teacher::new(first_name, last_name, age, faculty_id, title)),
         }
     }
 
     pub fn create_faculty(&self) -> Option<Faculty> {
-        if self.inputs.len() < 5 {
+        if !self.is_valid() {
             return None;
         }
+        let name = self.value(InputField::Name);
+        let building = self.value(InputField::Building);
+        let head_name = self.value(InputField::HeadName);
+        let established_year = self.number(InputField::EstablishedYear);
+        let num_staff = self.number(InputField::NumStaff);
 
-        // Extract values
-        let name = &self.inputs[0].1;
-        let building = &self.inputs[1].1;
-        let head_name = &self.inputs[2].1;
-        let established_year_str = &self.inputs[3].1;
-        let num_staff_str = &self.inputs[4].1;
+        match &self.modal_type {
+            ModalType::EditFaculty(faculty) => Some(Faculty {
+                version: faculty.version,
+                ..Faculty::with_id(faculty.id.clone(), name, building, head_name, established_year, num_staff)
+            }),
+            _ => Some(Faculty::new(name, building, head_name, established_year, num_staff)),
+        }
+    }
 
-        // Basic validation
-        if name.is_empty() || building.is_empty() || head_name.is_empty() || 
-           established_year_str.is_empty() || num_staff_str.is_empty() {
+    pub fn create_course(&self) -> Option<Course> {
+        if !self.is_valid() {
             return None;
         }
+        let code = self.value(InputField::Code);
+        let title = self.value(InputField::Title);
+        let credits = self.number(InputField::Credits);
+        let faculty_id = self.value(InputField::Faculty);
+        let teacher_ids = widgets::split_values(&self.value(InputField::Teachers));
 
-        // Parse numeric values
-        let established_year = match established_year_str.parse::<u32>() {
-            Ok(y) if y >= 1500 && y <= 2025 => y, // Assuming current year is 2025
-            _ => return None,
-        };
+        match &self.modal_type {
+            ModalType::EditCourse(course) => Some(Course {
+                version: course.version,
+                ..Course::with_id(course.id.clone(), code, title, credits, faculty_id, teacher_ids)
+            }),
+            _ => Some(Course::new(code, title, credits, faculty_id, teacher_ids)),
+        }
+    }
 
-        let num_staff = match num_staff_str.parse::<u32>() {
-            Ok(n) if n > 0 => n,
-            _ => return None,
-        };
+    pub fn create_enrollment(&self) -> Option<Enrollment> {
+        if !self.is_valid() {
+            return None;
+        }
+        let course_id = self.value(InputField::Course);
+        let term = self.value(InputField::Term);
+        let scale = self.rules.grading_scale;
+        let entered = self.value(InputField::Grade);
+        // The grade stays empty until it is awarded
+        let grade = scale.parse(&entered);
 
-        // Create Faculty
         match &self.modal_type {
-            ModalType::EditFaculty(faculty) => Some(Faculty::with_id(
-                faculty.id.clone(),
-                name.clone(),
-                building.clone(),
-                head_name.clone(),
-                established_year,
-                num_staff,
-            )),
-            _ => Some(Faculty::new(
-                name.clone(),
-                building.clone(),
-                head_name.clone(),
-                established_year,
-                num_staff,
-            )),
+            ModalType::EditEnrollment(enrollment) => {
+                // A grade on another scale is shown converted, so it is only replaced when
+                // the user actually changed it
+                let shown = self.modal_type.record_values(scale).into_iter().find(|(field, _)| *field == InputField::Grade);
+                let grade = match shown {
+                    Some((_, shown)) if shown == entered => enrollment.grade.clone(),
+                    _ => grade,
+                };
+                Some(Enrollment {
+                    version: enrollment.version,
+                    ..Enrollment::with_id(enrollment.id.clone(), enrollment.student_id.clone(), course_id, term, grade)
+                })
+            }
+            ModalType::AddEnrollment(student_id) => Some(Enrollment::new(student_id.clone(), course_id, term, grade)),
+            _ => None,
         }
     }
 
-    pub fn get_delete_id(&self) -> Option<String> {
+    // Policy picked in the delete confirmation; None when reassigning with no target available
+    pub fn delete_policy(&self) -> Option<DeletePolicy> {
+        match self.delete_choice {
+            DeleteChoice::Block => Some(DeletePolicy::Block),
+            DeleteChoice::Cascade => Some(DeletePolicy::Cascade),
+            DeleteChoice::Reassign => self.reassign_dropdown.selected_value().cloned().map(DeletePolicy::Reassign),
+        }
+    }
+
+    // Student whose detail view an enrollment form returns to
+    pub fn enrollment_student_id(&self) -> Option<String> {
         match &self.modal_type {
-            ModalType::DeleteConfirmation(id, _) => Some(id.clone()),
+            ModalType::AddEnrollment(student_id) => Some(student_id.clone()),
+            ModalType::EditEnrollment(enrollment) => Some(enrollment.student_id.clone()),
             _ => None,
         }
     }
 }
 
-// Render the active modal
-pub fn render_modal(f: &mut Frame, modal: &mut Modal) {
+// Layout of a form modal inside `area`: two rows per field, a separator and the button row
+pub fn form_chunks(area: Rect, field_count: usize) -> Rc<[Rect]> {
+    let inner_area = area.inner(Margin::new(1, 1));
+    let mut constraints = vec![Constraint::Length(2); field_count];
+    constraints.push(Constraint::Length(1)); // Separator
+    constraints.push(Constraint::Length(3)); // Buttons
+
+    Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints(constraints)
+        .split(inner_area)
+}
+
+// Render the active modal, recording its clickable elements over a backdrop that
+// swallows clicks on the screen behind it
+pub fn render_modal(f: &mut Frame, modal: &mut Modal, data_manager: &DataManager, keymap: &Keymap, hit_map: &mut HitMap) {
     if !modal.active {
         return;
     }
+    hit_map.add(f.area(), Hit::Backdrop);
 
     // Create a centered box for our modal
     let area = centered_rect(60, 60, f.area());
@@ -343,353 +489,311 @@ pub fn render_modal(f: &mut Frame, modal: &mut Modal) {
     f.render_widget(Clear, area);
     
     // Render the appropriate modal content
+    if let Some(schema) = modal.modal_type.form() {
+        render_form(f, modal, area, schema, keymap, hit_map);
+        return;
+    }
     match &modal.modal_type {
-        ModalType::AddStudent | ModalType::EditStudent(_) => {
-            render_student_modal(f, modal, area);
+        ModalType::StudentDetail(student_id) => {
+            let student_id = student_id.clone();
+            render_student_detail_modal(f, modal, &student_id, data_manager, keymap, area, hit_map);
         }
-        ModalType::AddTeacher | ModalType::EditTeacher(_) => {
-            render_teacher_modal(f, modal, area);
+        ModalType::DeleteConfirmation(_, _, name) => {
+            let name = name.clone();
+            render_delete_modal(f, modal, &name, keymap, area, hit_map);
         }
-        ModalType::AddFaculty | ModalType::EditFaculty(_) => {
-            render_faculty_modal(f, modal, area);
-        }
-        ModalType::DeleteConfirmation(_, name) => {
-            render_delete_modal(f, name, area);
-        }
-        ModalType::Message(msg) => {
-            render_message_modal(f, msg, area);
+        ModalType::PurgeConfirmation(_, name) => {
+            let name = format!("{} permanently", name);
+            render_delete_modal(f, modal, &name, keymap, area, hit_map);
         }
+        _ => {}
     }
 }
 
-fn render_student_modal(f: &mut Frame, modal: &mut Modal, area: Rect) {
-    let is_edit = matches!(modal.modal_type, ModalType::EditStudent(_));
-    let title = if is_edit { "Edit Student" } else { "Add Student" };
-    
-    // Create modal border with title
-    let block = Block::default()
-        .title(title)
-        .title_style(Style::default().fg(Color::Green).add_modifier(Modifier::BOLD))
-        .borders(Borders::ALL)
-        .border_type(BorderType::Rounded)
-        .border_style(Style::default().fg(Color::Green));
-    
-    f.render_widget(Clear, area); // Clear the area first
-    f.render_widget(block.clone(), area);
-    
-    // Create inner area for content with a margin
-    let inner_area = area.inner(Margin::new(1, 1));
-    
-    // Create layout for fields with less spacing between them
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .margin(1) // Reduced margin from 2 to 1
-        .constraints(
-            [
-                Constraint::Length(2), // First Name (reduced from 3)
-                Constraint::Length(2), // Last Name
-                Constraint::Length(2), // Age
-                Constraint::Length(2), // Major
-                Constraint::Length(2), // GPA
-                Constraint::Length(1), // Separator
-                Constraint::Length(3), // Buttons
-            ]
-            .as_ref(),
-        )
-        .split(inner_area);
-    
-    // Render the fields without borders
-    for i in 0..5 {
-        let (field, value) = &modal.inputs[i];
-        let is_active = modal.active_field == i;
-        
-        // For Major field (dropdown)
-        if i == 3 { // Major field is at index 3
-            // Create dropdown field without borders
-            let label_style = if is_active {
-                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
-            } else {
-                Style::default().fg(Color::Cyan)
-            };
-            
-            let value_style = Style::default().fg(Color::White);
-            // Show dropdown indicator with different appearance depending on whether it's open
-            let dropdown_indicator = if is_active {
-                if modal.major_dropdown.is_open { " ▲" } else { " ▼" }
-            } else {
-                ""
-            };
-            
-            let text = Line::from(vec![
-                Span::styled(format!("{}: ", field), label_style),
-                Span::styled(value, value_style),
-                Span::styled(dropdown_indicator, Style::default().fg(Color::Yellow)),
-            ]);
-            
-            let paragraph = Paragraph::new(text);
-            f.render_widget(paragraph, chunks[i]);
-        } else {
-            // Regular field without borders
-            let label_style = if is_active {
-                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
-            } else {
-                Style::default().fg(Color::Cyan)
-            };
-            
-            let value_style = Style::default().fg(Color::White);
-            let cursor = if is_active { "|" } else { "" };
-            
-            let text = Line::from(vec![
-                Span::styled(format!("{}: ", field), label_style),
-                Span::styled(value.clone(), value_style),
-                Span::styled(cursor, Style::default().fg(Color::Yellow)),
-            ]);
-            
-            let paragraph = Paragraph::new(text);
-            f.render_widget(paragraph, chunks[i]);
-        }
-        
-        // Add a line separator after each field except the last one
-        if i < 4 {
-            let separator = Block::default()
-                .borders(Borders::BOTTOM)
-                .border_style(Style::default().fg(Color::DarkGray));
-            
-            let separator_area = Rect::new(
-                chunks[i].x,
-                chunks[i].y + chunks[i].height - 1,
-                chunks[i].width,
-                1
-            );
-            
-            f.render_widget(separator, separator_area);
-        }
-    }
-    
-    // Add a separator line before the buttons
-    let separator = Block::default()
-        .borders(Borders::BOTTOM)
-        .border_style(Style::default().fg(Color::DarkGray));
-    f.render_widget(separator, chunks[5]);
-    
-    // Render buttons
-    let button_area = chunks[6];
-    let button_layout = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints(
-            [
-                Constraint::Percentage(50),
-                Constraint::Percentage(50),
-            ]
-        )
-        .split(button_area);
-    
-    render_modal_button(f, button_layout[0], "Enter: Save", Color::Green);
-    render_modal_button(f, button_layout[1], "Esc: Cancel", Color::Red);
-    
-    // Render the dropdown on top if it's open
-    if modal.active_field == 3 && modal.major_dropdown.is_open {
-        // Position the dropdown right below the major field
-        let dropdown_rect = Rect::new(
-            chunks[3].x,
-            chunks[3].y + 1, // Position right below the field
-            chunks[3].width,
-            10.min(modal.major_dropdown.options.len() as u16 + 2) // Limit dropdown height
-        );
-        
-        widgets::render_dropdown(f, &mut modal.major_dropdown, dropdown_rect);
-    }
-}
+// Render a form modal: every field with its value, dropdown fields with the
+// names of the selected records, then the Save/Cancel buttons
+fn render_form(f: &mut Frame, modal: &mut Modal, area: Rect, schema: &FormSchema, keymap: &Keymap, hit_map: &mut HitMap) {
+    let title = format!("{} {}", if modal.modal_type.is_edit() { "Edit" } else { "Add" }, schema.name);
+    let color = schema.color;
 
-fn render_teacher_modal(f: &mut Frame, modal: &mut Modal, area: Rect) {
-    let is_edit = matches!(modal.modal_type, ModalType::EditTeacher(_));
-    let title = if is_edit { "Edit Teacher" } else { "Add Teacher" };
-    
     // Create modal border with title
     let block = Block::default()
         .title(title)
-        .title_style(Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD))
+        .title_style(Style::default().fg(color).add_modifier(Modifier::BOLD))
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded)
-        .border_style(Style::default().fg(Color::Blue));
-    
+        .border_style(Style::default().fg(color));
+
     f.render_widget(Clear, area); // Clear the area first
     f.render_widget(block.clone(), area);
-    
-    // Create inner area for content with a margin
-    let inner_area = area.inner(Margin::new(1, 1));
-    
-    // Create layout for fields with less spacing between them
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .margin(1) // Reduced margin from 2 to 1
-        .constraints(
-            [
-                Constraint::Length(2), // First Name
-                Constraint::Length(2), // Last Name
-                Constraint::Length(2), // Age
-                Constraint::Length(2), // Department
-                Constraint::Length(2), // Title
-                Constraint::Length(1), // Separator
-                Constraint::Length(3), // Buttons
-            ]
-            .as_ref(),
-        )
-        .split(inner_area);
-    
+
+    let field_count = modal.fields.len();
+    let chunks = form_chunks(area, field_count);
+
     // Render the fields without borders
-    for i in 0..5 {
-        let (field, value) = &modal.inputs[i];
+    for (i, field) in modal.fields.iter().enumerate() {
         let is_active = modal.active_field == i;
-        
-        // Regular field without borders
+
         let label_style = if is_active {
             Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
         } else {
             Style::default().fg(Color::Cyan)
         };
-        
         let value_style = Style::default().fg(Color::White);
-        let cursor = if is_active { "|" } else { "" };
-        
-        let text = Line::from(vec![
-            Span::styled(format!("{}: ", field), label_style),
-            Span::styled(value.clone(), value_style),
-            Span::styled(cursor, Style::default().fg(Color::Yellow)),
-        ]);
-        
+
+        // Dropdown fields show the selected names and an open/closed indicator,
+        // text fields their cursor
+        let label = Span::styled(format!("{}: ", field.spec.label), label_style);
+        let room = chunks[i].width.saturating_sub(label.width() as u16);
+        let mut spans = vec![label];
+        match &field.dropdown {
+            Some(dropdown) => {
+                let marker = match (is_active, dropdown.is_open) {
+                    (false, _) => "",
+                    (true, true) => " ▲",
+                    (true, false) => " ▼",
+                };
+                spans.push(Span::styled(dropdown.display_value(field.value()), value_style));
+                spans.push(Span::styled(marker, Style::default().fg(Color::Yellow)));
+            }
+            None => spans.extend(field.input.spans(value_style, is_active, room)),
+        }
+        let text = Line::from(spans);
+
         let paragraph = Paragraph::new(text);
         f.render_widget(paragraph, chunks[i]);
-        
-        // Add a line separator after each field except the last one
-        if i < 4 {
+        hit_map.add(chunks[i], Hit::FormField(i));
+
+        // The row under the field holds its error, or a separator line unless it's the last field
+        let below = Rect::new(chunks[i].x, chunks[i].y + chunks[i].height - 1, chunks[i].width, 1);
+        if let Some(error) = field.error(&modal.rules) {
+            let error = Paragraph::new(format!("  ⚠ {}", error)).style(Style::default().fg(Color::Red));
+            f.render_widget(error, below);
+        } else if i + 1 < field_count {
             let separator = Block::default()
                 .borders(Borders::BOTTOM)
                 .border_style(Style::default().fg(Color::DarkGray));
-            
-            let separator_area = Rect::new(
-                chunks[i].x,
-                chunks[i].y + chunks[i].height - 1,
-                chunks[i].width,
-                1
-            );
-            
-            f.render_widget(separator, separator_area);
+            f.render_widget(separator, below);
         }
     }
-    
+
     // Add a separator line before the buttons
     let separator = Block::default()
         .borders(Borders::BOTTOM)
         .border_style(Style::default().fg(Color::DarkGray));
-    f.render_widget(separator, chunks[5]);
-    
+    f.render_widget(separator, chunks[field_count]);
+
     // Render buttons
-    let button_area = chunks[6];
-    let button_layout = Layout::default()
+    // Save stays greyed out until every field is valid
+    let button_layout = form_button_layout(chunks[field_count + 1]);
+    let save_color = if modal.is_valid() { Color::Green } else { Color::DarkGray };
+    let save = format!("{}: Save", keymap.key_label(KeyContext::Form, Action::Confirm));
+    let cancel = format!("{}: Cancel", keymap.key_label(KeyContext::Form, Action::Cancel));
+    render_modal_button(f, button_layout[0], &save, save_color);
+    render_modal_button(f, button_layout[1], &cancel, Color::Red);
+    hit_map.add(button_layout[0], Hit::ModalButton(ModalButton::Confirm));
+    hit_map.add(button_layout[1], Hit::ModalButton(ModalButton::Cancel));
+
+    // Render the dropdown of the active field on top if it's open
+    if let Some(field) = modal.fields.get_mut(modal.active_field)
+        && let Some(dropdown) = field.dropdown.as_mut()
+        && dropdown.is_open
+    {
+        let area = dropdown_rect(chunks[modal.active_field], dropdown);
+        widgets::render_dropdown(f, dropdown, area, field.input.value());
+        // Clicks on the list's border only close it
+        hit_map.add(area, Hit::Backdrop);
+        let offset = dropdown.list_state.offset();
+        for (index, row) in (offset..dropdown.options.len()).zip(area.inner(Margin::new(1, 1)).rows()) {
+            hit_map.add(row, Hit::DropdownItem(index));
+        }
+    }
+}
+
+fn form_button_layout(area: Rect) -> Rc<[Rect]> {
+    Layout::default()
         .direction(Direction::Horizontal)
         .constraints([
             Constraint::Percentage(50),
             Constraint::Percentage(50),
         ])
-        .split(button_area);
-    
-    render_modal_button(f, button_layout[0], "Enter: Save", Color::Green);
-    render_modal_button(f, button_layout[1], "Esc: Cancel", Color::Red);
+        .split(area)
 }
 
-fn render_faculty_modal(f: &mut Frame, modal: &mut Modal, area: Rect) {
-    let is_edit = matches!(modal.modal_type, ModalType::EditFaculty(_));
-    let title = if is_edit { "Edit Faculty" } else { "Add Faculty" };
-    
-    // Create modal border with title
+// Where a field's open dropdown goes: right below the field, at most 10 rows tall
+fn dropdown_rect(field_area: Rect, dropdown: &DropdownState) -> Rect {
+    Rect::new(
+        field_area.x,
+        field_area.y + 1,
+        field_area.width,
+        10.min(dropdown.options.len() as u16 + 2),
+    )
+}
+
+// Layout of the student detail modal: summary, enrollments table and buttons
+fn student_detail_chunks(area: Rect) -> Rc<[Rect]> {
+    Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([
+            Constraint::Length(3), // Student summary
+            Constraint::Min(3),    // Enrollments
+            Constraint::Length(1), // Buttons
+        ])
+        .split(area.inner(Margin::new(1, 1)))
+}
+
+fn render_student_detail_modal(
+    f: &mut Frame,
+    modal: &mut Modal,
+    student_id: &str,
+    data_manager: &DataManager,
+    keymap: &Keymap,
+    area: Rect,
+    hit_map: &mut HitMap,
+) {
     let block = Block::default()
-        .title(title)
-        .title_style(Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD))
+        .title(" Student Details ")
+        .title_style(Style::default().fg(Color::Green).add_modifier(Modifier::BOLD))
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded)
-        .border_style(Style::default().fg(Color::Magenta));
-    
-    f.render_widget(Clear, area); // Clear the area first
-    f.render_widget(block.clone(), area);
-    
-    // Create inner area for content with a margin
-    let inner_area = area.inner(Margin::new(1, 1));
-    
-    // Create layout for fields with less spacing between them
+        .border_style(Style::default().fg(Color::Green));
+
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+
+    let chunks = student_detail_chunks(area);
+    let label_style = Style::default().fg(Color::Cyan);
+    let value_style = Style::default().fg(Color::White);
+
+    // Summary of the student record with the GPA computed from graded enrollments
+    if let Some(student) = data_manager.get_student_by_id(student_id) {
+        let gpa = data_manager
+            .student_gpa(student_id)
+            .map(|g| format!("{:.2}", g))
+            .unwrap_or_else(|| "-".to_string());
+        let summary = vec![
+            Line::from(vec![
+                Span::styled("Name: ", label_style),
+                Span::styled(student.full_name(), value_style),
+                Span::styled("   Age: ", label_style),
+                Span::styled(student.age.to_string(), value_style),
+            ]),
+            Line::from(vec![
+                Span::styled("Major: ", label_style),
+                Span::styled(student.major.clone(), value_style),
+                Span::styled("   GPA: ", label_style),
+                Span::styled(gpa, value_style),
+            ]),
+        ];
+        f.render_widget(Paragraph::new(summary), chunks[0]);
+    }
+
+    // Enrollments table, with grades on the configured scale
+    let scale = data_manager.rules().grading_scale;
+    let header = Row::new(["Course", "Term", "Credits", "Grade"].map(|h| {
+        Span::styled(h, Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
+    }));
+    let enrollments = data_manager.enrollments_for_student(student_id);
+    let enrollment_count = enrollments.len();
+    let rows = enrollments.into_iter().map(|e| {
+        let course = data_manager.get_course_by_id(&e.course_id);
+        Row::new([
+            course.map(|c| format!("{} {}", c.code, c.title)).unwrap_or_default(),
+            e.term.clone(),
+            course.map(|c| c.credits.to_string()).unwrap_or_default(),
+            e.grade.as_ref().map(|g| g.display_in(scale)).unwrap_or_else(|| "-".to_string()),
+        ])
+    });
+    let widths = [
+        Constraint::Percentage(50),
+        Constraint::Percentage(20),
+        Constraint::Percentage(15),
+        Constraint::Percentage(15),
+    ];
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(Block::default()
+            .borders(Borders::TOP)
+            .border_style(Style::default().fg(Color::DarkGray))
+            .title("Enrollments"))
+        .row_highlight_style(Style::default().bg(Color::Blue).fg(Color::White).add_modifier(Modifier::BOLD))
+        .highlight_symbol(">> ");
+    f.render_stateful_widget(table, chunks[1], &mut modal.list_state);
+    // Rows start below the top border and the header
+    let offset = modal.list_state.offset();
+    for (index, row) in (offset..enrollment_count).zip(chunks[1].rows().skip(2)) {
+        hit_map.add(row, Hit::EnrollmentRow(index));
+    }
+
+    let key = |action| Span::styled(
+        keymap.key_label(KeyContext::StudentDetail, action),
+        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+    );
+    let hints = Paragraph::new(Line::from(vec![
+        key(Action::Add),
+        Span::raw(": Enroll   "),
+        key(Action::Edit),
+        Span::raw(": Edit grade   "),
+        key(Action::Delete),
+        Span::raw(": Remove   "),
+        key(Action::Cancel),
+        Span::raw(": Close"),
+    ]));
+    f.render_widget(hints, chunks[2]);
+}
+
+// Most dependents listed in the delete confirmation before summarising the rest
+const MAX_LISTED_DEPENDENTS: usize = 6;
+
+// Layout of the delete confirmation inside `area`: the modal rect and its rows
+// (message, dependents, policy choice, spacer, buttons)
+fn delete_modal_layout(modal: &Modal, area: Rect) -> (Rect, Rc<[Rect]>) {
+    let dependent_lines = match modal.dependents.len() {
+        0 => 0,
+        n if n > MAX_LISTED_DEPENDENTS => MAX_LISTED_DEPENDENTS + 1,
+        n => n,
+    } as u16;
+    // The policy rows are only needed when something depends on the entity
+    let policy_lines = if modal.dependents.is_empty() { 0 } else { 3 };
+
+    // 50 characters wide, 12 characters tall (minimum), growing with the dependents
+    let width = std::cmp::max(50, area.width.saturating_mul(80).saturating_div(100));
+    let height = 12 + dependent_lines + policy_lines;
+    let modal_area = centered_rect_with_min_size(width, height, area);
+
+    // Inner area for content with 2 character horizontal margin, 1 character vertical
+    let inner_area = modal_area.inner(Margin::new(2, 1));
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .margin(1) // Reduced margin from 2 to 1
-        .constraints(
-            [
-                Constraint::Length(2), // Name
-                Constraint::Length(2), // Building
-                Constraint::Length(2), // Head Name
-                Constraint::Length(2), // Established Year
-                Constraint::Length(2), // Number of Staff
-                Constraint::Length(1), // Separator
-                Constraint::Length(3), // Buttons
-            ]
-            .as_ref(),
-        )
+        .constraints([
+            Constraint::Length(3),              // Space for warning symbol + message
+            Constraint::Length(dependent_lines), // Records that reference the entity
+            Constraint::Length(policy_lines),   // Block / cascade / reassign choice
+            Constraint::Length(1),              // Empty space
+            Constraint::Length(3),              // Buttons height
+        ])
         .split(inner_area);
-    
-    // Render the fields without borders
-    for i in 0..5 {
-        let (field, value) = &modal.inputs[i];
-        let is_active = modal.active_field == i;
-        
-        // Regular field without borders
-        let label_style = if is_active {
-            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
-        } else {
-            Style::default().fg(Color::Cyan)
-        };
-        
-        let value_style = Style::default().fg(Color::White);
-        let cursor = if is_active { "|" } else { "" };
-        
-        let text = Line::from(vec![
-            Span::styled(format!("{}: ", field), label_style),
-            Span::styled(value.clone(), value_style),
-            Span::styled(cursor, Style::default().fg(Color::Yellow)),
-        ]);
-        
-        let paragraph = Paragraph::new(text);
-        f.render_widget(paragraph, chunks[i]);
-    }
-    
-    // Add a separator line before the buttons
-    let separator = Block::default()
-        .borders(Borders::BOTTOM)
-        .border_style(Style::default().fg(Color::DarkGray));
-    f.render_widget(separator, chunks[5]);
-    
-    // Render buttons
-    let button_area = chunks[6];
-    let button_layout = Layout::default()
+    (modal_area, chunks)
+}
+
+fn delete_button_layout(area: Rect) -> Rc<[Rect]> {
+    Layout::default()
         .direction(Direction::Horizontal)
         .constraints([
-            Constraint::Percentage(50),
-            Constraint::Percentage(50),
+            Constraint::Percentage(20),  // Left spacing
+            Constraint::Percentage(30),  // Delete button
+            Constraint::Percentage(30),  // Cancel button
+            Constraint::Percentage(20),  // Right spacing
         ])
-        .split(button_area);
-    
-    render_modal_button(f, button_layout[0], "Enter: Save", Color::Green);
-    render_modal_button(f, button_layout[1], "Esc: Cancel", Color::Red);
+        .split(area)
 }
 
-fn render_delete_modal(f: &mut Frame, name: &str, area: Rect) {
-    // Create a modal with fixed minimum width and height
-    // 50 characters wide, 12 characters tall (minimum)
-    let width = std::cmp::max(50, area.width.saturating_mul(80).saturating_div(100));
-    let height = 12; // Fixed minimum height
-    
-    let modal_area = centered_rect_with_min_size(width, height, area);
+fn render_delete_modal(f: &mut Frame, modal: &Modal, name: &str, keymap: &Keymap, area: Rect, hit_map: &mut HitMap) {
+    let (modal_area, chunks) = delete_modal_layout(modal, area);
     
     // Create a clear area
-    let clear_area = modal_area;
-    f.render_widget(Clear, clear_area);
+    f.render_widget(Clear, modal_area);
     
     // Create modal border with title
     let block = Block::default()
@@ -699,42 +803,65 @@ fn render_delete_modal(f: &mut Frame, name: &str, area: Rect) {
         .border_type(BorderType::Rounded)
         .border_style(Style::default().fg(Color::Red));
     
-    f.render_widget(block.clone(), modal_area);
-    
-    // Create inner area for content with 2 character horizontal margin, 1 character vertical
-    let inner_area = modal_area.inner(Margin::new(2, 1));
-    
-    // Create layout for message and buttons
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),    // Space for warning symbol + message
-            Constraint::Length(1),    // Empty space
-            Constraint::Length(3),    // Buttons height
-        ])
-        .split(inner_area);
+    f.render_widget(block, modal_area);
     
     // Warning symbol inline with text
-    let warning_text = format!("⚠  Are you sure you want to delete {}?", name);
+    let warning_text = if modal.dependents.is_empty() {
+        format!("⚠  Are you sure you want to delete {}?", name)
+    } else {
+        format!("⚠  {} is still referenced by {} record(s):", name, modal.dependents.len())
+    };
     let message = Paragraph::new(warning_text)
         .style(Style::default().fg(Color::White))
         .alignment(ratatui::layout::Alignment::Center);
     
     f.render_widget(message, chunks[0]);
+
+    if !modal.dependents.is_empty() {
+        let mut lines: Vec<Line> = modal.dependents
+            .iter()
+            .take(MAX_LISTED_DEPENDENTS)
+            .map(|dependent| Line::from(format!("  • {}", dependent)))
+            .collect();
+        if modal.dependents.len() > MAX_LISTED_DEPENDENTS {
+            lines.push(Line::from(format!("  … and {} more", modal.dependents.len() - MAX_LISTED_DEPENDENTS)));
+        }
+        f.render_widget(Paragraph::new(lines).style(Style::default().fg(Color::Gray)), chunks[1]);
+
+        // The chosen policy is highlighted; Left/Right switch between them
+        let mut choices = vec![Span::styled("←/→ On dependents: ", Style::default().fg(Color::Yellow))];
+        for choice in DeleteChoice::ALL {
+            let style = if choice == modal.delete_choice {
+                Style::default().fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Gray)
+            };
+            choices.push(Span::styled(format!(" {} ", choice.label()), style));
+            choices.push(Span::raw(" "));
+        }
+
+        let explanation = match modal.delete_choice {
+            DeleteChoice::Block => "Keep the record while anything references it".to_string(),
+            DeleteChoice::Cascade => "Delete the dependent records as well".to_string(),
+            DeleteChoice::Reassign => match modal.reassign_dropdown.list_state.selected()
+                .and_then(|i| modal.reassign_dropdown.options.get(i))
+            {
+                Some(target) => format!("↑/↓ Reassign them to: {}", target),
+                None => "Nothing else to reassign them to".to_string(),
+            },
+        };
+        let policy = Paragraph::new(vec![
+            Line::from(""),
+            Line::from(choices),
+            Line::from(Span::styled(explanation, Style::default().fg(Color::Gray))),
+        ]);
+        f.render_widget(policy, chunks[2]);
+    }
     
-    // Create button layout
-    let button_layout = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage(20),  // Left spacing
-            Constraint::Percentage(30),  // Delete button
-            Constraint::Percentage(30),  // Cancel button
-            Constraint::Percentage(20),  // Right spacing
-        ])
-        .split(chunks[2]);
+    let button_layout = delete_button_layout(chunks[4]);
     
     // Render delete button (red background, no borders)
-    let delete_button = Paragraph::new("Enter: Delete")
+    let delete_button = Paragraph::new(format!("{}: Delete", keymap.key_label(KeyContext::DeleteConfirmation, Action::Confirm)))
         .style(Style::default()
             .fg(Color::White)
             .bg(Color::Red)
@@ -743,7 +870,7 @@ fn render_delete_modal(f: &mut Frame, name: &str, area: Rect) {
         .block(Block::default().padding(Padding::new(1, 0, 0, 0)));
     
     // Render cancel button (blue background, no borders)
-    let cancel_button = Paragraph::new("Esc: Cancel")
+    let cancel_button = Paragraph::new(format!("{}: Cancel", keymap.key_label(KeyContext::DeleteConfirmation, Action::Cancel)))
         .style(Style::default()
             .fg(Color::White)
             .bg(Color::Blue)
@@ -753,44 +880,8 @@ fn render_delete_modal(f: &mut Frame, name: &str, area: Rect) {
     
     f.render_widget(delete_button, button_layout[1]);
     f.render_widget(cancel_button, button_layout[2]);
-}
-
-fn render_message_modal(f: &mut Frame, message: &str, area: Rect) {
-    // Create modal border with title
-    let block = Block::default()
-        .title(" Message ")
-        .title_style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
-        .borders(Borders::ALL)
-        .border_type(BorderType::Rounded)
-        .border_style(Style::default().fg(Color::Cyan))
-        .style(Style::default().bg(Color::DarkGray));
-    
-    f.render_widget(Clear, area); // Clear the area first
-    f.render_widget(block.clone(), area);
-    
-    // Create inner area for content - use Margin::new(1, 1) for a 1-character margin
-    let inner_area = area.inner(Margin::new(1, 1));
-    
-    // Create layout for message and buttons
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .margin(2)
-        .constraints([Constraint::Min(3), Constraint::Length(3)].as_ref())
-        .split(inner_area);
-    
-    // Render message with info icon
-    let message_text = Line::from(vec![
-        Span::styled("ℹ ", Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD)),
-        Span::styled(message, Style::default().fg(Color::White)),
-    ]);
-    
-    let message = Paragraph::new(message_text)
-        .alignment(ratatui::layout::Alignment::Center);
-    
-    f.render_widget(message, chunks[0]);
-    
-    // Render button with color
-    render_modal_button(f, chunks[1], "Press Esc to close", Color::Blue);
+    hit_map.add(button_layout[1], Hit::ModalButton(ModalButton::Confirm));
+    hit_map.add(button_layout[2], Hit::ModalButton(ModalButton::Cancel));
 }
 
 // Helper function to render a modal button
@@ -802,12 +893,6 @@ fn render_modal_button(f: &mut Frame, area: Rect, text: &str, color: Color) {
     f.render_widget(button, area);
 }
 
-// Helper function to check if a position is within a rectangle
-pub fn is_position_in_rect(position: (u16, u16), rect: ratatui::layout::Rect) -> bool {
-    let (x, y) = position;
-    x >= rect.x && x < rect.x + rect.width && y >= rect.y && y < rect.y + rect.height
-}
-
 // Helper function to create a centered rect with a minimum size
 pub fn centered_rect_with_min_size(percent_x: u16, percent_y: u16, r: ratatui::layout::Rect) -> ratatui::layout::Rect {
     use ratatui::layout::{Constraint, Direction, Layout};
@@ -838,194 +923,3 @@ pub fn centered_rect_with_min_size(percent_x: u16, percent_y: u16, r: ratatui::l
         ])
         .split(popup_layout[1])[1]
 }
-
-// Add this function at the end of the file to detect mouse clicks on modal buttons
-pub fn get_modal_element_at_position(
-    position: (u16, u16),
-    modal: &Modal,
-    area: Rect
-) -> Option<crate::ui::ModalButton> {
-    // Only process if modal is active
-    if !modal.active {
-        return None;
-    }
-    
-    // For delete confirmation modal
-    if let ModalType::DeleteConfirmation(_, _) = modal.modal_type {
-        // Use the same method to calculate the modal area as in render_delete_modal
-        let width = std::cmp::max(50, area.width.saturating_mul(80).saturating_div(100));
-        let height = 12; // Same fixed height as in render_delete_modal
-        let modal_area = centered_rect_with_min_size(width, height, area);
-        
-        if !is_position_in_rect(position, modal_area) {
-            return None;
-        }
-        
-        // Create inner area for content with the same margins as render_delete_modal
-        let inner_area = modal_area.inner(Margin::new(2, 1));
-        
-        // Use the same layout as in render_delete_modal
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Length(3),    // Space for warning symbol + message
-                Constraint::Length(1),    // Empty space
-                Constraint::Length(3),    // Buttons height
-            ])
-            .split(inner_area);
-            
-        // Use the same button layout as in render_delete_modal
-        let button_layout = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([
-                Constraint::Percentage(20),  // Left spacing
-                Constraint::Percentage(30),  // Delete button
-                Constraint::Percentage(30),  // Cancel button
-                Constraint::Percentage(20),  // Right spacing
-            ])
-            .split(chunks[2]);
-        
-        // Check if clicking on the delete button - use the entire button area
-        if is_position_in_rect(position, button_layout[1]) {
-            return Some(crate::ui::ModalButton::Confirm);
-        }
-        
-        // Check if clicking on the cancel button - use the entire button area
-        if is_position_in_rect(position, button_layout[2]) {
-            return Some(crate::ui::ModalButton::Cancel);
-        }
-        
-        return None;
-    }
-    
-    // For all other modals, use the default 60, 60 size
-    let modal_area = centered_rect(60, 60, area);
-    if !is_position_in_rect(position, modal_area) {
-        return None;
-    }
-    
-    // Create inner area for content with the same margin as in render functions
-    let inner_area = modal_area.inner(Margin::new(1, 1));
-    
-    // For other modals with form fields - use the same layout as in the render_*_modal functions
-    match modal.modal_type {
-        ModalType::AddStudent | ModalType::EditStudent(_) |
-        ModalType::AddTeacher | ModalType::EditTeacher(_) |
-        ModalType::AddFaculty | ModalType::EditFaculty(_) => {
-            // Layout for form fields - MATCH THE SAME LAYOUT AS IN THE RENDER FUNCTION
-            let chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .margin(1) // Use 1 instead of 2 to match render functions
-                .constraints([
-                    Constraint::Length(2), // Field 1 (2 instead of 3)
-                    Constraint::Length(2), // Field 2
-                    Constraint::Length(2), // Field 3
-                    Constraint::Length(2), // Field 4
-                    Constraint::Length(2), // Field 5
-                    Constraint::Length(1), // Separator
-                    Constraint::Length(3), // Buttons
-                ])
-                .split(inner_area);
-                
-            // Check the buttons row - now at index 6 instead of 5
-            let button_area = chunks[6]; // Index 6 for buttons
-            let button_layout = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([
-                    Constraint::Percentage(50),
-                    Constraint::Percentage(50),
-                ])
-                .split(button_area);
-                
-            // Check if clicking on the save button - this must take priority
-            if is_position_in_rect(position, button_layout[0]) {
-                return Some(crate::ui::ModalButton::Confirm);
-            }
-            
-            // Check if clicking on the cancel button - this must take priority
-            if is_position_in_rect(position, button_layout[1]) {
-                return Some(crate::ui::ModalButton::Cancel);
-            }
-        },
-        ModalType::Message(_) => {
-            // For message modal, any click anywhere should close it (like pressing Esc)
-            return Some(crate::ui::ModalButton::Cancel);
-        },
-        _ => {}
-    }
-    
-    None
-}
-
-// Check if a click is on a dropdown item and return the selected item if it is
-pub fn is_dropdown_item_clicked(position: (u16, u16), dropdown: &widgets::DropdownState, modal: &Modal) -> Option<String> {
-    // Only process if dropdown is open
-    if !dropdown.is_open {
-        return None;
-    }
-    
-    // First check if the click is on any button - buttons should take priority
-    let area = centered_rect(60, 60, terminal_size()); // Get the modal area
-    let inner_area = area.inner(Margin::new(1, 1));
-    
-    // Use the same layout parameters as in render_student_modal
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .margin(1) // Use 1 to match the rendering function
-        .constraints(
-            [
-                Constraint::Length(2), // First Name
-                Constraint::Length(2), // Last Name
-                Constraint::Length(2), // Age
-                Constraint::Length(2), // Major
-                Constraint::Length(2), // GPA
-                Constraint::Length(1), // Separator
-                Constraint::Length(3), // Buttons
-            ]
-            .as_ref(),
-        )
-        .split(inner_area);
-    
-    // Check if the click is on any button
-    let button_area = chunks[6];
-    let button_layout = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage(50),
-            Constraint::Percentage(50),
-        ])
-        .split(button_area);
-    
-    // If clicking on any button, don't process as dropdown item
-    if is_position_in_rect(position, button_layout[0]) || is_position_in_rect(position, button_layout[1]) {
-        return None;
-    }
-    
-    // Find the index of the major field
-    let major_field_index = 3; // We know it's index 3 in the student form
-    
-    // Get the area of the Major field
-    let major_field_area = chunks[major_field_index];
-    
-    // Calculate the dropdown area using the same logic as in render_student_modal
-    let dropdown_rect = Rect::new(
-        major_field_area.x,
-        major_field_area.y + 1, // Position right below the field
-        major_field_area.width,
-        10.min(dropdown.options.len() as u16 + 2) // Limit dropdown height (same as in render function)
-    );
-    
-    // Check if click is within the dropdown area
-    if !is_position_in_rect(position, dropdown_rect) {
-        return None;
-    }
-    
-    // Calculate which item was clicked (account for the top border)
-    let relative_y = position.1 - dropdown_rect.y - 1;
-    if relative_y >= dropdown.options.len() as u16 {
-        return None;
-    }
-    
-    // Return the selected item
-    dropdown.options.get(relative_y as usize).map(|s| s.clone())
-}
\ No newline at end of file
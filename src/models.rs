@@ -1,4 +1,6 @@
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -8,18 +10,26 @@ pub struct Student {
     pub last_name: String,
     pub age: u32,
     pub major: String,
-    pub gpa: f32,
+    // GPA typed in by hand before it was computed from enrollment grades. Kept as it was
+    // so older data files lose nothing, and used until the student has a graded enrollment.
+    #[serde(default, rename = "gpa", skip_serializing_if = "Option::is_none")]
+    pub recorded_gpa: Option<f32>,
+    // Counts the saves of the record, so a copy from before another instance saved it
+    // can be told apart; 0 until it is first saved
+    #[serde(default)]
+    pub version: u64,
 }
 
 impl Student {
-    pub fn new(first_name: String, last_name: String, age: u32, major: String, gpa: f32) -> Self {
+    pub fn new(first_name: String, last_name: String, age: u32, major: String) -> Self {
         Self {
             id: Uuid::new_v4().to_string(),
             first_name,
             last_name,
             age,
             major,
-            gpa,
+            recorded_gpa: None,
+            version: 0,
         }
     }
 
@@ -29,7 +39,6 @@ impl Student {
         last_name: String,
         age: u32,
         major: String,
-        gpa: f32,
     ) -> Self {
         Self {
             id,
@@ -37,7 +46,8 @@ impl Student {
             last_name,
             age,
             major,
-            gpa,
+            recorded_gpa: None,
+            version: 0,
         }
     }
 
@@ -52,8 +62,10 @@ pub struct Teacher {
     pub first_name: String,
     pub last_name: String,
     pub age: u32,
-    pub department: String,
+    pub faculty_id: String,
     pub title: String,
+    #[serde(default)]
+    pub version: u64,
 }
 
 impl Teacher {
@@ -61,7 +73,7 @@ impl Teacher {
         first_name: String,
         last_name: String,
         age: u32,
-        department: String,
+        faculty_id: String,
         title: String,
     ) -> Self {
         Self {
@@ -69,8 +81,9 @@ impl Teacher {
             first_name,
             last_name,
             age,
-            department,
+            faculty_id,
             title,
+            version: 0,
         }
     }
 
@@ -79,7 +92,7 @@ impl Teacher {
         first_name: String,
         last_name: String,
         age: u32,
-        department: String,
+        faculty_id: String,
         title: String,
     ) -> Self {
         Self {
@@ -87,8 +100,9 @@ impl Teacher {
             first_name,
             last_name,
             age,
-            department,
+            faculty_id,
             title,
+            version: 0,
         }
     }
 
@@ -105,6 +119,8 @@ pub struct Faculty {
     pub head_name: String,
     pub established_year: u32,
     pub num_staff: u32,
+    #[serde(default)]
+    pub version: u64,
 }
 
 impl Faculty {
@@ -122,6 +138,7 @@ impl Faculty {
             head_name,
             established_year,
             num_staff,
+            version: 0,
         }
     }
 
@@ -140,6 +157,508 @@ impl Faculty {
             head_name,
             established_year,
             num_staff,
+            version: 0,
         }
     }
-}
\ No newline at end of file
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Course {
+    pub id: String,
+    pub code: String,
+    pub title: String,
+    pub credits: u32,
+    pub faculty_id: String,
+    pub teacher_ids: Vec<String>,
+    #[serde(default)]
+    pub version: u64,
+}
+
+impl Course {
+    pub fn new(
+        code: String,
+        title: String,
+        credits: u32,
+        faculty_id: String,
+        teacher_ids: Vec<String>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            code,
+            title,
+            credits,
+            faculty_id,
+            teacher_ids,
+            version: 0,
+        }
+    }
+
+    pub fn with_id(
+        id: String,
+        code: String,
+        title: String,
+        credits: u32,
+        faculty_id: String,
+        teacher_ids: Vec<String>,
+    ) -> Self {
+        Self {
+            id,
+            code,
+            title,
+            credits,
+            faculty_id,
+            teacher_ids,
+            version: 0,
+        }
+    }
+}
+
+// Letter grades and their grade points on the 4.0 scale
+const LETTER_GRADES: &[(&str, f32)] = &[
+    ("A+", 4.0),
+    ("A", 4.0),
+    ("A-", 3.7),
+    ("B+", 3.3),
+    ("B", 3.0),
+    ("B-", 2.7),
+    ("C+", 2.3),
+    ("C", 2.0),
+    ("C-", 1.7),
+    ("D+", 1.3),
+    ("D", 1.0),
+    ("D-", 0.7),
+    ("F", 0.0),
+];
+
+// Grades on the 5-point scale and their grade points on the 4.0 scale
+const FIVE_POINT_GRADES: &[(&str, f32)] = &[("5", 4.0), ("4", 3.0), ("3", 2.0), ("2", 0.0), ("1", 0.0)];
+
+// ECTS grades and their grade points on the 4.0 scale
+const ECTS_GRADES: &[(&str, f32)] = &[
+    ("A", 4.0),
+    ("B", 3.5),
+    ("C", 3.0),
+    ("D", 2.5),
+    ("E", 2.0),
+    ("F", 0.0),
+];
+
+// Lowest score of each band on the 100-point scale and its grade points on the 4.0 scale
+const HUNDRED_POINT_BANDS: &[(u32, f32)] = &[
+    (93, 4.0),
+    (90, 3.7),
+    (87, 3.3),
+    (83, 3.0),
+    (80, 2.7),
+    (77, 2.3),
+    (73, 2.0),
+    (70, 1.7),
+    (67, 1.3),
+    (63, 1.0),
+    (60, 0.7),
+    (0, 0.0),
+];
+
+// Scale grades are entered and shown on. GPA is always computed on the 4.0 scale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum GradingScale {
+    #[default]
+    #[serde(rename = "4.0")]
+    FourPoint,
+    #[serde(rename = "5-point")]
+    FivePoint,
+    #[serde(rename = "100-point")]
+    HundredPoint,
+    #[serde(rename = "ects")]
+    Ects,
+}
+
+impl GradingScale {
+    // Scales with a fixed set of grades, best first
+    fn grades(&self) -> &'static [(&'static str, f32)] {
+        match self {
+            GradingScale::FourPoint => LETTER_GRADES,
+            GradingScale::FivePoint => FIVE_POINT_GRADES,
+            GradingScale::Ects => ECTS_GRADES,
+            GradingScale::HundredPoint => &[],
+        }
+    }
+
+    // Parse user input such as "B+" or "3.3" on the 4.0 scale, "4", "87" or "B" on the others
+    pub fn parse(&self, input: &str) -> Option<Grade> {
+        let input = input.trim();
+        match self {
+            GradingScale::FourPoint => Grade::parse(input),
+            GradingScale::HundredPoint => {
+                let score = input.parse::<u32>().ok().filter(|score| *score <= 100)?;
+                Some(Grade::Scaled { scale: *self, value: score.to_string() })
+            }
+            GradingScale::FivePoint | GradingScale::Ects => {
+                let value = input.to_uppercase();
+                self.grades()
+                    .iter()
+                    .any(|(grade, _)| *grade == value)
+                    .then_some(Grade::Scaled { scale: *self, value })
+            }
+        }
+    }
+
+    // Grade points on the 4.0 scale for a grade on this scale
+    pub fn points_of(&self, value: &str) -> Option<f32> {
+        match self {
+            GradingScale::FourPoint => Grade::parse(value)?.points(),
+            GradingScale::HundredPoint => {
+                let score = value.parse::<u32>().ok()?;
+                HUNDRED_POINT_BANDS.iter().find(|(min, _)| score >= *min).map(|(_, points)| *points)
+            }
+            GradingScale::FivePoint | GradingScale::Ects => {
+                self.grades().iter().find(|(grade, _)| *grade == value).map(|(_, points)| *points)
+            }
+        }
+    }
+
+    // The grade on this scale for grade points on the 4.0 scale: the best one not above them
+    pub fn grade_for_points(&self, points: f32) -> String {
+        // Leeway for points that went through a float conversion
+        let points = points + 0.001;
+        match self {
+            GradingScale::FourPoint => format!("{:.2}", points.clamp(0.0, 4.0)),
+            GradingScale::HundredPoint => HUNDRED_POINT_BANDS
+                .iter()
+                .find(|(_, band_points)| *band_points <= points)
+                .map(|(min, _)| min.to_string())
+                .unwrap_or_else(|| "0".to_string()),
+            GradingScale::FivePoint | GradingScale::Ects => {
+                let grades = self.grades();
+                grades
+                    .iter()
+                    .find(|(_, grade_points)| *grade_points <= points)
+                    .or(grades.last())
+                    .map(|(grade, _)| grade.to_string())
+                    .unwrap_or_default()
+            }
+        }
+    }
+
+    // What `parse` accepts, for error messages
+    pub fn expected(&self) -> &'static str {
+        match self {
+            GradingScale::FourPoint => "a letter grade (A+ to F) or points from 0 to 4",
+            GradingScale::FivePoint => "a grade from 1 to 5",
+            GradingScale::HundredPoint => "a score from 0 to 100",
+            GradingScale::Ects => "an ECTS grade from A to F",
+        }
+    }
+}
+
+impl fmt::Display for GradingScale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            GradingScale::FourPoint => "4.0",
+            GradingScale::FivePoint => "5-point",
+            GradingScale::HundredPoint => "100-point",
+            GradingScale::Ects => "ects",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+impl FromStr for GradingScale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "4.0" | "4-point" => Ok(GradingScale::FourPoint),
+            "5-point" => Ok(GradingScale::FivePoint),
+            "100-point" => Ok(GradingScale::HundredPoint),
+            "ects" => Ok(GradingScale::Ects),
+            other => Err(format!("unknown grading scale '{}' (expected 4.0, 5-point, 100-point or ects)", other)),
+        }
+    }
+}
+
+// Grade earned in a course: a letter or grade points on the 4.0 scale, or a grade on
+// another scale kept as it was entered
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Grade {
+    Points(f32),
+    Letter(String),
+    Scaled { scale: GradingScale, value: String },
+}
+
+impl Grade {
+    // Parse user input on the 4.0 scale, such as "B+" or "3.3"
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        if let Ok(points) = input.parse::<f32>() {
+            return (0.0..=4.0).contains(&points).then_some(Grade::Points(points));
+        }
+
+        let letter = input.to_uppercase();
+        LETTER_GRADES
+            .iter()
+            .any(|(l, _)| *l == letter)
+            .then_some(Grade::Letter(letter))
+    }
+
+    // Grade points on the 4.0 scale
+    pub fn points(&self) -> Option<f32> {
+        match self {
+            Grade::Points(points) => Some(*points),
+            Grade::Letter(letter) => LETTER_GRADES
+                .iter()
+                .find(|(l, _)| l == letter)
+                .map(|(_, points)| *points),
+            Grade::Scaled { scale, value } => scale.points_of(value),
+        }
+    }
+
+    fn scale(&self) -> GradingScale {
+        match self {
+            Grade::Points(_) | Grade::Letter(_) => GradingScale::FourPoint,
+            Grade::Scaled { scale, .. } => *scale,
+        }
+    }
+
+    // The grade as shown on `scale`: as entered when it's the grade's own scale, converted otherwise
+    pub fn display_in(&self, scale: GradingScale) -> String {
+        if self.scale() == scale {
+            return self.to_string();
+        }
+        match self.points() {
+            Some(points) => scale.grade_for_points(points),
+            None => self.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Grade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Grade::Points(points) => write!(f, "{:.2}", points),
+            Grade::Letter(letter) => write!(f, "{}", letter),
+            Grade::Scaled { value, .. } => write!(f, "{}", value),
+        }
+    }
+}
+
+// A student taking a course in a given term; the grade is empty until it is awarded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Enrollment {
+    pub id: String,
+    pub student_id: String,
+    pub course_id: String,
+    pub term: String,
+    pub grade: Option<Grade>,
+    #[serde(default)]
+    pub version: u64,
+}
+
+impl Enrollment {
+    pub fn new(student_id: String, course_id: String, term: String, grade: Option<Grade>) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            student_id,
+            course_id,
+            term,
+            grade,
+            version: 0,
+        }
+    }
+
+    pub fn with_id(
+        id: String,
+        student_id: String,
+        course_id: String,
+        term: String,
+        grade: Option<Grade>,
+    ) -> Self {
+        Self {
+            id,
+            student_id,
+            course_id,
+            term,
+            grade,
+            version: 0,
+        }
+    }
+}
+
+// Records carrying a save counter
+pub trait Versioned {
+    fn id(&self) -> &str;
+    fn version(&self) -> u64;
+    fn set_version(&mut self, version: u64);
+}
+
+impl Versioned for Student {
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn version(&self) -> u64 {
+        self.version
+    }
+    fn set_version(&mut self, version: u64) {
+        self.version = version;
+    }
+}
+
+impl Versioned for Teacher {
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn version(&self) -> u64 {
+        self.version
+    }
+    fn set_version(&mut self, version: u64) {
+        self.version = version;
+    }
+}
+
+impl Versioned for Faculty {
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn version(&self) -> u64 {
+        self.version
+    }
+    fn set_version(&mut self, version: u64) {
+        self.version = version;
+    }
+}
+
+impl Versioned for Course {
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn version(&self) -> u64 {
+        self.version
+    }
+    fn set_version(&mut self, version: u64) {
+        self.version = version;
+    }
+}
+
+impl Versioned for Enrollment {
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn version(&self) -> u64 {
+        self.version
+    }
+    fn set_version(&mut self, version: u64) {
+        self.version = version;
+    }
+}
+
+// The kinds of top-level records, for code that works across all of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntityKind {
+    Student,
+    Teacher,
+    Faculty,
+    Course,
+}
+
+impl fmt::Display for EntityKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            EntityKind::Student => "student",
+            EntityKind::Teacher => "teacher",
+            EntityKind::Faculty => "faculty",
+            EntityKind::Course => "course",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+impl FromStr for EntityKind {
+    type Err = String;
+
+    // Accepts singular or plural names, e.g. "student" or "students"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "student" | "students" => Ok(EntityKind::Student),
+            "teacher" | "teachers" => Ok(EntityKind::Teacher),
+            "faculty" | "faculties" => Ok(EntityKind::Faculty),
+            "course" | "courses" => Ok(EntityKind::Course),
+            other => Err(format!("unknown entity '{}' (expected student, teacher, faculty or course)", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCALES: [GradingScale; 4] =
+        [GradingScale::FourPoint, GradingScale::FivePoint, GradingScale::HundredPoint, GradingScale::Ects];
+
+    #[test]
+    fn parses_grades_on_each_scale() {
+        assert_eq!(GradingScale::FourPoint.parse(" b+ "), Some(Grade::Letter("B+".to_string())));
+        assert_eq!(GradingScale::FourPoint.parse("3.3"), Some(Grade::Points(3.3)));
+        assert_eq!(GradingScale::FourPoint.parse("4.5"), None);
+        assert_eq!(GradingScale::FourPoint.parse("E"), None);
+
+        let scaled = |scale, value: &str| Some(Grade::Scaled { scale, value: value.to_string() });
+        assert_eq!(GradingScale::FivePoint.parse("4"), scaled(GradingScale::FivePoint, "4"));
+        assert_eq!(GradingScale::FivePoint.parse("6"), None);
+        assert_eq!(GradingScale::HundredPoint.parse("87"), scaled(GradingScale::HundredPoint, "87"));
+        assert_eq!(GradingScale::HundredPoint.parse("101"), None);
+        assert_eq!(GradingScale::HundredPoint.parse("-1"), None);
+        assert_eq!(GradingScale::Ects.parse("c"), scaled(GradingScale::Ects, "C"));
+        assert_eq!(GradingScale::Ects.parse("B+"), None);
+    }
+
+    #[test]
+    fn points_of_grades_on_each_scale() {
+        assert_eq!(GradingScale::FourPoint.points_of("B+"), Some(3.3));
+        assert_eq!(GradingScale::FourPoint.points_of("2.5"), Some(2.5));
+        assert_eq!(GradingScale::FivePoint.points_of("4"), Some(3.0));
+        assert_eq!(GradingScale::HundredPoint.points_of("100"), Some(4.0));
+        assert_eq!(GradingScale::HundredPoint.points_of("87"), Some(3.3));
+        assert_eq!(GradingScale::HundredPoint.points_of("86"), Some(3.0));
+        assert_eq!(GradingScale::HundredPoint.points_of("59"), Some(0.0));
+        assert_eq!(GradingScale::Ects.points_of("B"), Some(3.5));
+        assert_eq!(GradingScale::Ects.points_of("G"), None);
+    }
+
+    #[test]
+    fn grade_for_points_round_trips() {
+        // Grades sharing their points (5-point "2" and "1") come back as the better one
+        for scale in SCALES {
+            let grades: Vec<String> = match scale {
+                GradingScale::HundredPoint => HUNDRED_POINT_BANDS.iter().map(|(min, _)| min.to_string()).collect(),
+                GradingScale::FourPoint => LETTER_GRADES.iter().map(|(grade, _)| grade.to_string()).collect(),
+                _ => scale.grades().iter().map(|(grade, _)| grade.to_string()).collect(),
+            };
+            for grade in grades {
+                let points = scale.points_of(&grade).unwrap();
+                let converted = scale.grade_for_points(points);
+                assert_eq!(scale.points_of(&converted), Some(points), "{} on the {} scale", grade, scale);
+            }
+        }
+        assert_eq!(GradingScale::FivePoint.grade_for_points(0.0), "2");
+        assert_eq!(GradingScale::HundredPoint.grade_for_points(3.3), "87");
+    }
+
+    #[test]
+    fn grade_for_points_allows_for_float_error() {
+        assert_eq!(GradingScale::HundredPoint.grade_for_points(3.2999), "87");
+        assert_eq!(GradingScale::Ects.grade_for_points(3.4999), "B");
+        // Below the leeway, the grade drops to the next band
+        assert_eq!(GradingScale::HundredPoint.grade_for_points(3.29), "83");
+        assert_eq!(GradingScale::Ects.grade_for_points(-1.0), "F");
+    }
+
+    #[test]
+    fn grades_display_on_their_own_scale_as_entered() {
+        let grade = GradingScale::HundredPoint.parse("88").unwrap();
+        assert_eq!(grade.display_in(GradingScale::HundredPoint), "88");
+        assert_eq!(grade.display_in(GradingScale::FourPoint), "3.30");
+        assert_eq!(Grade::Letter("B+".to_string()).display_in(GradingScale::Ects), "C");
+        assert_eq!(Grade::Points(3.3).display_in(GradingScale::FourPoint), "3.30");
+    }
+}
@@ -0,0 +1,90 @@
+use crate::models::GradingScale;
+use chrono::Datelike;
+
+// Validation limits that differ between installations, set in the `[rules]` table of the
+// config file. Forms, the command line and grade display all read them from here.
+
+// Inclusive bounds of a whole-number field; no `max` means no upper limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bounds {
+    pub min: u32,
+    pub max: Option<u32>,
+}
+
+impl Bounds {
+    pub const fn new(min: u32, max: Option<u32>) -> Self {
+        Self { min, max }
+    }
+}
+
+// A limited field, as named in the form schemas
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    StudentAge,
+    TeacherAge,
+    EstablishedYear,
+    Credits,
+    NumStaff,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rules {
+    pub student_age: Bounds,
+    pub teacher_age: Bounds,
+    // Without a configured maximum, the current year
+    pub established_year: Bounds,
+    pub credits: Bounds,
+    pub num_staff: Bounds,
+    // Scale grades are entered and shown on
+    pub grading_scale: GradingScale,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            student_age: Bounds::new(16, Some(99)),
+            teacher_age: Bounds::new(18, Some(99)),
+            established_year: Bounds::new(1500, None),
+            credits: Bounds::new(1, Some(30)),
+            num_staff: Bounds::new(1, None),
+            grading_scale: GradingScale::FourPoint,
+        }
+    }
+}
+
+impl Rules {
+    pub fn get(&self, limit: Limit) -> Bounds {
+        match limit {
+            Limit::StudentAge => self.student_age,
+            Limit::TeacherAge => self.teacher_age,
+            Limit::EstablishedYear => self.established_year,
+            Limit::Credits => self.credits,
+            Limit::NumStaff => self.num_staff,
+        }
+    }
+
+    pub fn get_mut(&mut self, limit: Limit) -> &mut Bounds {
+        match limit {
+            Limit::StudentAge => &mut self.student_age,
+            Limit::TeacherAge => &mut self.teacher_age,
+            Limit::EstablishedYear => &mut self.established_year,
+            Limit::Credits => &mut self.credits,
+            Limit::NumStaff => &mut self.num_staff,
+        }
+    }
+
+    // Inclusive range a field's value must be in, with the defaults filled in
+    pub fn range(&self, limit: Limit) -> (u32, u32) {
+        let bounds = self.get(limit);
+        let default_max = match limit {
+            // No faculty can be established in the future
+            Limit::EstablishedYear => current_year(),
+            _ => u32::MAX,
+        };
+        (bounds.min, bounds.max.unwrap_or(default_max))
+    }
+}
+
+fn current_year() -> u32 {
+    chrono::Local::now().year().max(0) as u32
+}
@@ -0,0 +1,196 @@
+pub mod json;
+pub mod sqlite;
+
+use crate::models::{Course, Enrollment, Faculty, Student, Teacher};
+use crate::rules::Rules;
+use crate::trash::TrashItem;
+use anyhow::{bail, Result};
+use serde_json::Value;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use self::json::JsonStorage;
+use self::sqlite::SqliteStorage;
+
+// Every record the application works with, as held in memory
+#[derive(Debug, Clone, Default)]
+pub struct Dataset {
+    pub students: Vec<Student>,
+    pub teachers: Vec<Teacher>,
+    pub faculties: Vec<Faculty>,
+    pub courses: Vec<Course>,
+    pub enrollments: Vec<Enrollment>,
+    // Deleted records, newest last
+    pub trash: Vec<TrashItem>,
+}
+
+// A group of records stored together: one JSON file or one SQLite table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Collection {
+    Students,
+    Teachers,
+    Faculties,
+    Courses,
+    Enrollments,
+    Trash,
+}
+
+impl Collection {
+    pub const ALL: [Collection; 6] = [
+        Collection::Students,
+        Collection::Teachers,
+        Collection::Faculties,
+        Collection::Courses,
+        Collection::Enrollments,
+        Collection::Trash,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Collection::Students => "students",
+            Collection::Teachers => "teachers",
+            Collection::Faculties => "faculties",
+            Collection::Courses => "courses",
+            Collection::Enrollments => "enrollments",
+            Collection::Trash => "trash",
+        }
+    }
+}
+
+// A single record written or removed, identified by its id
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Put(Collection, String),
+    Delete(Collection, String),
+}
+
+impl Change {
+    pub fn collection(&self) -> Collection {
+        match self {
+            Change::Put(collection, _) | Change::Delete(collection, _) => *collection,
+        }
+    }
+}
+
+// A collection that could not be read, with the position the parser reported
+#[derive(Debug, Clone)]
+pub struct LoadError {
+    pub collection: Collection,
+    // File or table the records came from, as shown to the user
+    pub location: String,
+    // 1-based position of the error; 0 when unknown (e.g. the file could not be opened)
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl LoadError {
+    pub fn new(collection: Collection, location: String, err: &anyhow::Error) -> Self {
+        match err.downcast_ref::<serde_json::Error>() {
+            Some(json_err) => {
+                // serde_json appends the position to its message; it is shown separately
+                let position = format!(" at line {} column {}", json_err.line(), json_err.column());
+                let message = json_err.to_string();
+                Self {
+                    collection,
+                    location,
+                    line: json_err.line(),
+                    column: json_err.column(),
+                    message: message.strip_suffix(&position).unwrap_or(&message).to_string(),
+                }
+            }
+            None => Self {
+                collection,
+                location,
+                line: 0,
+                column: 0,
+                message: err.root_cause().to_string(),
+            },
+        }
+    }
+}
+
+// Where records are persisted. The data manager keeps everything in memory and
+// tells the backend which records changed, so each backend can write as little
+// as it is able to.
+pub trait Storage {
+    // Read every collection. One that can't be read comes back empty with the reason
+    // in the returned errors, so the caller can offer recovery instead of overwriting it.
+    fn load(&mut self) -> Result<(Dataset, Vec<LoadError>)>;
+
+    // Persist `changes`, which have already been applied to `data`
+    fn commit(&mut self, data: &Dataset, changes: &[Change]) -> Result<()>;
+
+    // The raw records of a collection, so the ones that still parse can be kept
+    fn load_raw(&mut self, collection: Collection) -> Result<Vec<Value>>;
+
+    // Replace unreadable collections with their newest readable backup
+    fn restore_backups(&mut self, collections: &[Collection]) -> Result<()>;
+
+    // Files holding the data, watched for changes made by other programs
+    fn watched_files(&self) -> Vec<PathBuf>;
+}
+
+// Available storage backends, chosen at startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StorageKind {
+    #[default]
+    Json,
+    Sqlite,
+}
+
+impl FromStr for StorageKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(StorageKind::Json),
+            "sqlite" => Ok(StorageKind::Sqlite),
+            other => bail!("Unknown storage backend '{}' (expected json or sqlite)", other),
+        }
+    }
+}
+
+impl fmt::Display for StorageKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageKind::Json => write!(f, "json"),
+            StorageKind::Sqlite => write!(f, "sqlite"),
+        }
+    }
+}
+
+// Open the chosen backend on `data_dir`, bringing JSON files written by older versions up
+// to date. A new SQLite database starts out with whatever the JSON files in the same
+// directory hold. Call with the data directory locked, since files may be rewritten.
+pub fn open(kind: StorageKind, data_dir: &Path, rules: &Rules, backup_count: usize) -> Result<Box<dyn Storage>> {
+    match kind {
+        StorageKind::Json => {
+            let storage = JsonStorage::new(data_dir, backup_count)?;
+            storage.migrate(rules)?;
+            Ok(Box::new(storage))
+        }
+        StorageKind::Sqlite => {
+            let path = data_dir.join(sqlite::DATABASE_FILE);
+            if path.exists() {
+                return Ok(Box::new(SqliteStorage::open(&path)?));
+            }
+
+            // Check the JSON files before creating the database, so a failed import can be retried
+            let mut json = JsonStorage::new(data_dir, backup_count)?;
+            json.migrate(rules)?;
+            let (data, errors) = json.load()?;
+            if let Some(error) = errors.first() {
+                bail!(
+                    "Cannot start a new SQLite database: {} could not be read ({})",
+                    error.location,
+                    error.message
+                );
+            }
+            let mut storage = SqliteStorage::open(&path)?;
+            storage.import(&data)?;
+            Ok(Box::new(storage))
+        }
+    }
+}
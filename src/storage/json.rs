@@ -0,0 +1,294 @@
+use super::{Change, Collection, Dataset, LoadError, Storage};
+use crate::models::{Course, Enrollment, Faculty, Student, Teacher};
+use crate::rules::{Limit, Rules};
+use crate::trash::TrashItem;
+use anyhow::{bail, Context, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+// Building and head of a faculty created for an old department, until someone fills them in
+const UNKNOWN: &str = "Unknown";
+
+// One pretty-printed JSON file per collection. Any change rewrites the whole file.
+pub struct JsonStorage {
+    data_dir: PathBuf,
+    // Number of rotating `.bak` copies kept next to each data file
+    backup_count: usize,
+}
+
+impl JsonStorage {
+    pub fn new(data_dir: &Path, backup_count: usize) -> Result<Self> {
+        // Create the data directory if it doesn't exist
+        fs::create_dir_all(data_dir).context("Failed to create data directory")?;
+        Ok(Self {
+            data_dir: data_dir.to_path_buf(),
+            backup_count,
+        })
+    }
+
+    fn file_name(collection: Collection) -> String {
+        format!("{}.json", collection.name())
+    }
+
+    // Load a data file, recording why it could not be read instead of failing
+    fn load_or_report<T: DeserializeOwned>(&self, collection: Collection, errors: &mut Vec<LoadError>) -> Vec<T> {
+        let filename = Self::file_name(collection);
+        match self.load_from_file(&filename) {
+            Ok(records) => records,
+            Err(err) => {
+                errors.push(LoadError::new(collection, filename, &err));
+                Vec::new()
+            }
+        }
+    }
+
+    fn save_collection(&self, data: &Dataset, collection: Collection) -> Result<()> {
+        let filename = Self::file_name(collection);
+        match collection {
+            Collection::Students => self.save_to_file(&data.students, &filename),
+            Collection::Teachers => self.save_to_file(&data.teachers, &filename),
+            Collection::Faculties => self.save_to_file(&data.faculties, &filename),
+            Collection::Courses => self.save_to_file(&data.courses, &filename),
+            Collection::Enrollments => self.save_to_file(&data.enrollments, &filename),
+            Collection::Trash => self.save_to_file(&data.trash, &filename),
+        }
+    }
+
+    fn is_readable_data_file(collection: Collection, path: &Path) -> bool {
+        fn parses<T: DeserializeOwned>(path: &Path) -> bool {
+            File::open(path)
+                .ok()
+                .and_then(|file| serde_json::from_reader::<_, Vec<T>>(BufReader::new(file)).ok())
+                .is_some()
+        }
+        match collection {
+            Collection::Students => parses::<Student>(path),
+            Collection::Teachers => parses::<Teacher>(path),
+            Collection::Faculties => parses::<Faculty>(path),
+            Collection::Courses => parses::<Course>(path),
+            Collection::Enrollments => parses::<Enrollment>(path),
+            Collection::Trash => parses::<TrashItem>(path),
+        }
+    }
+
+    // Bring data files written by older versions up to date. This rewrites them, so it runs
+    // once when the storage is opened instead of on every load.
+    pub fn migrate(&self, rules: &Rules) -> Result<()> {
+        // Linking old departments needs the real faculty list, so nothing is migrated while
+        // it is unreadable; the load reports it instead
+        let Ok(mut faculties) = self.load_from_file::<Faculty>(&Self::file_name(Collection::Faculties)) else {
+            return Ok(());
+        };
+        self.migrate_teacher_departments(&mut faculties, rules)
+    }
+
+    // Older teacher records store a free-text `department` instead of a `faculty_id`.
+    // Link each one to the faculty with that name, creating the faculty if none exists.
+    fn migrate_teacher_departments(&self, faculties: &mut Vec<Faculty>, rules: &Rules) -> Result<()> {
+        // Unreadable files are left for the typed load to report
+        let Ok(mut records) = self.load_from_file::<Value>("teachers.json") else {
+            return Ok(());
+        };
+        let mut migrated = false;
+        let mut created = Vec::new();
+        for record in records.iter_mut() {
+            let Some(object) = record.as_object_mut() else {
+                continue;
+            };
+            if object.contains_key("faculty_id") {
+                continue;
+            }
+            let Some(Value::String(department)) = object.remove("department") else {
+                continue;
+            };
+
+            let existing = faculties
+                .iter()
+                .find(|f| f.name.eq_ignore_ascii_case(department.trim()))
+                .map(|f| f.id.clone());
+            let faculty_id = match existing {
+                Some(id) => id,
+                None => {
+                    let faculty = placeholder_faculty(department.trim().to_string(), rules);
+                    let id = faculty.id.clone();
+                    faculties.push(faculty);
+                    created.push(id.clone());
+                    id
+                }
+            };
+            object.insert("faculty_id".to_string(), Value::String(faculty_id));
+            migrated = true;
+        }
+
+        if migrated {
+            // A new faculty counts the teachers linked to it as its staff
+            let (min_staff, max_staff) = rules.range(Limit::NumStaff);
+            for faculty in faculties.iter_mut().filter(|f| created.contains(&f.id)) {
+                let linked = records.iter().filter(|r| r.get("faculty_id").and_then(Value::as_str) == Some(&faculty.id)).count();
+                faculty.num_staff = (linked as u32).clamp(min_staff, max_staff);
+            }
+            self.save_to_file(faculties, "faculties.json")?;
+            self.save_to_file(&records, "teachers.json")?;
+        }
+        Ok(())
+    }
+
+    // Generic method to load entities from a JSON file
+    fn load_from_file<T: DeserializeOwned>(&self, filename: &str) -> Result<Vec<T>> {
+        let file_path = self.data_dir.join(filename);
+
+        if !file_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let file = File::open(&file_path).context(format!("Failed to open {}", filename))?;
+        let reader = BufReader::new(file);
+        let data = serde_json::from_reader(reader).context(format!("Failed to parse {}", filename))?;
+        Ok(data)
+    }
+
+    // Generic method to save entities to a JSON file, keeping the old contents as a backup
+    fn save_to_file<T: Serialize>(&self, data: &[T], filename: &str) -> Result<()> {
+        self.replace_file(filename, true, |path| Self::write_synced(data, path))
+    }
+
+    // Replace a data file with what `write` puts in a temporary file. The temporary file is
+    // synced and then renamed over the original, so a crash or a full disk never leaves a
+    // truncated file behind. With `back_up`, the replaced contents join the backups.
+    fn replace_file(&self, filename: &str, back_up: bool, write: impl FnOnce(&Path) -> Result<()>) -> Result<()> {
+        let file_path = self.data_dir.join(filename);
+        let tmp_path = self.data_dir.join(format!("{}.tmp", filename));
+
+        if let Err(err) = write(&tmp_path) {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(err).context(format!("Failed to write data to {}", filename));
+        }
+
+        if back_up {
+            self.rotate_backups(filename)?;
+        }
+        fs::rename(&tmp_path, &file_path).context(format!("Failed to replace {}", filename))?;
+
+        // Persist the rename itself; directories can't be opened for syncing on Windows
+        #[cfg(unix)]
+        File::open(&self.data_dir)
+            .and_then(|dir| dir.sync_all())
+            .context("Failed to sync data directory")?;
+        Ok(())
+    }
+
+    fn write_synced<T: Serialize>(data: &[T], path: &Path) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, data)?;
+        let file = writer.into_inner().map_err(|err| err.into_error())?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    fn copy_synced(from: &Path, to: &Path) -> Result<()> {
+        fs::copy(from, to)?;
+        // Windows only syncs files opened for writing
+        OpenOptions::new().write(true).open(to)?.sync_all()?;
+        Ok(())
+    }
+
+    // Path of the `n`th backup of a data file; 1 is the most recent
+    fn backup_path(&self, filename: &str, n: usize) -> PathBuf {
+        self.data_dir.join(format!("{}.{}.bak", filename, n))
+    }
+
+    // Shift existing backups up by one, dropping the oldest, and copy the current file
+    // into the newest slot. The original stays in place until it is replaced.
+    fn rotate_backups(&self, filename: &str) -> Result<()> {
+        let file_path = self.data_dir.join(filename);
+        if self.backup_count == 0 || !file_path.exists() {
+            return Ok(());
+        }
+
+        for n in (1..self.backup_count).rev() {
+            let from = self.backup_path(filename, n);
+            if from.exists() {
+                fs::rename(&from, self.backup_path(filename, n + 1))
+                    .context(format!("Failed to rotate backups of {}", filename))?;
+            }
+        }
+        fs::copy(&file_path, self.backup_path(filename, 1))
+            .context(format!("Failed to back up {}", filename))?;
+        Ok(())
+    }
+}
+
+impl Storage for JsonStorage {
+    fn load(&mut self) -> Result<(Dataset, Vec<LoadError>)> {
+        let mut errors = Vec::new();
+        let data = Dataset {
+            students: self.load_or_report(Collection::Students, &mut errors),
+            teachers: self.load_or_report(Collection::Teachers, &mut errors),
+            faculties: self.load_or_report(Collection::Faculties, &mut errors),
+            courses: self.load_or_report(Collection::Courses, &mut errors),
+            enrollments: self.load_or_report(Collection::Enrollments, &mut errors),
+            trash: self.load_or_report(Collection::Trash, &mut errors),
+        };
+        Ok((data, errors))
+    }
+
+    fn commit(&mut self, data: &Dataset, changes: &[Change]) -> Result<()> {
+        // Each touched file is rewritten once, however many of its records changed
+        for collection in Collection::ALL {
+            if changes.iter().any(|change| change.collection() == collection) {
+                self.save_collection(data, collection)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn load_raw(&mut self, collection: Collection) -> Result<Vec<Value>> {
+        let filename = Self::file_name(collection);
+        self.load_from_file(&filename)
+            .context(format!("{} is not a readable JSON list; restore a backup instead", filename))
+    }
+
+    // The damaged file is kept next to the restored one as `<file>.corrupt`
+    fn restore_backups(&mut self, collections: &[Collection]) -> Result<()> {
+        // Find a backup for every file before touching any of them
+        let mut restores = Vec::new();
+        for &collection in collections {
+            let filename = Self::file_name(collection);
+            let backup = (1..=self.backup_count)
+                .map(|n| self.backup_path(&filename, n))
+                .find(|path| path.exists() && Self::is_readable_data_file(collection, path));
+            match backup {
+                Some(backup) => restores.push((filename, backup)),
+                None => bail!("No readable backup of {}", filename),
+            }
+        }
+
+        for (filename, backup) in restores {
+            let file_path = self.data_dir.join(&filename);
+            if file_path.exists() {
+                fs::copy(&file_path, self.data_dir.join(format!("{}.corrupt", filename)))
+                    .context(format!("Failed to keep a copy of {}", filename))?;
+            }
+            // Restored like a save, but the damaged contents don't push a good backup out
+            self.replace_file(&filename, false, |path| Self::copy_synced(&backup, path))
+                .context(format!("Failed to restore {}", filename))?;
+        }
+        Ok(())
+    }
+
+    fn watched_files(&self) -> Vec<PathBuf> {
+        Collection::ALL.iter().map(|&c| self.data_dir.join(Self::file_name(c))).collect()
+    }
+}
+
+// A faculty for an old department name, with values that pass validation so it can be
+// edited like any other
+fn placeholder_faculty(name: String, rules: &Rules) -> Faculty {
+    // The latest allowed year, so it reads as recently added rather than made up
+    let (_, established_year) = rules.range(Limit::EstablishedYear);
+    let (num_staff, _) = rules.range(Limit::NumStaff);
+    Faculty::new(name, UNKNOWN.to_string(), UNKNOWN.to_string(), established_year, num_staff)
+}
@@ -0,0 +1,383 @@
+use super::{Change, Collection, Dataset, LoadError, Storage};
+use crate::models::{Course, Enrollment, Faculty, Grade, GradingScale, Student, Teacher};
+use crate::trash::TrashItem;
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection, Transaction};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// Database file created inside the data directory
+pub const DATABASE_FILE: &str = "university.db";
+
+// Records keep their insertion order through `rowid`, which upserts preserve
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS students (
+        id TEXT PRIMARY KEY,
+        first_name TEXT NOT NULL,
+        last_name TEXT NOT NULL,
+        age INTEGER NOT NULL,
+        major TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS teachers (
+        id TEXT PRIMARY KEY,
+        first_name TEXT NOT NULL,
+        last_name TEXT NOT NULL,
+        age INTEGER NOT NULL,
+        faculty_id TEXT NOT NULL,
+        title TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS faculties (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        building TEXT NOT NULL,
+        head_name TEXT NOT NULL,
+        established_year INTEGER NOT NULL,
+        num_staff INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS courses (
+        id TEXT PRIMARY KEY,
+        code TEXT NOT NULL,
+        title TEXT NOT NULL,
+        credits INTEGER NOT NULL,
+        faculty_id TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS course_teachers (
+        course_id TEXT NOT NULL,
+        teacher_id TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        PRIMARY KEY (course_id, teacher_id)
+    );
+    CREATE TABLE IF NOT EXISTS enrollments (
+        id TEXT PRIMARY KEY,
+        student_id TEXT NOT NULL,
+        course_id TEXT NOT NULL,
+        term TEXT NOT NULL,
+        grade_points REAL,
+        grade_letter TEXT,
+        grade_scale TEXT
+    );
+    CREATE TABLE IF NOT EXISTS trash (
+        id TEXT PRIMARY KEY,
+        deleted_at TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        name TEXT NOT NULL,
+        records TEXT NOT NULL
+    );
+";
+
+// Columns added after the first release, created in databases that predate them
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("enrollments", "grade_scale", "TEXT"),
+    ("students", "version", "INTEGER NOT NULL DEFAULT 0"),
+    ("teachers", "version", "INTEGER NOT NULL DEFAULT 0"),
+    ("faculties", "version", "INTEGER NOT NULL DEFAULT 0"),
+    ("courses", "version", "INTEGER NOT NULL DEFAULT 0"),
+    ("enrollments", "version", "INTEGER NOT NULL DEFAULT 0"),
+    ("students", "gpa", "REAL"),
+];
+
+// One table per collection; only the records that changed are written
+pub struct SqliteStorage {
+    conn: Connection,
+    path: PathBuf,
+}
+
+impl SqliteStorage {
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path).context(format!("Failed to open {}", path.display()))?;
+        conn.execute_batch(SCHEMA).context("Failed to create database tables")?;
+        for (table, column, kind) in ADDED_COLUMNS {
+            let exists = conn
+                .prepare(&format!("SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1", table))?
+                .exists([column])?;
+            if !exists {
+                conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, kind))
+                    .context(format!("Failed to add column {}.{}", table, column))?;
+            }
+        }
+        Ok(Self { conn, path: path.to_path_buf() })
+    }
+
+    // Write every record of `data`, e.g. when starting from the JSON files
+    pub fn import(&mut self, data: &Dataset) -> Result<()> {
+        let mut changes = Vec::new();
+        changes.extend(data.students.iter().map(|s| Change::Put(Collection::Students, s.id.clone())));
+        changes.extend(data.teachers.iter().map(|t| Change::Put(Collection::Teachers, t.id.clone())));
+        changes.extend(data.faculties.iter().map(|f| Change::Put(Collection::Faculties, f.id.clone())));
+        changes.extend(data.courses.iter().map(|c| Change::Put(Collection::Courses, c.id.clone())));
+        changes.extend(data.enrollments.iter().map(|e| Change::Put(Collection::Enrollments, e.id.clone())));
+        changes.extend(data.trash.iter().map(|t| Change::Put(Collection::Trash, t.id.clone())));
+        self.commit(data, &changes)
+    }
+
+    fn load_students(&self) -> Result<Vec<Student>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, first_name, last_name, age, major, version, gpa FROM students ORDER BY rowid",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(Student {
+                version: row.get(5)?,
+                recorded_gpa: row.get(6)?,
+                ..Student::with_id(row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    fn load_teachers(&self) -> Result<Vec<Teacher>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, first_name, last_name, age, faculty_id, title, version FROM teachers ORDER BY rowid",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(Teacher {
+                version: row.get(6)?,
+                ..Teacher::with_id(row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?)
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    fn load_faculties(&self) -> Result<Vec<Faculty>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, building, head_name, established_year, num_staff, version FROM faculties ORDER BY rowid",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(Faculty {
+                version: row.get(6)?,
+                ..Faculty::with_id(row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?)
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    fn load_courses(&self) -> Result<Vec<Course>> {
+        let mut teachers: HashMap<String, Vec<String>> = HashMap::new();
+        let mut stmt = self.conn.prepare(
+            "SELECT course_id, teacher_id FROM course_teachers ORDER BY course_id, position",
+        )?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            teachers.entry(row.get(0)?).or_default().push(row.get(1)?);
+        }
+
+        let mut stmt = self.conn.prepare(
+            "SELECT id, code, title, credits, faculty_id, version FROM courses ORDER BY rowid",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let id: String = row.get(0)?;
+            let teacher_ids = teachers.remove(&id).unwrap_or_default();
+            Ok(Course {
+                version: row.get(5)?,
+                ..Course::with_id(id, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, teacher_ids)
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    fn load_enrollments(&self) -> Result<Vec<Enrollment>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, student_id, course_id, term, grade_points, grade_letter, grade_scale, version
+             FROM enrollments ORDER BY rowid",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let points: Option<f64> = row.get(4)?;
+            let letter: Option<String> = row.get(5)?;
+            let scale: Option<String> = row.get(6)?;
+            let scale = scale
+                .map(|scale| scale.parse::<GradingScale>())
+                .transpose()
+                .map_err(|err| rusqlite::Error::FromSqlConversionFailure(6, rusqlite::types::Type::Text, err.into()))?;
+            // Grades on another scale keep their value in the letter column
+            let grade = match (points, letter, scale) {
+                (Some(points), _, _) => Some(Grade::Points(points as f32)),
+                (None, Some(value), Some(scale)) => Some(Grade::Scaled { scale, value }),
+                (None, Some(letter), None) => Some(Grade::Letter(letter)),
+                (None, None, _) => None,
+            };
+            Ok(Enrollment {
+                version: row.get(7)?,
+                ..Enrollment::with_id(row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, grade)
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    // The deleted records of an item are kept together as JSON
+    fn load_trash(&self) -> Result<Vec<TrashItem>> {
+        let mut stmt = self.conn.prepare("SELECT id, deleted_at, kind, name, records FROM trash ORDER BY rowid")?;
+        let mut rows = stmt.query([])?;
+        let mut items = Vec::new();
+        while let Some(row) = rows.next()? {
+            let deleted_at: String = row.get(1)?;
+            let kind: String = row.get(2)?;
+            let records: String = row.get(4)?;
+            items.push(TrashItem {
+                id: row.get(0)?,
+                deleted_at: deleted_at.parse().context("Invalid deletion time in the trash table")?,
+                kind: kind.parse().map_err(anyhow::Error::msg)?,
+                name: row.get(3)?,
+                records: serde_json::from_str(&records)?,
+            });
+        }
+        Ok(items)
+    }
+
+    fn load_or_report<T>(
+        &self,
+        collection: Collection,
+        load: fn(&Self) -> Result<Vec<T>>,
+        errors: &mut Vec<LoadError>,
+    ) -> Vec<T> {
+        match load(self) {
+            Ok(records) => records,
+            Err(err) => {
+                let location = format!("{} ({} table)", DATABASE_FILE, collection.name());
+                errors.push(LoadError::new(collection, location, &err));
+                Vec::new()
+            }
+        }
+    }
+
+    // Insert or update one record; a record that is no longer in `data` is skipped
+    fn put(tx: &Transaction, data: &Dataset, collection: Collection, id: &str) -> Result<()> {
+        match collection {
+            Collection::Students => {
+                let Some(s) = data.students.iter().find(|s| s.id == id) else {
+                    return Ok(());
+                };
+                tx.execute(
+                    "INSERT INTO students (id, first_name, last_name, age, major, version, gpa) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                     ON CONFLICT(id) DO UPDATE SET first_name = excluded.first_name,
+                         last_name = excluded.last_name, age = excluded.age, major = excluded.major,
+                         version = excluded.version, gpa = excluded.gpa",
+                    params![s.id, s.first_name, s.last_name, s.age, s.major, s.version, s.recorded_gpa],
+                )?;
+            }
+            Collection::Teachers => {
+                let Some(t) = data.teachers.iter().find(|t| t.id == id) else {
+                    return Ok(());
+                };
+                tx.execute(
+                    "INSERT INTO teachers (id, first_name, last_name, age, faculty_id, title, version)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                     ON CONFLICT(id) DO UPDATE SET first_name = excluded.first_name,
+                         last_name = excluded.last_name, age = excluded.age,
+                         faculty_id = excluded.faculty_id, title = excluded.title, version = excluded.version",
+                    params![t.id, t.first_name, t.last_name, t.age, t.faculty_id, t.title, t.version],
+                )?;
+            }
+            Collection::Faculties => {
+                let Some(f) = data.faculties.iter().find(|f| f.id == id) else {
+                    return Ok(());
+                };
+                tx.execute(
+                    "INSERT INTO faculties (id, name, building, head_name, established_year, num_staff, version)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                     ON CONFLICT(id) DO UPDATE SET name = excluded.name, building = excluded.building,
+                         head_name = excluded.head_name, established_year = excluded.established_year,
+                         num_staff = excluded.num_staff, version = excluded.version",
+                    params![f.id, f.name, f.building, f.head_name, f.established_year, f.num_staff, f.version],
+                )?;
+            }
+            Collection::Courses => {
+                let Some(c) = data.courses.iter().find(|c| c.id == id) else {
+                    return Ok(());
+                };
+                tx.execute(
+                    "INSERT INTO courses (id, code, title, credits, faculty_id, version) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                     ON CONFLICT(id) DO UPDATE SET code = excluded.code, title = excluded.title,
+                         credits = excluded.credits, faculty_id = excluded.faculty_id, version = excluded.version",
+                    params![c.id, c.code, c.title, c.credits, c.faculty_id, c.version],
+                )?;
+                tx.execute("DELETE FROM course_teachers WHERE course_id = ?1", params![c.id])?;
+                for (position, teacher_id) in c.teacher_ids.iter().enumerate() {
+                    tx.execute(
+                        "INSERT OR IGNORE INTO course_teachers (course_id, teacher_id, position) VALUES (?1, ?2, ?3)",
+                        params![c.id, teacher_id, position as i64],
+                    )?;
+                }
+            }
+            Collection::Enrollments => {
+                let Some(e) = data.enrollments.iter().find(|e| e.id == id) else {
+                    return Ok(());
+                };
+                let (points, letter, scale) = match &e.grade {
+                    Some(Grade::Points(points)) => (Some(*points as f64), None, None),
+                    Some(Grade::Letter(letter)) => (None, Some(letter.clone()), None),
+                    Some(Grade::Scaled { scale, value }) => (None, Some(value.clone()), Some(scale.to_string())),
+                    None => (None, None, None),
+                };
+                tx.execute(
+                    "INSERT INTO enrollments (id, student_id, course_id, term, grade_points, grade_letter, grade_scale, version)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                     ON CONFLICT(id) DO UPDATE SET student_id = excluded.student_id,
+                         course_id = excluded.course_id, term = excluded.term,
+                         grade_points = excluded.grade_points, grade_letter = excluded.grade_letter,
+                         grade_scale = excluded.grade_scale, version = excluded.version",
+                    params![e.id, e.student_id, e.course_id, e.term, points, letter, scale, e.version],
+                )?;
+            }
+            Collection::Trash => {
+                let Some(t) = data.trash.iter().find(|t| t.id == id) else {
+                    return Ok(());
+                };
+                // Items never change once trashed, so an existing row is left alone
+                tx.execute(
+                    "INSERT OR IGNORE INTO trash (id, deleted_at, kind, name, records) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![t.id, t.deleted_at.to_rfc3339(), t.kind.to_string(), t.name, serde_json::to_string(&t.records)?],
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn delete(tx: &Transaction, collection: Collection, id: &str) -> Result<()> {
+        if collection == Collection::Courses {
+            tx.execute("DELETE FROM course_teachers WHERE course_id = ?1", params![id])?;
+        }
+        // Table names come from the fixed set of collections
+        tx.execute(&format!("DELETE FROM {} WHERE id = ?1", collection.name()), params![id])?;
+        Ok(())
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn load(&mut self) -> Result<(Dataset, Vec<LoadError>)> {
+        let mut errors = Vec::new();
+        let data = Dataset {
+            students: self.load_or_report(Collection::Students, Self::load_students, &mut errors),
+            teachers: self.load_or_report(Collection::Teachers, Self::load_teachers, &mut errors),
+            faculties: self.load_or_report(Collection::Faculties, Self::load_faculties, &mut errors),
+            courses: self.load_or_report(Collection::Courses, Self::load_courses, &mut errors),
+            enrollments: self.load_or_report(Collection::Enrollments, Self::load_enrollments, &mut errors),
+            trash: self.load_or_report(Collection::Trash, Self::load_trash, &mut errors),
+        };
+        Ok((data, errors))
+    }
+
+    // All changes go in one transaction, so a cascading delete is applied completely or not at all
+    fn commit(&mut self, data: &Dataset, changes: &[Change]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for change in changes {
+            match change {
+                Change::Put(collection, id) => Self::put(&tx, data, *collection, id)?,
+                Change::Delete(collection, id) => Self::delete(&tx, *collection, id)?,
+            }
+        }
+        tx.commit().context("Failed to save changes to the database")?;
+        Ok(())
+    }
+
+    fn load_raw(&mut self, collection: Collection) -> Result<Vec<Value>> {
+        bail!("The SQLite database can't skip individual {} records; open it read-only instead", collection.name())
+    }
+
+    fn restore_backups(&mut self, _collections: &[Collection]) -> Result<()> {
+        bail!("The SQLite database keeps no backups")
+    }
+
+    fn watched_files(&self) -> Vec<PathBuf> {
+        vec![self.path.clone()]
+    }
+}
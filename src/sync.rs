@@ -0,0 +1,307 @@
+use crate::storage::{Change, Collection, Dataset};
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+// Keeping the data in memory in step with the files on disk, which other programs or
+// another instance may change while the application runs.
+
+// What a file looked like when last seen: its modification time and size, or `None`
+// while it doesn't exist
+type Stamp = Option<(SystemTime, u64)>;
+
+fn stamp(path: &Path) -> Stamp {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+// Lock file in the data directory; instances hold it while reading or writing the data
+const LOCK_FILE: &str = ".lock";
+
+// How long to wait for another instance to finish saving
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Advisory lock on the data directory. It keeps instances from reading or writing the
+// files while another one is in the middle of a save; programs that don't take it, such
+// as a text editor, are not held up.
+pub struct DataLock {
+    file: File,
+    path: PathBuf,
+}
+
+// Releases the lock when dropped
+pub struct LockGuard<'a>(&'a File);
+
+impl Drop for LockGuard<'_> {
+    fn drop(&mut self) {
+        let _ = self.0.unlock();
+    }
+}
+
+impl DataLock {
+    pub fn open(data_dir: &Path) -> Result<Self> {
+        fs::create_dir_all(data_dir).context("Failed to create data directory")?;
+        let path = data_dir.join(LOCK_FILE);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        Ok(Self { file, path })
+    }
+
+    // Wait until no other instance holds the lock, then take it
+    pub fn acquire(&self) -> Result<LockGuard<'_>> {
+        let started = Instant::now();
+        loop {
+            match self.file.try_lock() {
+                Ok(()) => return Ok(LockGuard(&self.file)),
+                Err(TryLockError::WouldBlock) => {
+                    if started.elapsed() > LOCK_TIMEOUT {
+                        bail!("Another instance is still saving ({} is locked); try again", self.path.display());
+                    }
+                    thread::sleep(Duration::from_millis(50));
+                }
+                Err(TryLockError::Error(err)) => {
+                    return Err(err).with_context(|| format!("Failed to lock {}", self.path.display()));
+                }
+            }
+        }
+    }
+}
+
+// A save refused because records it would write were also changed by another instance
+// or program since they were loaded
+#[derive(Debug)]
+pub struct Conflict {
+    // e.g. "student Ann Lee"
+    pub records: Vec<String>,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} changed elsewhere since it was loaded; reload (r) to merge", self.records.join(", "))
+    }
+}
+
+impl std::error::Error for Conflict {}
+
+// Notices changes to the data files by comparing their stamps, polled on every tick
+pub struct FileWatcher {
+    files: Vec<PathBuf>,
+    seen: Vec<Stamp>,
+    // The files as they were when the user chose to keep working without reloading
+    dismissed: Vec<Stamp>,
+}
+
+impl FileWatcher {
+    pub fn new(files: Vec<PathBuf>) -> Self {
+        let seen: Vec<Stamp> = files.iter().map(|path| stamp(path)).collect();
+        Self { files, dismissed: seen.clone(), seen }
+    }
+
+    fn stamps(&self) -> Vec<Stamp> {
+        self.files.iter().map(|path| stamp(path)).collect()
+    }
+
+    // Names of the files that changed since they were last marked as seen
+    pub fn changed(&self) -> Vec<String> {
+        self.files
+            .iter()
+            .zip(&self.seen)
+            .filter(|(path, seen)| stamp(path) != **seen)
+            .map(|(path, _)| path.file_name().unwrap_or_default().to_string_lossy().to_string())
+            .collect()
+    }
+
+    // Take the files as they are now, e.g. after loading or saving them
+    pub fn mark_seen(&mut self) {
+        self.seen = self.stamps();
+        self.dismissed = self.seen.clone();
+    }
+
+    // Stop reporting the current changes, while still treating them as unread
+    pub fn dismiss(&mut self) {
+        self.dismissed = self.stamps();
+    }
+
+    // Whether the files changed no further since `dismiss`
+    pub fn is_dismissed(&self) -> bool {
+        self.stamps() == self.dismissed
+    }
+}
+
+// The result of merging the records changed in memory into the data read from disk
+#[derive(Debug, Default)]
+pub struct Merge {
+    pub data: Dataset,
+    // Records of `data` that differ from the disk and have to be saved
+    pub changes: Vec<Change>,
+    // Records changed both in memory and on disk, in different ways
+    pub conflicts: Vec<(Collection, String)>,
+}
+
+// Three-way merge: `base` is the data as last loaded or saved, `ours` the data in memory
+// and `theirs` the data now on disk. Records only changed on one side take that side's
+// version; where both changed a record, `keep_mine` picks ours over theirs.
+pub fn merge(base: &Dataset, ours: &Dataset, mut theirs: Dataset, keep_mine: bool) -> Merge {
+    let mut merge = Merge::default();
+    merge.list(Collection::Students, &base.students, &ours.students, &mut theirs.students, |s| &s.id, keep_mine);
+    merge.list(Collection::Teachers, &base.teachers, &ours.teachers, &mut theirs.teachers, |t| &t.id, keep_mine);
+    merge.list(Collection::Faculties, &base.faculties, &ours.faculties, &mut theirs.faculties, |f| &f.id, keep_mine);
+    merge.list(Collection::Courses, &base.courses, &ours.courses, &mut theirs.courses, |c| &c.id, keep_mine);
+    merge.list(Collection::Enrollments, &base.enrollments, &ours.enrollments, &mut theirs.enrollments, |e| &e.id, keep_mine);
+    merge.list(Collection::Trash, &base.trash, &ours.trash, &mut theirs.trash, |t| &t.id, keep_mine);
+    merge.data = theirs;
+    merge
+}
+
+// Number of records that differ between `base` and `ours`
+pub fn unsaved_count(base: &Dataset, ours: &Dataset) -> usize {
+    changed_ids(&base.students, &ours.students, |s| &s.id).len()
+        + changed_ids(&base.teachers, &ours.teachers, |t| &t.id).len()
+        + changed_ids(&base.faculties, &ours.faculties, |f| &f.id).len()
+        + changed_ids(&base.courses, &ours.courses, |c| &c.id).len()
+        + changed_ids(&base.enrollments, &ours.enrollments, |e| &e.id).len()
+        + changed_ids(&base.trash, &ours.trash, |t| &t.id).len()
+}
+
+// Records are compared by their JSON form, which is exactly what gets stored
+fn same<T: Serialize>(a: Option<&T>, b: Option<&T>) -> bool {
+    a.map(|a| serde_json::to_value(a).ok()) == b.map(|b| serde_json::to_value(b).ok())
+}
+
+// Ids of the records added, changed or removed from `base` to `ours`, in that order
+fn changed_ids<'a, T: Serialize>(base: &'a [T], ours: &'a [T], key: fn(&T) -> &str) -> Vec<&'a str> {
+    let base_by_id: HashMap<&str, &T> = base.iter().map(|r| (key(r), r)).collect();
+    let ours_by_id: HashMap<&str, &T> = ours.iter().map(|r| (key(r), r)).collect();
+    let ids = ours.iter().chain(base.iter().filter(|r| !ours_by_id.contains_key(key(r)))).map(key);
+    ids.filter(|id| !same(base_by_id.get(id).copied(), ours_by_id.get(id).copied())).collect()
+}
+
+impl Merge {
+    // Bring one collection's changes from `ours` into `theirs`
+    fn list<T: Clone + Serialize>(
+        &mut self,
+        collection: Collection,
+        base: &[T],
+        ours: &[T],
+        theirs: &mut Vec<T>,
+        key: fn(&T) -> &str,
+        keep_mine: bool,
+    ) {
+        for id in changed_ids(base, ours, key) {
+            let mine = ours.iter().find(|r| key(r) == id);
+            let on_disk = theirs.iter().position(|r| key(r) == id);
+            if same(mine, on_disk.map(|index| &theirs[index])) {
+                continue;
+            }
+            // The disk changed the record too, so one of the two versions is lost
+            if !same(base.iter().find(|r| key(r) == id), on_disk.map(|index| &theirs[index])) {
+                self.conflicts.push((collection, id.to_string()));
+                if !keep_mine {
+                    continue;
+                }
+            }
+            match (mine, on_disk) {
+                (Some(record), Some(index)) => theirs[index] = record.clone(),
+                (Some(record), None) => theirs.push(record.clone()),
+                (None, Some(index)) => {
+                    theirs.remove(index);
+                }
+                (None, None) => {}
+            }
+            self.changes.push(match mine {
+                Some(_) => Change::Put(collection, id.to_string()),
+                None => Change::Delete(collection, id.to_string()),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Student;
+
+    fn dataset(students: &[&Student]) -> Dataset {
+        Dataset { students: students.iter().map(|s| (*s).clone()).collect(), ..Dataset::default() }
+    }
+
+    fn with_age(student: &Student, age: u32) -> Student {
+        Student { age, ..student.clone() }
+    }
+
+    fn ages(data: &Dataset) -> Vec<(String, u32)> {
+        data.students.iter().map(|s| (s.first_name.clone(), s.age)).collect()
+    }
+
+    #[test]
+    fn merges_edits_to_different_records() {
+        let ann = Student::new("Ann".to_string(), "Lee".to_string(), 20, "Physics".to_string());
+        let bob = Student::new("Bob".to_string(), "Ray".to_string(), 21, "History".to_string());
+        let cat = Student::new("Cat".to_string(), "Fox".to_string(), 22, "Biology".to_string());
+        let base = dataset(&[&ann, &bob]);
+        let ours = dataset(&[&with_age(&ann, 30), &bob, &cat]);
+        let theirs = dataset(&[&ann, &with_age(&bob, 31)]);
+
+        let merge = merge(&base, &ours, theirs, false);
+        assert!(merge.conflicts.is_empty());
+        assert_eq!(ages(&merge.data), [("Ann".to_string(), 30), ("Bob".to_string(), 31), ("Cat".to_string(), 22)]);
+        assert_eq!(
+            merge.changes,
+            [Change::Put(Collection::Students, ann.id.clone()), Change::Put(Collection::Students, cat.id.clone())]
+        );
+        assert_eq!(unsaved_count(&base, &ours), 2);
+    }
+
+    #[test]
+    fn merges_deletions() {
+        let ann = Student::new("Ann".to_string(), "Lee".to_string(), 20, "Physics".to_string());
+        let bob = Student::new("Bob".to_string(), "Ray".to_string(), 21, "History".to_string());
+        let base = dataset(&[&ann, &bob]);
+        let ours = dataset(&[&ann]);
+        let theirs = dataset(&[&with_age(&ann, 30), &bob]);
+
+        let merge = merge(&base, &ours, theirs, false);
+        assert!(merge.conflicts.is_empty());
+        assert_eq!(ages(&merge.data), [("Ann".to_string(), 30)]);
+        assert_eq!(merge.changes, [Change::Delete(Collection::Students, bob.id.clone())]);
+    }
+
+    #[test]
+    fn reports_records_changed_on_both_sides() {
+        let ann = Student::new("Ann".to_string(), "Lee".to_string(), 20, "Physics".to_string());
+        let base = dataset(&[&ann]);
+        let ours = dataset(&[&with_age(&ann, 30)]);
+        let theirs = dataset(&[&with_age(&ann, 40)]);
+
+        let kept_theirs = merge(&base, &ours, theirs.clone(), false);
+        assert_eq!(kept_theirs.conflicts, [(Collection::Students, ann.id.clone())]);
+        assert_eq!(ages(&kept_theirs.data), [("Ann".to_string(), 40)]);
+        assert!(kept_theirs.changes.is_empty());
+
+        let kept_mine = merge(&base, &ours, theirs, true);
+        assert_eq!(kept_mine.conflicts, [(Collection::Students, ann.id.clone())]);
+        assert_eq!(ages(&kept_mine.data), [("Ann".to_string(), 30)]);
+        assert_eq!(kept_mine.changes, [Change::Put(Collection::Students, ann.id.clone())]);
+    }
+
+    #[test]
+    fn identical_edits_on_both_sides_do_not_conflict() {
+        let ann = Student::new("Ann".to_string(), "Lee".to_string(), 20, "Physics".to_string());
+        let base = dataset(&[&ann]);
+        let ours = dataset(&[&with_age(&ann, 30)]);
+
+        let merge = merge(&base, &ours, ours.clone(), false);
+        assert!(merge.conflicts.is_empty());
+        assert!(merge.changes.is_empty());
+        assert_eq!(ages(&merge.data), [("Ann".to_string(), 30)]);
+    }
+}
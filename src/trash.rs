@@ -0,0 +1,71 @@
+use crate::models::{Course, EntityKind, Enrollment, Faculty, Student, Teacher};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+// Deleted records, kept until they are restored or purged. A delete moves the record and
+// everything a cascade took with it into one item, so restoring brings them all back.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashItem {
+    pub id: String,
+    pub deleted_at: DateTime<Local>,
+    // Kind and name of the record the user deleted
+    pub kind: EntityKind,
+    pub name: String,
+    pub records: TrashedRecords,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrashedRecords {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub students: Vec<Student>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub teachers: Vec<Teacher>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub faculties: Vec<Faculty>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub courses: Vec<Course>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub enrollments: Vec<Enrollment>,
+}
+
+impl TrashItem {
+    pub fn new(kind: EntityKind, name: String, records: TrashedRecords) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            deleted_at: Local::now(),
+            kind,
+            name,
+            records,
+        }
+    }
+
+    pub fn deleted_time(&self) -> String {
+        self.deleted_at.format("%Y-%m-%d %H:%M").to_string()
+    }
+
+    // Counts of the records deleted along with the main one, e.g. "2 courses, 5 enrollments"
+    pub fn dependents_summary(&self) -> String {
+        let records = &self.records;
+        // The deleted record itself is one of its kind's list
+        let own = |kind: EntityKind, count: usize| if self.kind == kind { count.saturating_sub(1) } else { count };
+        [
+            (own(EntityKind::Student, records.students.len()), "student", "students"),
+            (own(EntityKind::Teacher, records.teachers.len()), "teacher", "teachers"),
+            (own(EntityKind::Faculty, records.faculties.len()), "faculty", "faculties"),
+            (own(EntityKind::Course, records.courses.len()), "course", "courses"),
+            (records.enrollments.len(), "enrollment", "enrollments"),
+        ]
+        .into_iter()
+        .filter(|(count, _, _)| *count > 0)
+        .map(|(count, one, many)| format!("{} {}", count, if count == 1 { one } else { many }))
+        .collect::<Vec<_>>()
+        .join(", ")
+    }
+
+    // Older than `days` days; 0 days means items are kept until purged by hand
+    pub fn is_expired(&self, days: u32) -> bool {
+        days > 0 && Local::now() - self.deleted_at > chrono::Duration::days(days.into())
+    }
+}
@@ -1,18 +1,78 @@
-use crate::models::{Faculty, Student, Teacher};
+use crate::audit::AuditEntry;
+use crate::data_manager::{DataManager, DiskChanges, SearchResults};
+use crate::keymap::{Action, KeyContext, Keymap};
+use crate::models::{Course, EntityKind, Faculty, Student, Teacher};
+use crate::storage::LoadError;
+use crate::trash::TrashItem;
+use crate::hit_map::{Hit, HitMap};
+use crate::widgets::TextInput;
+use std::cmp::Ordering;
+use std::rc::Rc;
 use ratatui::{
-    layout::{Constraint, Direction, Layout, Margin, Rect},
+    layout::{Constraint, Direction, Flex, Layout, Margin, Rect},
     style::{Color, Modifier, Style},
     symbols,
     text::{Line, Span, Text},
     widgets::{
-        Block, BorderType, Borders, List, ListItem, Paragraph, Row, Scrollbar, ScrollbarOrientation, ScrollbarState,
-        Table, TableState, Tabs,
+        Block, BorderType, Borders, Clear, Paragraph, Row, Scrollbar, ScrollbarOrientation, ScrollbarState, Table,
+        TableState, Tabs, Wrap,
     },
     Frame,
 };
 
-// Tab titles - used both for rendering and click detection
-const TAB_TITLES: &[&str] = &["Students (1)", "Teachers (2)", "Faculties (3)"];
+// Tab titles, in the order of `ActiveTab::ALL`
+const TAB_TITLES: &[&str] = &["Students (1)", "Teachers (2)", "Faculties (3)", "Courses (4)", "Audit Log (5)", "Trash (6)"];
+
+// Table column titles and widths - used for rendering, sorting and header clicks
+const STUDENT_COLUMNS: &[&str] = &["Name", "Age", "Major", "GPA"];
+const STUDENT_WIDTHS: &[Constraint] = &[
+    Constraint::Percentage(40),
+    Constraint::Percentage(10),
+    Constraint::Percentage(35),
+    Constraint::Percentage(15),
+];
+const TEACHER_COLUMNS: &[&str] = &["Name", "Age", "Faculty", "Title"];
+const TEACHER_WIDTHS: &[Constraint] = &[
+    Constraint::Percentage(30),
+    Constraint::Percentage(10),
+    Constraint::Percentage(40),
+    Constraint::Percentage(20),
+];
+const FACULTY_COLUMNS: &[&str] = &["Name", "Building", "Head", "Est. Year", "Staff"];
+const FACULTY_WIDTHS: &[Constraint] = &[
+    Constraint::Percentage(25),
+    Constraint::Percentage(20),
+    Constraint::Percentage(25),
+    Constraint::Percentage(15),
+    Constraint::Percentage(15),
+];
+const COURSE_COLUMNS: &[&str] = &["Code", "Title", "Credits", "Faculty", "Teachers"];
+const COURSE_WIDTHS: &[Constraint] = &[
+    Constraint::Percentage(12),
+    Constraint::Percentage(33),
+    Constraint::Percentage(10),
+    Constraint::Percentage(20),
+    Constraint::Percentage(25),
+];
+const AUDIT_COLUMNS: &[&str] = &["Time", "User", "Action", "Entity", "Operation", "Changes"];
+const AUDIT_WIDTHS: &[Constraint] = &[
+    Constraint::Length(19),
+    Constraint::Length(10),
+    Constraint::Length(7),
+    Constraint::Length(10),
+    Constraint::Percentage(30),
+    Constraint::Fill(1),
+];
+const TRASH_COLUMNS: &[&str] = &["Deleted", "Kind", "Name", "Deleted With It"];
+const TRASH_WIDTHS: &[Constraint] = &[
+    Constraint::Length(16),
+    Constraint::Length(8),
+    Constraint::Percentage(40),
+    Constraint::Fill(1),
+];
+
+// Width of the ">> " marker in front of the selected row
+const HIGHLIGHT_SYMBOL: &str = ">> ";
 
 // Current active tab
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -20,44 +80,156 @@ pub enum ActiveTab {
     Students,
     Teachers,
     Faculties,
+    Courses,
+    // Read-only journal of every change
+    Audit,
+    // Deleted records, which can be restored or purged
+    Trash,
 }
 
 impl ActiveTab {
-    pub fn to_string(&self) -> &'static str {
-        match self {
-            ActiveTab::Students => "Students",
-            ActiveTab::Teachers => "Teachers",
-            ActiveTab::Faculties => "Faculties",
-        }
-    }
+    pub const ALL: [ActiveTab; 6] = [
+        ActiveTab::Students,
+        ActiveTab::Teachers,
+        ActiveTab::Faculties,
+        ActiveTab::Courses,
+        ActiveTab::Audit,
+        ActiveTab::Trash,
+    ];
 
     pub fn next(&self) -> Self {
         match self {
             ActiveTab::Students => ActiveTab::Teachers,
             ActiveTab::Teachers => ActiveTab::Faculties,
-            ActiveTab::Faculties => ActiveTab::Students,
+            ActiveTab::Faculties => ActiveTab::Courses,
+            ActiveTab::Courses => ActiveTab::Audit,
+            ActiveTab::Audit => ActiveTab::Trash,
+            ActiveTab::Trash => ActiveTab::Students,
         }
     }
 
     pub fn previous(&self) -> Self {
         match self {
-            ActiveTab::Students => ActiveTab::Faculties,
+            ActiveTab::Students => ActiveTab::Trash,
             ActiveTab::Teachers => ActiveTab::Students,
             ActiveTab::Faculties => ActiveTab::Teachers,
+            ActiveTab::Courses => ActiveTab::Faculties,
+            ActiveTab::Audit => ActiveTab::Courses,
+            ActiveTab::Trash => ActiveTab::Audit,
+        }
+    }
+
+    // Kind of record listed on this tab; the audit log and trash list none that can be edited
+    pub fn entity_kind(&self) -> Option<EntityKind> {
+        match self {
+            ActiveTab::Students => Some(EntityKind::Student),
+            ActiveTab::Teachers => Some(EntityKind::Teacher),
+            ActiveTab::Faculties => Some(EntityKind::Faculty),
+            ActiveTab::Courses => Some(EntityKind::Course),
+            ActiveTab::Audit | ActiveTab::Trash => None,
+        }
+    }
+
+    // Tab listing this kind of record
+    pub fn for_kind(kind: EntityKind) -> Self {
+        match kind {
+            EntityKind::Student => ActiveTab::Students,
+            EntityKind::Teacher => ActiveTab::Teachers,
+            EntityKind::Faculty => ActiveTab::Faculties,
+            EntityKind::Course => ActiveTab::Courses,
+        }
+    }
+
+    pub fn columns(&self) -> &'static [&'static str] {
+        match self {
+            ActiveTab::Students => STUDENT_COLUMNS,
+            ActiveTab::Teachers => TEACHER_COLUMNS,
+            ActiveTab::Faculties => FACULTY_COLUMNS,
+            ActiveTab::Courses => COURSE_COLUMNS,
+            ActiveTab::Audit => AUDIT_COLUMNS,
+            ActiveTab::Trash => TRASH_COLUMNS,
+        }
+    }
+
+    fn column_widths(&self) -> &'static [Constraint] {
+        match self {
+            ActiveTab::Students => STUDENT_WIDTHS,
+            ActiveTab::Teachers => TEACHER_WIDTHS,
+            ActiveTab::Faculties => FACULTY_WIDTHS,
+            ActiveTab::Courses => COURSE_WIDTHS,
+            ActiveTab::Audit => AUDIT_WIDTHS,
+            ActiveTab::Trash => TRASH_WIDTHS,
         }
     }
 }
 
+// Column a table is sorted by; without one, records keep their insertion order
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TableSort {
+    pub column: Option<usize>,
+    pub descending: bool,
+}
+
+impl TableSort {
+    // Sort by `column`, or flip the direction if the table is already sorted by it
+    pub fn toggle(&mut self, column: usize) {
+        if self.column == Some(column) {
+            self.descending = !self.descending;
+        } else {
+            self.column = Some(column);
+            self.descending = false;
+        }
+    }
+
+    // Sort ascending by the next column; after the last one, back to insertion order
+    pub fn next_column(&mut self, column_count: usize) {
+        self.column = match self.column {
+            None => Some(0),
+            Some(column) if column + 1 < column_count => Some(column + 1),
+            Some(_) => None,
+        };
+        self.descending = false;
+    }
+
+    // Flip the direction, sorting by the first column if the table isn't sorted yet
+    pub fn reverse(&mut self) {
+        self.column.get_or_insert(0);
+        self.descending = !self.descending;
+    }
+
+    fn apply(&self, ordering: Ordering) -> Ordering {
+        if self.descending { ordering.reverse() } else { ordering }
+    }
+}
+
 // App state structure
 pub struct AppState {
     pub active_tab: ActiveTab,
     pub student_list_state: TableState,
     pub teacher_list_state: TableState,
     pub faculty_list_state: TableState,
-    pub search_query: String,
-    pub show_help: bool,
+    pub course_list_state: TableState,
+    pub audit_list_state: TableState,
+    pub trash_list_state: TableState,
+    pub search_query: TextInput,
     pub notification: Option<String>,
     pub notification_timer: u16,
+    // Ticks a new notification stays visible
+    pub notification_ticks: u16,
+    pub student_sort: TableSort,
+    pub teacher_sort: TableSort,
+    pub faculty_sort: TableSort,
+    pub course_sort: TableSort,
+    pub audit_sort: TableSort,
+    pub trash_sort: TableSort,
+    // Rows that fit in the current table, as of the last render
+    pub table_page_size: usize,
+    pub show_detail_pane: bool,
+    pub show_help: bool,
+    // First line of the help overlay shown
+    pub help_scroll: u16,
+    // Where the last frame drew its clickable elements
+    pub hit_map: HitMap,
 }
 
 impl Default for AppState {
@@ -71,50 +243,78 @@ impl Default for AppState {
         let mut faculty_list_state = TableState::default();
         faculty_list_state.select(Some(0));
 
+        let mut course_list_state = TableState::default();
+        course_list_state.select(Some(0));
+
+        let mut audit_list_state = TableState::default();
+        audit_list_state.select(Some(0));
+
+        let mut trash_list_state = TableState::default();
+        trash_list_state.select(Some(0));
+
         Self {
             active_tab: ActiveTab::Students,
             student_list_state,
             teacher_list_state,
             faculty_list_state,
-            search_query: String::new(),
-            show_help: false,
+            course_list_state,
+            audit_list_state,
+            trash_list_state,
+            search_query: TextInput::default(),
             notification: None,
             notification_timer: 0,
+            notification_ticks: 30, // 3 seconds at 10 ticks/second
+            student_sort: TableSort::default(),
+            teacher_sort: TableSort::default(),
+            faculty_sort: TableSort::default(),
+            course_sort: TableSort::default(),
+            audit_sort: TableSort::default(),
+            trash_sort: TableSort::default(),
+            table_page_size: 0,
+            show_detail_pane: false,
+            show_help: false,
+            help_scroll: 0,
+            hit_map: HitMap::default(),
         }
     }
 }
 
 impl AppState {
-    pub fn select_next(&mut self) {
+    // Move the selection by `delta` rows, staying within the table's `row_count` rows
+    pub fn move_selection(&mut self, delta: isize, row_count: usize) {
         let state = self.get_current_table_state();
-        let next = match state.selected() {
-            Some(i) => Some(i + 1),
-            None => Some(0),
-        };
-        state.select(next);
+        if row_count == 0 {
+            state.select(None);
+            return;
+        }
+        let current = state.selected().unwrap_or(0).min(row_count - 1);
+        state.select(Some(current.saturating_add_signed(delta).min(row_count - 1)));
     }
 
-    pub fn select_previous(&mut self) {
-        let state = self.get_current_table_state();
-        let prev = match state.selected() {
-            Some(i) => {
-                if i == 0 {
-                    Some(0)
-                } else {
-                    Some(i - 1)
-                }
-            }
-            None => Some(0),
-        };
-        state.select(prev);
+    pub fn select_next(&mut self, row_count: usize) {
+        self.move_selection(1, row_count);
     }
 
-    pub fn unselect(&mut self) {
-        self.get_current_table_state().select(None);
+    pub fn select_previous(&mut self, row_count: usize) {
+        self.move_selection(-1, row_count);
     }
 
-    pub fn select_first(&mut self) {
-        self.get_current_table_state().select(Some(0));
+    // PageUp/PageDown move by the number of rows that fit in the table
+    pub fn page_down(&mut self, row_count: usize) {
+        self.move_selection(self.table_page_size.max(1) as isize, row_count);
+    }
+
+    pub fn page_up(&mut self, row_count: usize) {
+        self.move_selection(-(self.table_page_size.max(1) as isize), row_count);
+    }
+
+    pub fn select_first(&mut self, row_count: usize) {
+        let selection = if row_count == 0 { None } else { Some(0) };
+        self.get_current_table_state().select(selection);
+    }
+
+    pub fn select_last(&mut self, row_count: usize) {
+        self.get_current_table_state().select(row_count.checked_sub(1));
     }
 
     pub fn get_current_table_state(&mut self) -> &mut TableState {
@@ -122,12 +322,26 @@ impl AppState {
             ActiveTab::Students => &mut self.student_list_state,
             ActiveTab::Teachers => &mut self.teacher_list_state,
             ActiveTab::Faculties => &mut self.faculty_list_state,
+            ActiveTab::Courses => &mut self.course_list_state,
+            ActiveTab::Audit => &mut self.audit_list_state,
+            ActiveTab::Trash => &mut self.trash_list_state,
+        }
+    }
+
+    pub fn get_current_sort(&mut self) -> &mut TableSort {
+        match self.active_tab {
+            ActiveTab::Students => &mut self.student_sort,
+            ActiveTab::Teachers => &mut self.teacher_sort,
+            ActiveTab::Faculties => &mut self.faculty_sort,
+            ActiveTab::Courses => &mut self.course_sort,
+            ActiveTab::Audit => &mut self.audit_sort,
+            ActiveTab::Trash => &mut self.trash_sort,
         }
     }
 
     pub fn show_notification(&mut self, message: String) {
         self.notification = Some(message);
-        self.notification_timer = 30; // Show notification for 3 seconds at 10 ticks/second
+        self.notification_timer = self.notification_ticks;
     }
 
     pub fn update_notification_timer(&mut self) {
@@ -140,16 +354,6 @@ impl AppState {
     }
 }
 
-// Define the types of UI elements that can be clicked
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum UiElement {
-    Tab(ActiveTab),
-    TableRow(usize),
-    ActionButton(ActionButton),
-    ModalButton(ModalButton),
-    None,
-}
-
 // Types of action buttons in the UI
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ActionButton {
@@ -167,129 +371,163 @@ pub enum ModalButton {
     Cancel,
 }
 
-// Determine which UI element is at a specific position
-pub fn get_element_at_position(
-    position: (u16, u16),
-    active_tab: ActiveTab,
-    data_manager: &crate::data_manager::DataManager,
-    app_state: &mut AppState,
-) -> UiElement {
-    let (x, y) = position;
-    
-    // Get terminal size to calculate proportional positions
-    let terminal_size = crossterm::terminal::size().unwrap_or((80, 24));
-    let terminal_width = terminal_size.0;
-    
-    // Tab handling - considering the header area (0-2) including borders
-    if y < 3 {
-        // The tabs are inside a bordered block
-        // We need to account for the left border (1 column) and make sure we're inside the block
-        if x > 0 && x < terminal_width - 1 {
-            // Add left border offset
-            let mut offset = 1;
-            
-            // Calculate the boundaries for each tab based on the actual title lengths
-            // with some padding for visual separation and highlight area
-            for (i, title) in TAB_TITLES.iter().enumerate() {
-                // Calculate width: title length + some padding (4 chars)
-                let tab_width = title.len() as u16 + 4;
-                
-                // If this position is within this tab's bounds
-                if x < offset + tab_width {
-                    return match i {
-                        0 => UiElement::Tab(ActiveTab::Students),
-                        1 => UiElement::Tab(ActiveTab::Teachers),
-                        2 => UiElement::Tab(ActiveTab::Faculties),
-                        _ => UiElement::None, // shouldn't happen
-                    };
-                }
-                
-                // Move offset to the end of this tab
-                offset += tab_width;
-            }
-            
-            // If we get here and we're still within the tab bar, it's the last tab
-            return UiElement::Tab(ActiveTab::Faculties);
-        }
-    }
-    
-    let terminal_height = terminal_size.1;
-    
-    // Action buttons - near bottom of screen
-    let action_bar_row = terminal_height - 4; // One row for footer, plus action bar height
-    
-    // Check if clicking on action buttons row
-    if y >= action_bar_row && y <= action_bar_row + 2 {
-        // Match the actual rendering constraints in the render_action_bar function
-        let total_width = terminal_width - 2; // Account for borders
-        
-        // Calculate button boundaries based on percentages from render_action_bar
-        let add_width = total_width * 15 / 100;
-        let edit_width = total_width * 15 / 100;
-        let delete_width = total_width * 15 / 100;
-        let search_width = total_width * 25 / 100;
-        
-        // Calculate the cumulative positions
-        let add_end = 1 + add_width; // +1 for left border
-        let edit_end = add_end + edit_width;
-        let delete_end = edit_end + delete_width;
-        let search_end = delete_end + search_width;
-        
-        // Check which button was clicked based on the adjusted positions
-        if x < add_end {
-            return UiElement::ActionButton(ActionButton::Add);
-        } else if x < edit_end {
-            return UiElement::ActionButton(ActionButton::Edit);
-        } else if x < delete_end {
-            return UiElement::ActionButton(ActionButton::Delete);
-        } else if x < search_end {
-            return UiElement::ActionButton(ActionButton::Search);
-        } else {
-            return UiElement::ActionButton(ActionButton::Refresh);
-        }
+// Cells of a table header, laid out the way ratatui lays out table columns
+fn header_cells(area: Rect, widths: &[Constraint], has_selection: bool) -> Rc<[Rect]> {
+    let selection_width = if has_selection { HIGHLIGHT_SYMBOL.len() as u16 } else { 0 };
+    let [_, columns_area] = Layout::horizontal([Constraint::Length(selection_width), Constraint::Fill(0)]).areas(area);
+    Layout::horizontal(widths.iter().copied())
+        .flex(Flex::Start)
+        .spacing(1)
+        .split(columns_area)
+}
+
+// Records matching the search query, in each table's sort order. Rendering and
+// everything that maps a row back to its record go through here.
+pub fn visible_records<'a>(data_manager: &'a DataManager, app_state: &AppState) -> SearchResults<'a> {
+    let mut results = data_manager.search(app_state.search_query.value());
+    sort_records(&mut results.students, app_state.student_sort, |a, b, column| match column {
+        0 => compare_text(&a.full_name(), &b.full_name()),
+        1 => a.age.cmp(&b.age),
+        2 => compare_text(&a.major, &b.major),
+        _ => compare_missing_last(data_manager.student_gpa(&a.id), data_manager.student_gpa(&b.id), f32::total_cmp),
+    });
+    let faculty_name = |id: &str| data_manager.get_faculty_by_id(id).map(|f| f.name.clone()).unwrap_or_default();
+    sort_records(&mut results.teachers, app_state.teacher_sort, |a, b, column| match column {
+        0 => compare_text(&a.full_name(), &b.full_name()),
+        1 => a.age.cmp(&b.age),
+        2 => compare_text(&faculty_name(&a.faculty_id), &faculty_name(&b.faculty_id)),
+        _ => compare_text(&a.title, &b.title),
+    });
+    sort_records(&mut results.faculties, app_state.faculty_sort, |a, b, column| match column {
+        0 => compare_text(&a.name, &b.name),
+        1 => compare_text(&a.building, &b.building),
+        2 => compare_text(&a.head_name, &b.head_name),
+        3 => a.established_year.cmp(&b.established_year),
+        _ => a.num_staff.cmp(&b.num_staff),
+    });
+    sort_records(&mut results.courses, app_state.course_sort, |a, b, column| match column {
+        0 => compare_text(&a.code, &b.code),
+        1 => compare_text(&a.title, &b.title),
+        2 => a.credits.cmp(&b.credits),
+        3 => compare_text(&faculty_name(&a.faculty_id), &faculty_name(&b.faculty_id)),
+        _ => compare_text(&course_teacher_names(data_manager, a), &course_teacher_names(data_manager, b)),
+    });
+    sort_records(&mut results.audit, app_state.audit_sort, |a, b, column| match column {
+        0 => a.timestamp.cmp(&b.timestamp),
+        1 => compare_text(&a.user, &b.user),
+        2 => a.action.label().cmp(b.action.label()),
+        3 => a.entity.cmp(&b.entity),
+        4 => compare_text(&a.operation, &b.operation),
+        _ => compare_text(&a.summary(), &b.summary()),
+    });
+    sort_records(&mut results.trash, app_state.trash_sort, |a, b, column| match column {
+        0 => a.deleted_at.cmp(&b.deleted_at),
+        1 => a.kind.to_string().cmp(&b.kind.to_string()),
+        2 => compare_text(&a.name, &b.name),
+        _ => compare_text(&a.dependents_summary(), &b.dependents_summary()),
+    });
+    results
+}
+
+// Stable, so records that compare equal keep their insertion order
+fn sort_records<T>(records: &mut [&T], sort: TableSort, compare: impl Fn(&T, &T, usize) -> Ordering) {
+    if let Some(column) = sort.column {
+        records.sort_by(|a, b| sort.apply(compare(a, b, column)));
     }
-    
-    // Table rows handling
-    let data_start_row = 9;  
-    
-    // Table ends right above action buttons
-    let table_end_row = action_bar_row;
-    
-    // Check if clicking in the table area
-    if y >= data_start_row && y < table_end_row {
-        // Calculate row index by subtracting starting position
-        let row_index = (y - data_start_row) as usize;
-        
-        // Verify the row index is valid for the current tab
-        match active_tab {
-            ActiveTab::Students => {
-                if row_index < data_manager.get_all_students().len() {
-                    return UiElement::TableRow(row_index);
-                }
-            },
-            ActiveTab::Teachers => {
-                if row_index < data_manager.get_all_teachers().len() {
-                    return UiElement::TableRow(row_index);
-                }
-            },
-            ActiveTab::Faculties => {
-                if row_index < data_manager.get_all_faculties().len() {
-                    return UiElement::TableRow(row_index);
-                }
-            },
-        }
+}
+
+fn compare_text(a: &str, b: &str) -> Ordering {
+    a.to_lowercase().cmp(&b.to_lowercase())
+}
+
+// Empty values (e.g. no GPA yet) go after all others
+fn compare_missing_last<T>(a: Option<T>, b: Option<T>, compare: impl Fn(&T, &T) -> Ordering) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => compare(&a, &b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
     }
-    
-    UiElement::None
 }
 
-impl AppState {
-    // ...existing code...
+fn course_teacher_names(data_manager: &DataManager, course: &Course) -> String {
+    course
+        .teacher_ids
+        .iter()
+        .filter_map(|id| data_manager.get_teacher_by_id(id))
+        .map(|t| t.full_name())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Header row of a table, marking the sort column with its direction
+fn header_row(columns: &[&str], sort: TableSort) -> Row<'static> {
+    let cells = columns.iter().enumerate().map(|(i, title)| {
+        let title = match sort.column {
+            Some(column) if column == i => format!("{} {}", title, if sort.descending { "▼" } else { "▲" }),
+            _ => title.to_string(),
+        };
+        Span::styled(title, Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
+    });
+    Row::new(cells)
+        .height(1)
+        .bottom_margin(1)
+        .style(Style::default().bg(Color::Black))
 }
 
+// Scrollbar next to a table, placed by the table's real scroll offset. Also records how
+// many rows fit in the table, for paging, and where its header cells and rows are.
+fn render_table_scrollbar(f: &mut Frame, area: Rect, table_area: Rect, app_state: &mut AppState, total_items: usize) {
+    let visible_items = table_area.height.saturating_sub(4) as usize; // Subtract borders and header
+    app_state.table_page_size = visible_items;
+
+    // Inside the border the header takes one row plus a margin row, then come the records
+    let inner = table_area.inner(Margin::new(1, 1));
+    let header = Rect { height: 1.min(inner.height), ..inner };
+    let widths = app_state.active_tab.column_widths();
+    for (column, cell) in header_cells(header, widths, total_items > 0).iter().enumerate() {
+        app_state.hit_map.add(*cell, Hit::TableHeader(column));
+    }
+    let offset = app_state.get_current_table_state().offset();
+    for (index, row) in (offset..total_items).zip(inner.rows().skip(2)) {
+        app_state.hit_map.add(row, Hit::TableRow(index));
+    }
+
+    if total_items == 0 {
+        return;
+    }
+
+    let scrollbar = Scrollbar::default()
+        .orientation(ScrollbarOrientation::VerticalRight)
+        .symbols(symbols::scrollbar::VERTICAL)
+        .begin_symbol(None)
+        .end_symbol(None)
+        .track_symbol(Some("│"))
+        .thumb_symbol("█");
+
+    // The scrollbar tracks the first visible row, which can range over every offset that
+    // still fills the viewport
+    let offset = app_state.get_current_table_state().offset();
+    let offsets = total_items.saturating_sub(visible_items) + 1;
+    f.render_stateful_widget(
+        scrollbar,
+        area,
+        &mut ScrollbarState::new(offsets)
+            .position(offset)
+            .viewport_content_length(visible_items),
+    );
+}
 
 // UI rendering functions
-pub fn render(f: &mut Frame, app_state: &mut AppState, students: &[Student], teachers: &[Teacher], faculties: &[Faculty]) {
+// `searching` is true while the search bar has the keyboard
+pub fn render(
+    f: &mut Frame,
+    app_state: &mut AppState,
+    data_manager: &DataManager,
+    keymap: &Keymap,
+    results: &SearchResults,
+    searching: bool,
+) {
     // Set a dark background for the entire screen
     let background = Block::default()
         .style(Style::default().bg(Color::Rgb(16, 16, 28))); // Dark blue/purple background
@@ -309,10 +547,10 @@ pub fn render(f: &mut Frame, app_state: &mut AppState, students: &[Student], tea
     render_header(f, chunks[0], app_state);
 
     // Render the main content area (tab content)
-    render_main_content(f, chunks[1], app_state, students, teachers, faculties);
+    render_main_content(f, chunks[1], app_state, data_manager, keymap, results, searching);
 
     // Render the footer with shortcuts
-    render_footer(f, chunks[2]);
+    render_footer(f, chunks[2], keymap, data_manager.is_read_only(), data_manager.undo_description());
 
     // Render notification if present
     if let Some(notification) = &app_state.notification {
@@ -320,7 +558,7 @@ pub fn render(f: &mut Frame, app_state: &mut AppState, students: &[Student], tea
     }
 }
 
-fn render_header(f: &mut Frame, area: Rect, app_state: &AppState) {
+fn render_header(f: &mut Frame, area: Rect, app_state: &mut AppState) {
     let titles: Vec<_> = TAB_TITLES
         .iter()
         .enumerate()
@@ -356,15 +594,24 @@ fn render_header(f: &mut Frame, area: Rect, app_state: &AppState) {
         .style(Style::default().fg(Color::White));
 
     f.render_widget(tabs, area);
+
+    // Each tab is a space, its title and a space, followed by a one column divider
+    let mut x = area.x + 1;
+    for (title, tab) in TAB_TITLES.iter().zip(ActiveTab::ALL) {
+        let width = (Line::from(*title).width() as u16 + 3).min(area.right().saturating_sub(x));
+        app_state.hit_map.add(Rect::new(x, area.y, width, area.height), Hit::Tab(tab));
+        x += width;
+    }
 }
 
 fn render_main_content(
     f: &mut Frame,
     area: Rect,
     app_state: &mut AppState,
-    students: &[Student],
-    teachers: &[Teacher],
-    faculties: &[Faculty],
+    data_manager: &DataManager,
+    keymap: &Keymap,
+    results: &SearchResults,
+    searching: bool,
 ) {
     // Split the main area into search bar and content
     let chunks = Layout::default()
@@ -377,21 +624,201 @@ fn render_main_content(
         .split(area);
 
     // Render search bar
-    render_search_bar(f, chunks[0], app_state);
+    render_search_bar(f, chunks[0], app_state, searching);
 
     // Render content based on active tab
+    let (table_area, pane_area) = table_and_pane_areas(chunks[1], app_state.show_detail_pane);
     match app_state.active_tab {
-        ActiveTab::Students => render_students_table(f, chunks[1], app_state, students),
-        ActiveTab::Teachers => render_teachers_table(f, chunks[1], app_state, teachers),
-        ActiveTab::Faculties => render_faculties_table(f, chunks[1], app_state, faculties),
+        ActiveTab::Students => render_students_table(f, table_area, app_state, data_manager, &results.students),
+        ActiveTab::Teachers => render_teachers_table(f, table_area, app_state, data_manager, &results.teachers),
+        ActiveTab::Faculties => render_faculties_table(f, table_area, app_state, &results.faculties),
+        ActiveTab::Courses => render_courses_table(f, table_area, app_state, data_manager, &results.courses),
+        ActiveTab::Audit => render_audit_table(f, table_area, app_state, &results.audit),
+        ActiveTab::Trash => render_trash_table(f, table_area, app_state, keymap, &results.trash),
+    }
+    if let Some(pane_area) = pane_area {
+        render_detail_pane(f, pane_area, app_state, data_manager, results);
     }
 
     // Render action bar
-    render_action_bar(f, chunks[2]);
+    render_action_bar(f, chunks[2], keymap, &mut app_state.hit_map);
+}
+
+// Split the content area between the table and, when shown, the detail pane on its right
+pub fn table_and_pane_areas(area: Rect, show_pane: bool) -> (Rect, Option<Rect>) {
+    if !show_pane {
+        return (area, None);
+    }
+    let [table, pane] = Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(area);
+    (table, Some(pane))
 }
 
-fn render_search_bar(f: &mut Frame, area: Rect, app_state: &AppState) {
-    let search_text = Paragraph::new(format!("Search: {}", app_state.search_query))
+// Every field of the selected record, followed by the records related to it
+fn render_detail_pane(
+    f: &mut Frame,
+    area: Rect,
+    app_state: &mut AppState,
+    data_manager: &DataManager,
+    results: &SearchResults,
+) {
+    let label_style = Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD);
+    let field = |label: &str, value: String| {
+        Line::from(vec![Span::styled(format!("{}: ", label), label_style), Span::raw(value)])
+    };
+    let section = |title: &str, items: Vec<String>| {
+        let mut lines = vec![
+            Line::raw(""),
+            Line::styled(format!("{} ({})", title, items.len()), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        ];
+        if items.is_empty() {
+            lines.push(Line::styled("  none", Style::default().fg(Color::DarkGray)));
+        }
+        lines.extend(items.into_iter().map(|item| Line::raw(format!("  • {}", item))));
+        lines
+    };
+    let faculty_name = |id: &str| data_manager.get_faculty_by_id(id).map(|f| f.name.clone()).unwrap_or_else(|| "-".to_string());
+    let course_label = |c: &Course| format!("{} {}", c.code, c.title);
+
+    let selected = app_state.get_current_table_state().selected();
+    let lines: Vec<Line> = match app_state.active_tab {
+        ActiveTab::Students => selected.and_then(|i| results.students.get(i)).map(|s| {
+            let enrollments = data_manager
+                .enrollments_for_student(&s.id)
+                .iter()
+                .map(|e| {
+                    let course = data_manager.get_course_by_id(&e.course_id).map(course_label).unwrap_or_default();
+                    let grade = e.grade.as_ref().map(|g| g.display_in(data_manager.rules().grading_scale));
+                    let grade = grade.unwrap_or_else(|| "-".to_string());
+                    format!("{} ({}): {}", course, e.term, grade)
+                })
+                .collect();
+            let gpa = data_manager.student_gpa(&s.id).map(|gpa| format!("{:.2}", gpa));
+            let mut lines = vec![
+                field("First name", s.first_name.clone()),
+                field("Last name", s.last_name.clone()),
+                field("Age", s.age.to_string()),
+                field("Major", s.major.clone()),
+                field("GPA", gpa.unwrap_or_else(|| "-".to_string())),
+                field("Id", s.id.clone()),
+            ];
+            lines.extend(section("Enrollments", enrollments));
+            lines
+        }),
+        ActiveTab::Teachers => selected.and_then(|i| results.teachers.get(i)).map(|t| {
+            let courses = data_manager.courses_taught_by(&t.id).into_iter().map(course_label).collect();
+            let mut lines = vec![
+                field("First name", t.first_name.clone()),
+                field("Last name", t.last_name.clone()),
+                field("Age", t.age.to_string()),
+                field("Title", t.title.clone()),
+                field("Faculty", faculty_name(&t.faculty_id)),
+                field("Id", t.id.clone()),
+            ];
+            lines.extend(section("Courses", courses));
+            lines
+        }),
+        ActiveTab::Faculties => selected.and_then(|i| results.faculties.get(i)).map(|fac| {
+            let teachers = data_manager.teachers_in_faculty(&fac.id).iter().map(|t| t.full_name()).collect();
+            let courses = data_manager.courses_in_faculty(&fac.id).into_iter().map(course_label).collect();
+            let students = data_manager.students_in_faculty(&fac.id).iter().map(|s| s.full_name()).collect();
+            let mut lines = vec![
+                field("Name", fac.name.clone()),
+                field("Building", fac.building.clone()),
+                field("Head", fac.head_name.clone()),
+                field("Established", fac.established_year.to_string()),
+                field("Staff", fac.num_staff.to_string()),
+                field("Id", fac.id.clone()),
+            ];
+            lines.extend(section("Teachers", teachers));
+            lines.extend(section("Courses", courses));
+            lines.extend(section("Students", students));
+            lines
+        }),
+        ActiveTab::Courses => selected.and_then(|i| results.courses.get(i)).map(|c| {
+            let teachers = c
+                .teacher_ids
+                .iter()
+                .filter_map(|id| data_manager.get_teacher_by_id(id))
+                .map(|t| t.full_name())
+                .collect();
+            let students = data_manager
+                .enrollments_for_course(&c.id)
+                .iter()
+                .filter_map(|e| {
+                    let student = data_manager.get_student_by_id(&e.student_id)?;
+                    Some(format!("{} ({})", student.full_name(), e.term))
+                })
+                .collect();
+            let mut lines = vec![
+                field("Code", c.code.clone()),
+                field("Title", c.title.clone()),
+                field("Credits", c.credits.to_string()),
+                field("Faculty", faculty_name(&c.faculty_id)),
+                field("Id", c.id.clone()),
+            ];
+            lines.extend(section("Teachers", teachers));
+            lines.extend(section("Students", students));
+            lines
+        }),
+        ActiveTab::Audit => selected.and_then(|i| results.audit.get(i)).map(|entry| {
+            let changes = entry.change_texts();
+            let mut lines = vec![
+                field("Time", entry.time()),
+                field("User", entry.user.clone()),
+                field("Action", entry.action.label().to_string()),
+                field("Entity", entry.entity.clone()),
+                field("Id", entry.id.clone()),
+                field("Operation", entry.operation.clone()),
+            ];
+            lines.extend(section("Changes", changes));
+            lines
+        }),
+        ActiveTab::Trash => selected.and_then(|i| results.trash.get(i)).map(|item| {
+            let records = &item.records;
+            let mut lines = vec![
+                field("Deleted", item.deleted_time()),
+                field("Kind", item.kind.to_string()),
+                field("Name", item.name.clone()),
+            ];
+            lines.extend(section("Students", records.students.iter().map(|s| s.full_name()).collect()));
+            lines.extend(section("Teachers", records.teachers.iter().map(|t| t.full_name()).collect()));
+            lines.extend(section("Faculties", records.faculties.iter().map(|f| f.name.clone()).collect()));
+            lines.extend(section("Courses", records.courses.iter().map(course_label).collect()));
+            let enrollments = records
+                .enrollments
+                .iter()
+                .map(|e| {
+                    let student = data_manager.get_student_by_id(&e.student_id).map(|s| s.full_name());
+                    let student = student.or_else(|| records.students.iter().find(|s| s.id == e.student_id).map(|s| s.full_name()));
+                    let course = data_manager.get_course_by_id(&e.course_id).map(|c| c.code.clone());
+                    let course = course.or_else(|| records.courses.iter().find(|c| c.id == e.course_id).map(|c| c.code.clone()));
+                    format!("{} in {} ({})", student.unwrap_or_default(), course.unwrap_or_default(), e.term)
+                })
+                .collect();
+            lines.extend(section("Enrollments", enrollments));
+            lines
+        }),
+    }
+    .unwrap_or_else(|| vec![Line::styled("No record selected", Style::default().fg(Color::DarkGray))]);
+
+    let pane = Paragraph::new(lines)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(Color::Gray))
+            .title("Details (p to hide)")
+            .title_style(Style::default().fg(Color::Gray).add_modifier(Modifier::BOLD)))
+        .wrap(Wrap { trim: false });
+    f.render_widget(pane, area);
+}
+
+fn render_search_bar(f: &mut Frame, area: Rect, app_state: &AppState, searching: bool) {
+    let label = Span::raw("Search: ");
+    // Room left for the query inside the borders
+    let room = area.width.saturating_sub(2 + label.width() as u16);
+    let mut spans = vec![label];
+    spans.extend(app_state.search_query.spans(Style::default(), searching, room));
+    let search_text = Paragraph::new(Line::from(spans))
         .block(Block::default()
             .borders(Borders::ALL)
             .border_type(BorderType::Rounded)
@@ -402,12 +829,17 @@ fn render_search_bar(f: &mut Frame, area: Rect, app_state: &AppState) {
     f.render_widget(search_text, area);
 }
 
-fn render_students_table(f: &mut Frame, area: Rect, app_state: &mut AppState, students: &[Student]) {
+fn render_students_table(
+    f: &mut Frame,
+    area: Rect,
+    app_state: &mut AppState,
+    data_manager: &DataManager,
+    students: &[&Student],
+) {
     let selected_style = Style::default()
         .bg(Color::Blue)
         .fg(Color::White)
         .add_modifier(Modifier::BOLD);
-    let normal_style = Style::default().bg(Color::Black);
     
     // Create a layout that leaves space for the scrollbar on the right
     let horizontal_layout = Layout::default()
@@ -421,34 +853,23 @@ fn render_students_table(f: &mut Frame, area: Rect, app_state: &mut AppState, st
     let table_area = horizontal_layout[0];
     let scrollbar_area = horizontal_layout[1];
     
-    let header_cells = ["Name", "Age", "Major", "GPA"]
-        .iter()
-        .map(|h| {
-            Span::styled(*h, Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
-        });
-    let header = Row::new(header_cells)
-        .height(1)
-        .bottom_margin(1)
-        .style(normal_style);
+    let header = header_row(STUDENT_COLUMNS, app_state.student_sort);
     
     let rows = students.iter().map(|s| {
         let cells = [
             s.full_name(),
             s.age.to_string(),
             s.major.clone(),
-            format!("{:.2}", s.gpa),
+            // GPA is computed from the student's graded enrollments
+            data_manager
+                .student_gpa(&s.id)
+                .map(|gpa| format!("{:.2}", gpa))
+                .unwrap_or_else(|| "-".to_string()),
         ];
         Row::new(cells).height(1).bottom_margin(0)
     });
     
-    let widths = [
-        Constraint::Percentage(40),
-        Constraint::Percentage(10),
-        Constraint::Percentage(35),
-        Constraint::Percentage(15),
-    ];
-    
-    let table = Table::new(rows, widths)
+    let table = Table::new(rows, STUDENT_WIDTHS.iter().copied())
         .header(header)
         .block(Block::default()
             .borders(Borders::ALL)
@@ -457,41 +878,24 @@ fn render_students_table(f: &mut Frame, area: Rect, app_state: &mut AppState, st
             .title("Students")
             .title_style(Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)))
         .row_highlight_style(selected_style)
-        .highlight_symbol(">> ");
+        .highlight_symbol(HIGHLIGHT_SYMBOL);
     
     f.render_stateful_widget(table, table_area, &mut app_state.student_list_state);
     
-    // Render scrollbar if we have items to scroll
-    if !students.is_empty() {
-        let scrollbar = Scrollbar::default()
-            .orientation(ScrollbarOrientation::VerticalRight)
-            .symbols(symbols::scrollbar::VERTICAL)
-            .begin_symbol(None)
-            .end_symbol(None)
-            .track_symbol(Some("│"))
-            .thumb_symbol("█");
-
-        // Calculate scrollbar values based on the current selection
-        let selected_index = app_state.student_list_state.selected().unwrap_or(0);
-        let total_items = students.len();
-        let max_visible_items = table_area.height.saturating_sub(4) as usize; // Subtract borders and header
-
-        f.render_stateful_widget(
-            scrollbar,
-            scrollbar_area,
-            &mut ScrollbarState::new(total_items)
-                .position(selected_index)
-                .viewport_content_length(max_visible_items),
-        );
-    }
+    render_table_scrollbar(f, scrollbar_area, table_area, app_state, students.len());
 }
 
-fn render_teachers_table(f: &mut Frame, area: Rect, app_state: &mut AppState, teachers: &[Teacher]) {
+fn render_teachers_table(
+    f: &mut Frame,
+    area: Rect,
+    app_state: &mut AppState,
+    data_manager: &DataManager,
+    teachers: &[&Teacher],
+) {
     let selected_style = Style::default()
         .bg(Color::Blue)
         .fg(Color::White)
         .add_modifier(Modifier::BOLD);
-    let normal_style = Style::default().bg(Color::Black);
     
     // Create a layout that leaves space for the scrollbar on the right
     let horizontal_layout = Layout::default()
@@ -505,34 +909,23 @@ fn render_teachers_table(f: &mut Frame, area: Rect, app_state: &mut AppState, te
     let table_area = horizontal_layout[0];
     let scrollbar_area = horizontal_layout[1];
     
-    let header_cells = ["Name", "Age", "Department", "Title"]
-        .iter()
-        .map(|h| {
-            Span::styled(*h, Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
-        });
-    let header = Row::new(header_cells)
-        .height(1)
-        .bottom_margin(1)
-        .style(normal_style);
+    let header = header_row(TEACHER_COLUMNS, app_state.teacher_sort);
     
     let rows = teachers.iter().map(|t| {
         let cells = [
             t.full_name(),
             t.age.to_string(),
-            t.department.clone(),
+            // Resolve the faculty so renames show up immediately
+            data_manager
+                .get_faculty_by_id(&t.faculty_id)
+                .map(|f| f.name.clone())
+                .unwrap_or_default(),
             t.title.clone(),
         ];
         Row::new(cells).height(1).bottom_margin(0)
     });
     
-    let widths = [
-        Constraint::Percentage(30),
-        Constraint::Percentage(10),
-        Constraint::Percentage(40),
-        Constraint::Percentage(20),
-    ];
-    
-    let table = Table::new(rows, widths)
+    let table = Table::new(rows, TEACHER_WIDTHS.iter().copied())
         .header(header)
         .block(Block::default()
             .borders(Borders::ALL)
@@ -541,41 +934,18 @@ fn render_teachers_table(f: &mut Frame, area: Rect, app_state: &mut AppState, te
             .title("Teachers")
             .title_style(Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD)))
         .row_highlight_style(selected_style)
-        .highlight_symbol(">> ");
+        .highlight_symbol(HIGHLIGHT_SYMBOL);
     
     f.render_stateful_widget(table, table_area, &mut app_state.teacher_list_state);
     
-    // Render scrollbar if we have items to scroll
-    if !teachers.is_empty() {
-        let scrollbar = Scrollbar::default()
-            .orientation(ScrollbarOrientation::VerticalRight)
-            .symbols(symbols::scrollbar::VERTICAL)
-            .begin_symbol(None)
-            .end_symbol(None)
-            .track_symbol(Some("│"))
-            .thumb_symbol("█");
-
-        // Calculate scrollbar values based on the current selection
-        let selected_index = app_state.teacher_list_state.selected().unwrap_or(0);
-        let total_items = teachers.len();
-        let max_visible_items = table_area.height.saturating_sub(4) as usize; // Subtract borders and header
-
-        f.render_stateful_widget(
-            scrollbar,
-            scrollbar_area,
-            &mut ScrollbarState::new(total_items)
-                .position(selected_index)
-                .viewport_content_length(max_visible_items),
-        );
-    }
+    render_table_scrollbar(f, scrollbar_area, table_area, app_state, teachers.len());
 }
 
-fn render_faculties_table(f: &mut Frame, area: Rect, app_state: &mut AppState, faculties: &[Faculty]) {
+fn render_faculties_table(f: &mut Frame, area: Rect, app_state: &mut AppState, faculties: &[&Faculty]) {
     let selected_style = Style::default()
         .bg(Color::Blue)
         .fg(Color::White)
         .add_modifier(Modifier::BOLD);
-    let normal_style = Style::default().bg(Color::Black);
     
     // Create a layout that leaves space for the scrollbar on the right
     let horizontal_layout = Layout::default()
@@ -589,15 +959,7 @@ fn render_faculties_table(f: &mut Frame, area: Rect, app_state: &mut AppState, f
     let table_area = horizontal_layout[0];
     let scrollbar_area = horizontal_layout[1];
     
-    let header_cells = ["Name", "Building", "Head", "Est. Year", "Staff"]
-        .iter()
-        .map(|h| {
-            Span::styled(*h, Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
-        });
-    let header = Row::new(header_cells)
-        .height(1)
-        .bottom_margin(1)
-        .style(normal_style);
+    let header = header_row(FACULTY_COLUMNS, app_state.faculty_sort);
     
     let rows = faculties.iter().map(|f| {
         let cells = [
@@ -610,15 +972,7 @@ fn render_faculties_table(f: &mut Frame, area: Rect, app_state: &mut AppState, f
         Row::new(cells).height(1).bottom_margin(0)
     });
     
-    let widths = [
-        Constraint::Percentage(25),
-        Constraint::Percentage(20),
-        Constraint::Percentage(25),
-        Constraint::Percentage(15),
-        Constraint::Percentage(15),
-    ];
-    
-    let table = Table::new(rows, widths)
+    let table = Table::new(rows, FACULTY_WIDTHS.iter().copied())
         .header(header)
         .block(Block::default()
             .borders(Borders::ALL)
@@ -627,36 +981,167 @@ fn render_faculties_table(f: &mut Frame, area: Rect, app_state: &mut AppState, f
             .title("Faculties")
             .title_style(Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)))
         .row_highlight_style(selected_style)
-        .highlight_symbol(">> ");
+        .highlight_symbol(HIGHLIGHT_SYMBOL);
     
     f.render_stateful_widget(table, table_area, &mut app_state.faculty_list_state);
     
-    // Render scrollbar if we have items to scroll
-    if !faculties.is_empty() {
-        let scrollbar = Scrollbar::default()
-            .orientation(ScrollbarOrientation::VerticalRight)
-            .symbols(symbols::scrollbar::VERTICAL)
-            .begin_symbol(None)
-            .end_symbol(None)
-            .track_symbol(Some("│"))
-            .thumb_symbol("█");
+    render_table_scrollbar(f, scrollbar_area, table_area, app_state, faculties.len());
+}
+
+fn render_courses_table(
+    f: &mut Frame,
+    area: Rect,
+    app_state: &mut AppState,
+    data_manager: &DataManager,
+    courses: &[&Course],
+) {
+    let selected_style = Style::default()
+        .bg(Color::Blue)
+        .fg(Color::White)
+        .add_modifier(Modifier::BOLD);
+    
+    // Create a layout that leaves space for the scrollbar on the right
+    let horizontal_layout = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Min(10),     // Table area
+            Constraint::Length(1),   // Scrollbar area
+        ])
+        .split(area);
+    
+    let table_area = horizontal_layout[0];
+    let scrollbar_area = horizontal_layout[1];
+    
+    let header = header_row(COURSE_COLUMNS, app_state.course_sort);
+    
+    let rows = courses.iter().map(|c| {
+        // Resolve referenced records to names for display
+        let faculty = data_manager
+            .get_faculty_by_id(&c.faculty_id)
+            .map(|f| f.name.clone())
+            .unwrap_or_default();
+        let teachers = course_teacher_names(data_manager, c);
+        let cells = [
+            c.code.clone(),
+            c.title.clone(),
+            c.credits.to_string(),
+            faculty,
+            teachers,
+        ];
+        Row::new(cells).height(1).bottom_margin(0)
+    });
+    
+    let table = Table::new(rows, COURSE_WIDTHS.iter().copied())
+        .header(header)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(Color::LightYellow))
+            .title("Courses")
+            .title_style(Style::default().fg(Color::LightYellow).add_modifier(Modifier::BOLD)))
+        .row_highlight_style(selected_style)
+        .highlight_symbol(HIGHLIGHT_SYMBOL);
+    
+    f.render_stateful_widget(table, table_area, &mut app_state.course_list_state);
+    
+    render_table_scrollbar(f, scrollbar_area, table_area, app_state, courses.len());
+}
 
-        // Calculate scrollbar values based on the current selection
-        let selected_index = app_state.faculty_list_state.selected().unwrap_or(0);
-        let total_items = faculties.len();
-        let max_visible_items = table_area.height.saturating_sub(4) as usize; // Subtract borders and header
+fn render_audit_table(f: &mut Frame, area: Rect, app_state: &mut AppState, entries: &[&AuditEntry]) {
+    let selected_style = Style::default()
+        .bg(Color::Blue)
+        .fg(Color::White)
+        .add_modifier(Modifier::BOLD);
 
-        f.render_stateful_widget(
-            scrollbar,
-            scrollbar_area,
-            &mut ScrollbarState::new(total_items)
-                .position(selected_index)
-                .viewport_content_length(max_visible_items),
-        );
-    }
+    // Create a layout that leaves space for the scrollbar on the right
+    let horizontal_layout = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Min(10),     // Table area
+            Constraint::Length(1),   // Scrollbar area
+        ])
+        .split(area);
+
+    let table_area = horizontal_layout[0];
+    let scrollbar_area = horizontal_layout[1];
+
+    let header = header_row(AUDIT_COLUMNS, app_state.audit_sort);
+
+    let rows = entries.iter().map(|e| {
+        let cells = [
+            e.time(),
+            e.user.clone(),
+            e.action.label().to_string(),
+            e.entity.clone(),
+            e.operation.clone(),
+            e.summary(),
+        ];
+        Row::new(cells).height(1).bottom_margin(0)
+    });
+
+    let table = Table::new(rows, AUDIT_WIDTHS.iter().copied())
+        .header(header)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(Color::Gray))
+            .title("Audit Log (entity:<kind> date:<yyyy-mm-dd> to filter)")
+            .title_style(Style::default().fg(Color::Gray).add_modifier(Modifier::BOLD)))
+        .row_highlight_style(selected_style)
+        .highlight_symbol(HIGHLIGHT_SYMBOL);
+
+    f.render_stateful_widget(table, table_area, &mut app_state.audit_list_state);
+
+    render_table_scrollbar(f, scrollbar_area, table_area, app_state, entries.len());
+}
+
+fn render_trash_table(f: &mut Frame, area: Rect, app_state: &mut AppState, keymap: &Keymap, items: &[&TrashItem]) {
+    let selected_style = Style::default()
+        .bg(Color::Blue)
+        .fg(Color::White)
+        .add_modifier(Modifier::BOLD);
+
+    // Create a layout that leaves space for the scrollbar on the right
+    let horizontal_layout = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Min(10),     // Table area
+            Constraint::Length(1),   // Scrollbar area
+        ])
+        .split(area);
+
+    let table_area = horizontal_layout[0];
+    let scrollbar_area = horizontal_layout[1];
+
+    let header = header_row(TRASH_COLUMNS, app_state.trash_sort);
+
+    let rows = items.iter().map(|t| {
+        let cells = [t.deleted_time(), t.kind.to_string(), t.name.clone(), t.dependents_summary()];
+        Row::new(cells).height(1).bottom_margin(0)
+    });
+
+    let table = Table::new(rows, TRASH_WIDTHS.iter().copied())
+        .header(header)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(Color::Red))
+            .title(format!(
+                "Trash ({} to restore, {} to purge)",
+                keymap.key_label(KeyContext::Normal, Action::Restore),
+                keymap.key_label(KeyContext::Normal, Action::Delete)
+            ))
+            .title_style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)))
+        .row_highlight_style(selected_style)
+        .highlight_symbol(HIGHLIGHT_SYMBOL);
+
+    f.render_stateful_widget(table, table_area, &mut app_state.trash_list_state);
+
+    render_table_scrollbar(f, scrollbar_area, table_area, app_state, items.len());
 }
 
-fn render_action_bar(f: &mut Frame, area: Rect) {
+// Button labels show the keys currently bound to each action
+fn render_action_bar(f: &mut Frame, area: Rect, keymap: &Keymap, hit_map: &mut HitMap) {
     // Create a background for the action bar
     let block = Block::default()
         .title(" Actions ")
@@ -678,17 +1163,23 @@ fn render_action_bar(f: &mut Frame, area: Rect) {
             Constraint::Percentage(15), // Edit button
             Constraint::Percentage(15), // Delete button
             Constraint::Percentage(25), // Focus Search button
-            Constraint::Percentage(15), // Refresh button
+            Constraint::Percentage(15), // Reload button
             Constraint::Percentage(15), // Extra space
         ])
         .split(inner_area);
     
     // Render colored buttons similar to the delete modal buttons
-    render_button(f, button_layout[0], "A: Add", Color::Green);
-    render_button(f, button_layout[1], "E: Edit", Color::Blue);
-    render_button(f, button_layout[2], "D: Delete", Color::Red);
-    render_button(f, button_layout[3], "F: Focus Search", Color::Yellow);
-    render_button(f, button_layout[4], "R: Refresh", Color::Cyan);
+    let label = |action, text| format!("{}: {}", keymap.key_label(KeyContext::Normal, action), text);
+    render_button(f, button_layout[0], &label(Action::Add, "Add"), Color::Green);
+    render_button(f, button_layout[1], &label(Action::Edit, "Edit"), Color::Blue);
+    render_button(f, button_layout[2], &label(Action::Delete, "Delete"), Color::Red);
+    render_button(f, button_layout[3], &label(Action::Search, "Focus Search"), Color::Yellow);
+    render_button(f, button_layout[4], &label(Action::Refresh, "Reload"), Color::Cyan);
+
+    let buttons = [ActionButton::Add, ActionButton::Edit, ActionButton::Delete, ActionButton::Search, ActionButton::Refresh];
+    for (button, area) in buttons.into_iter().zip(button_layout.iter()) {
+        hit_map.add(*area, Hit::ActionButton(button));
+    }
 }
 
 // Helper function to render a button
@@ -703,23 +1194,74 @@ fn render_button(f: &mut Frame, area: Rect, text: &str, color: Color) {
     f.render_widget(button, area);
 }
 
-fn render_footer(f: &mut Frame, area: Rect) {
-    let text = Line::from(vec![
-        Span::styled("Q", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+// `undo` describes the change the undo key would revert
+fn render_footer(f: &mut Frame, area: Rect, keymap: &Keymap, read_only: bool, undo: Option<&str>) {
+    let key = |action| {
+        Span::styled(keymap.key_label(KeyContext::Normal, action), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+    };
+    let mut text = Line::from(vec![
+        key(Action::Quit),
         Span::raw(": Quit   "),
-        Span::styled("Tab/1/2/3", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        key(Action::NextTab),
         Span::raw(": Switch tabs   "),
-        Span::styled("↑/↓", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        key(Action::Up),
+        Span::styled("/", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        key(Action::Down),
         Span::raw(": Navigate   "),
-        Span::styled("H", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        key(Action::Help),
         Span::raw(": Help"),
     ]);
+    if read_only {
+        text.push_span(Span::raw("   "));
+        text.push_span(Span::styled(" READ-ONLY ", Style::default().fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD)));
+    }
+    if let Some(undo) = undo {
+        text.push_span(Span::raw("   "));
+        text.push_span(key(Action::Undo));
+        text.push_span(Span::raw(format!(": Undo {}", undo)));
+    }
     
     let paragraph = Paragraph::new(text).style(Style::default().fg(Color::White));
     f.render_widget(paragraph, area);
 }
 
-fn render_notification(f: &mut Frame, notification: &str) {
+// Key bindings for `context`, straight from the table that dispatches them
+pub fn render_help(f: &mut Frame, keymap: &Keymap, context: KeyContext, scroll: &mut u16) {
+    let area = centered_rect(60, 70, f.area());
+    let entries = keymap.help_entries(context);
+    let key_width = entries.iter().map(|(keys, _)| keys.chars().count()).max().unwrap_or(0);
+    let lines: Vec<Line> = entries
+        .into_iter()
+        .map(|(keys, description)| {
+            Line::from(vec![
+                Span::styled(
+                    format!("{:width$}  ", keys, width = key_width),
+                    Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(description),
+            ])
+        })
+        .collect();
+
+    // Keep the last line at the bottom at most, however far the user scrolled
+    let max_scroll = (lines.len() as u16).saturating_sub(area.height.saturating_sub(2));
+    *scroll = (*scroll).min(max_scroll);
+
+    let help = Paragraph::new(lines)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(Color::Yellow))
+            .title(format!("Help: {} ", context.title()))
+            .title_bottom(" ↑/↓ scroll, Esc close ")
+            .title_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)))
+        .style(Style::default().bg(Color::Rgb(16, 16, 28)).fg(Color::White))
+        .scroll((*scroll, 0));
+    f.render_widget(Clear, area);
+    f.render_widget(help, area);
+}
+
+pub fn render_notification(f: &mut Frame, notification: &str) {
     let area = centered_rect(60, 4, f.area());
     
     let block = Block::default()
@@ -743,6 +1285,107 @@ fn render_notification(f: &mut Frame, notification: &str) {
     f.render_widget(paragraph, inner);
 }
 
+// Startup screen shown when data files could not be read, instead of the tables
+pub fn render_recovery(f: &mut Frame, errors: &[LoadError], keymap: &Keymap, message: Option<&str>) {
+    let background = Block::default()
+        .style(Style::default().bg(Color::Rgb(16, 16, 28)));
+    f.render_widget(background, f.area());
+
+    let block = Block::default()
+        .title(" Data Recovery ")
+        .title_style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Red));
+    let area = f.area().inner(Margin::new(2, 1));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let key_style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+    let mut lines = vec![
+        Line::from("Some data files could not be read. Nothing has been saved over them."),
+        Line::from(""),
+    ];
+    for error in errors {
+        let position = if error.line > 0 {
+            format!(" (line {}, column {})", error.line, error.column)
+        } else {
+            String::new()
+        };
+        lines.push(Line::from(vec![
+            Span::styled(error.location.clone(), Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+            Span::raw(position),
+        ]));
+        lines.push(Line::from(Span::styled(format!("  {}", error.message), Style::default().fg(Color::Gray))));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![Span::styled(keymap.key_label(KeyContext::Recovery, Action::OpenReadOnly), key_style), Span::raw(": Open read-only, without saving any changes")]));
+    lines.push(Line::from(vec![Span::styled(keymap.key_label(KeyContext::Recovery, Action::RestoreBackups), key_style), Span::raw(": Restore the newest readable backup of each file")]));
+    lines.push(Line::from(vec![Span::styled(keymap.key_label(KeyContext::Recovery, Action::SkipInvalidRecords), key_style), Span::raw(": Skip the bad records and keep the rest")]));
+    lines.push(Line::from(vec![Span::styled(keymap.key_label(KeyContext::Recovery, Action::Quit), key_style), Span::raw(": Quit")]));
+    if let Some(message) = message {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(message.to_string(), Style::default().fg(Color::Red))));
+    }
+
+    let paragraph = Paragraph::new(lines)
+        .style(Style::default().fg(Color::White))
+        .wrap(Wrap { trim: false });
+    f.render_widget(paragraph, inner.inner(Margin::new(1, 1)));
+}
+
+pub fn render_reload_prompt(f: &mut Frame, changes: &DiskChanges, keymap: &Keymap, message: Option<&str>) {
+    let area = centered_rect(60, 50, f.area());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Reload Data ")
+        .title_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Yellow));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let key_style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+    let mut lines = Vec::new();
+    if !changes.files.is_empty() {
+        lines.push(Line::from(format!("Another program changed {}.", changes.files.join(", "))));
+        lines.push(Line::from(""));
+    }
+    lines.push(Line::from(match changes.unsaved {
+        0 => "There are no unsaved changes.".to_string(),
+        unsaved => format!("{} unsaved change(s) will be merged into the data on disk and saved.", unsaved),
+    }));
+    if !changes.conflicts.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled("Also changed on disk:", Style::default().fg(Color::Red))));
+        for conflict in &changes.conflicts {
+            lines.push(Line::from(format!("  • {}", conflict)));
+        }
+    }
+    lines.push(Line::from(""));
+    if changes.conflicts.is_empty() {
+        lines.push(Line::from(vec![Span::styled(keymap.key_label(KeyContext::Reload, Action::Reload), key_style), Span::raw(": Reload")]));
+    } else {
+        lines.push(Line::from(vec![Span::styled(keymap.key_label(KeyContext::Reload, Action::Reload), key_style), Span::raw(": Reload, keeping the disk's version of these")]));
+        lines.push(Line::from(vec![Span::styled(keymap.key_label(KeyContext::Reload, Action::ReloadKeepingMine), key_style), Span::raw(": Reload, keeping your version of these")]));
+    }
+    lines.push(Line::from(vec![
+        Span::styled(keymap.key_label(KeyContext::Reload, Action::Cancel), key_style),
+        Span::raw(": Keep working; saves still merge in the changed files"),
+    ]));
+    if let Some(message) = message {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(message.to_string(), Style::default().fg(Color::Red))));
+    }
+
+    let paragraph = Paragraph::new(lines)
+        .style(Style::default().fg(Color::White))
+        .wrap(Wrap { trim: false });
+    f.render_widget(paragraph, inner.inner(Margin::new(1, 1)));
+}
+
 // Helper function to create a centered rect using percentage of the available rect
 pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
@@ -1,10 +1,13 @@
+use crate::keymap::Action;
 use ratatui::{
-    layout::{Margin, Rect},
+    layout::Rect,
     style::{Color, Modifier, Style},
-    text::{Line, Span},
-    widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph},
+    text::Span,
+    widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState},
     Frame,
 };
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 // Predefined list of majors for the student dropdown
 pub const MAJORS: &[&str] = &[
@@ -32,25 +35,50 @@ pub const MAJORS: &[&str] = &[
 pub struct DropdownState {
     pub is_open: bool,
     pub options: Vec<String>,
+    // Value stored in the form for each option (e.g. a record id); same as the label by default
+    pub values: Vec<String>,
+    // Multi-select dropdowns toggle options instead of picking a single one
+    pub multi_select: bool,
     pub list_state: ListState,
 }
 
 impl DropdownState {
     pub fn new(options: Vec<String>) -> Self {
+        let values = options.clone();
+        Self::with_values(options, values)
+    }
+
+    // Create a dropdown whose displayed labels differ from the stored values
+    pub fn with_values(options: Vec<String>, values: Vec<String>) -> Self {
         let mut list_state = ListState::default();
-        list_state.select(Some(0));
+        if !options.is_empty() {
+            list_state.select(Some(0));
+        }
         Self {
             is_open: false,
             options,
+            values,
+            multi_select: false,
             list_state,
         }
     }
 
+    // Create a dropdown that allows several values, stored comma-separated
+    pub fn multi(options: Vec<String>, values: Vec<String>) -> Self {
+        Self {
+            multi_select: true,
+            ..Self::with_values(options, values)
+        }
+    }
+
     pub fn toggle_open(&mut self) {
         self.is_open = !self.is_open;
     }
 
     pub fn select_next(&mut self) {
+        if self.options.is_empty() {
+            return;
+        }
         let i = match self.list_state.selected() {
             Some(i) => {
                 if i >= self.options.len() - 1 {
@@ -65,6 +93,9 @@ impl DropdownState {
     }
 
     pub fn select_prev(&mut self) {
+        if self.options.is_empty() {
+            return;
+        }
         let i = match self.list_state.selected() {
             Some(i) => {
                 if i == 0 {
@@ -78,24 +109,15 @@ impl DropdownState {
         self.list_state.select(Some(i));
     }
 
-    pub fn selected_item(&self) -> Option<&String> {
+    pub fn selected_value(&self) -> Option<&String> {
         match self.list_state.selected() {
-            Some(i) => self.options.get(i),
+            Some(i) => self.values.get(i),
             None => None,
         }
     }
 
-    pub fn set_options(&mut self, options: Vec<String>) {
-        self.options = options;
-        if self.options.is_empty() {
-            self.list_state.select(None);
-        } else {
-            self.list_state.select(Some(0));
-        }
-    }
-
     pub fn select_by_value(&mut self, value: &str) {
-        for (i, option) in self.options.iter().enumerate() {
+        for (i, option) in self.values.iter().enumerate() {
             if option == value {
                 self.list_state.select(Some(i));
                 return;
@@ -106,27 +128,67 @@ impl DropdownState {
             self.list_state.select(Some(0));
         }
     }
+
+    // Apply the highlighted option to a form value: replaces it for single-select
+    // dropdowns and toggles membership in the comma-separated list for multi-select
+    pub fn apply_selection(&self, current: &str) -> Option<String> {
+        let value = self.selected_value()?;
+        if !self.multi_select {
+            return Some(value.clone());
+        }
+
+        let mut values = split_values(current);
+        if let Some(pos) = values.iter().position(|v| v == value) {
+            values.remove(pos);
+        } else {
+            values.push(value.clone());
+        }
+        Some(values.join(","))
+    }
+
+    // Human-readable text for a stored form value
+    pub fn display_value(&self, current: &str) -> String {
+        split_values(current)
+            .iter()
+            .map(|value| match self.values.iter().position(|v| v == value) {
+                Some(i) => self.options[i].clone(),
+                None => value.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
 }
 
-// Function to render the dropdown list
-pub fn render_dropdown(f: &mut Frame, dropdown_state: &mut DropdownState, area: Rect) {
-    // Calculate the position for the dropdown - right below the field
-    let dropdown_area = Rect::new(
-        area.x,
-        area.y + 1, // Position right at the bottom edge of the field
-        area.width,
-        12.min(dropdown_state.options.len() as u16 + 2), // Height based on number of options with max of 12
-    );
-    
+// Split a comma-separated multi-select value into its parts
+pub fn split_values(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+// Function to render the dropdown list into `dropdown_area`, border included; `current` is
+// the form value, used to mark the checked options of a multi-select dropdown
+pub fn render_dropdown(f: &mut Frame, dropdown_state: &mut DropdownState, dropdown_area: Rect, current: &str) {
     // Clear the area to prevent visual artifacts
     f.render_widget(Clear, dropdown_area);
     
     // Create the items for the dropdown list
+    let checked = split_values(current);
     let items: Vec<ListItem> = dropdown_state
         .options
         .iter()
-        .map(|option| {
-            ListItem::new(option.as_str())
+        .zip(&dropdown_state.values)
+        .map(|(option, value)| {
+            let text = if dropdown_state.multi_select {
+                let mark = if checked.contains(value) { "[x] " } else { "[ ] " };
+                format!("{}{}", mark, option)
+            } else {
+                option.clone()
+            };
+            ListItem::new(text)
                 .style(Style::default().fg(Color::White))
         })
         .collect();
@@ -146,4 +208,281 @@ pub fn render_dropdown(f: &mut Frame, dropdown_state: &mut DropdownState, area:
     
     // Render the dropdown list with the current selection state
     f.render_stateful_widget(list, dropdown_area, &mut dropdown_state.list_state);
-}
\ No newline at end of file
+}
+// Single-line text being edited, with a cursor that moves and deletes by grapheme, so
+// "й" typed as two code points or an emoji with a skin tone go away with one Backspace
+#[derive(Debug, Clone, Default)]
+pub struct TextInput {
+    value: String,
+    // Byte offset into `value`, always on a grapheme boundary
+    cursor: usize,
+}
+
+impl TextInput {
+    // Starts with the cursor at the end
+    pub fn new(value: String) -> Self {
+        let cursor = value.len();
+        Self { value, cursor }
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.value.is_empty()
+    }
+
+    pub fn set(&mut self, value: String) {
+        *self = Self::new(value);
+    }
+
+    pub fn clear(&mut self) {
+        self.set(String::new());
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.value.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    // Insert pasted text; line breaks become spaces since the input is a single line
+    pub fn insert_str(&mut self, text: &str) {
+        let text: String = text
+            .chars()
+            .filter(|c| *c != '\r')
+            .map(|c| if c == '\n' || c == '\t' { ' ' } else { c })
+            .filter(|c| !c.is_control())
+            .collect();
+        self.value.insert_str(self.cursor, &text);
+        self.cursor += text.len();
+    }
+
+    fn previous_boundary(&self) -> usize {
+        self.value[..self.cursor]
+            .graphemes(true)
+            .next_back()
+            .map_or(0, |grapheme| self.cursor - grapheme.len())
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.value[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map_or(self.value.len(), |grapheme| self.cursor + grapheme.len())
+    }
+
+    // Start of the word before the cursor, skipping the spaces in between
+    fn previous_word(&self) -> usize {
+        let mut graphemes = self.value[..self.cursor].grapheme_indices(true).rev().peekable();
+        while graphemes.next_if(|(_, g)| is_space(g)).is_some() {}
+        let mut start = graphemes.peek().map_or(0, |(i, _)| *i);
+        for (i, g) in graphemes {
+            if is_space(g) {
+                break;
+            }
+            start = i;
+        }
+        start
+    }
+
+    // End of the word after the cursor, skipping the spaces before it
+    fn next_word(&self) -> usize {
+        let rest = &self.value[self.cursor..];
+        let mut graphemes = rest.grapheme_indices(true).peekable();
+        while graphemes.next_if(|(_, g)| is_space(g)).is_some() {}
+        let end = graphemes.find(|(_, g)| is_space(g)).map_or(rest.len(), |(i, _)| i);
+        self.cursor + end
+    }
+
+    // Carry out an action that `Action::edits_text`; true if the text changed
+    pub fn apply(&mut self, action: Action) -> bool {
+        let before = self.value.len();
+        match action {
+            Action::CursorLeft => self.cursor = self.previous_boundary(),
+            Action::CursorRight => self.cursor = self.next_boundary(),
+            Action::WordLeft => self.cursor = self.previous_word(),
+            Action::WordRight => self.cursor = self.next_word(),
+            Action::LineStart => self.cursor = 0,
+            Action::LineEnd => self.cursor = self.value.len(),
+            Action::DeleteChar => {
+                let start = self.previous_boundary();
+                self.value.replace_range(start..self.cursor, "");
+                self.cursor = start;
+            }
+            Action::DeleteNextChar => {
+                let end = self.next_boundary();
+                self.value.replace_range(self.cursor..end, "");
+            }
+            Action::DeleteWord => {
+                let start = self.previous_word();
+                self.value.replace_range(start..self.cursor, "");
+                self.cursor = start;
+            }
+            _ => {}
+        }
+        self.value.len() != before
+    }
+
+    // The text as spans fitting in `width` columns, with the grapheme under the cursor
+    // highlighted when focused. Text too long to fit scrolls so the cursor stays visible.
+    pub fn spans(&self, style: Style, focused: bool, width: u16) -> Vec<Span<'_>> {
+        let width = width as usize;
+        if !focused {
+            return vec![Span::styled(clip(&self.value, width), style)];
+        }
+        let end = self.next_boundary();
+        let under_cursor = match &self.value[self.cursor..end] {
+            "" => " ",
+            grapheme => grapheme,
+        };
+
+        // Drop graphemes from the front until everything up to and including the cursor fits
+        let mut start = 0;
+        let mut used = self.value[..self.cursor].width() + under_cursor.width();
+        for grapheme in self.value[..self.cursor].graphemes(true) {
+            if used <= width {
+                break;
+            }
+            start += grapheme.len();
+            used -= grapheme.width();
+        }
+        vec![
+            Span::styled(&self.value[start..self.cursor], style),
+            Span::styled(under_cursor, style.add_modifier(Modifier::REVERSED)),
+            Span::styled(clip(&self.value[end..], width.saturating_sub(used)), style),
+        ]
+    }
+}
+
+// The longest start of `text` that fits in `width` columns, cut between graphemes
+fn clip(text: &str, width: usize) -> &str {
+    let mut used = 0;
+    for (i, grapheme) in text.grapheme_indices(true) {
+        used += grapheme.width();
+        if used > width {
+            return &text[..i];
+        }
+    }
+    text
+}
+
+fn is_space(grapheme: &str) -> bool {
+    grapheme.chars().all(char::is_whitespace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // "й" written as "и" plus a combining breve, and a waving hand with a skin tone
+    const SHORT_I: &str = "и\u{306}";
+    const WAVE: &str = "👋🏽";
+
+    // The text of each span, for comparing what `spans` shows
+    fn texts(spans: Vec<Span<'_>>) -> Vec<String> {
+        spans.into_iter().map(|span| span.content.into_owned()).collect()
+    }
+
+    #[test]
+    fn moves_the_cursor_by_grapheme() {
+        let mut input = TextInput::new(format!("a{}{}b", SHORT_I, WAVE));
+        input.apply(Action::CursorLeft);
+        input.apply(Action::CursorLeft);
+        input.insert('|');
+        assert_eq!(input.value(), format!("a{}|{}b", SHORT_I, WAVE));
+
+        input.apply(Action::LineStart);
+        input.apply(Action::CursorRight);
+        input.apply(Action::CursorRight);
+        input.insert('|');
+        assert_eq!(input.value(), format!("a{}||{}b", SHORT_I, WAVE));
+
+        // Moving past either end stays there
+        input.apply(Action::LineEnd);
+        input.apply(Action::CursorRight);
+        input.insert('!');
+        assert!(input.value().ends_with("b!"));
+    }
+
+    #[test]
+    fn deletes_whole_graphemes() {
+        let mut input = TextInput::new(format!("{}{}", SHORT_I, WAVE));
+        assert!(input.apply(Action::DeleteChar));
+        assert_eq!(input.value(), SHORT_I);
+
+        input.apply(Action::LineStart);
+        assert!(input.apply(Action::DeleteNextChar));
+        assert_eq!(input.value(), "");
+        assert!(!input.apply(Action::DeleteChar));
+        assert!(!input.apply(Action::DeleteNextChar));
+    }
+
+    #[test]
+    fn moves_and_deletes_by_word() {
+        let mut input = TextInput::new("Ada  Lovelace".to_string());
+        input.apply(Action::WordLeft);
+        input.insert('|');
+        assert_eq!(input.value(), "Ada  |Lovelace");
+
+        input.apply(Action::LineStart);
+        input.apply(Action::WordRight);
+        input.insert('|');
+        assert_eq!(input.value(), "Ada|  |Lovelace");
+
+        // The spaces before the word go with it
+        let mut input = TextInput::new("Ada  Lovelace  ".to_string());
+        assert!(input.apply(Action::DeleteWord));
+        assert_eq!(input.value(), "Ada  ");
+    }
+
+    #[test]
+    fn inserts_at_the_cursor() {
+        let mut input = TextInput::new("ac".to_string());
+        input.apply(Action::CursorLeft);
+        input.insert('b');
+        assert_eq!(input.value(), "abc");
+
+        // Pasted line breaks and tabs become spaces, other control characters are dropped
+        input.insert_str("1\r\n2\t3\u{7}");
+        input.insert('|');
+        assert_eq!(input.value(), "ab1 2 3|c");
+    }
+
+    #[test]
+    fn spans_highlight_the_grapheme_under_the_cursor() {
+        let mut input = TextInput::new(format!("a{}b", SHORT_I));
+        assert_eq!(texts(input.spans(Style::default(), true, 20)), ["a\u{438}\u{306}b", " ", ""]);
+        input.apply(Action::CursorLeft);
+        input.apply(Action::CursorLeft);
+        assert_eq!(texts(input.spans(Style::default(), true, 20)), ["a", SHORT_I, "b"]);
+        assert_eq!(texts(input.spans(Style::default(), false, 20)), [format!("a{}b", SHORT_I)]);
+    }
+
+    #[test]
+    fn spans_scroll_to_keep_the_cursor_visible() {
+        let mut input = TextInput::new("abcdefghij".to_string());
+        // At the end: the last characters and the cursor cell fill the width
+        assert_eq!(texts(input.spans(Style::default(), true, 5)), ["ghij", " ", ""]);
+
+        // At the start: the text after the cursor is cut at the width
+        input.apply(Action::LineStart);
+        assert_eq!(texts(input.spans(Style::default(), true, 5)), ["", "a", "bcde"]);
+
+        input.apply(Action::WordRight);
+        input.apply(Action::CursorLeft);
+        input.apply(Action::CursorLeft);
+        assert_eq!(texts(input.spans(Style::default(), true, 5)), ["efgh", "i", ""]);
+
+        // Without focus the start is shown
+        assert_eq!(texts(input.spans(Style::default(), false, 5)), ["abcde"]);
+    }
+
+    #[test]
+    fn spans_never_split_a_wide_grapheme() {
+        let input = TextInput::new(format!("{}{}{}", WAVE, WAVE, WAVE));
+        // Two columns per wave: only one fits next to the cursor cell in four columns
+        assert_eq!(texts(input.spans(Style::default(), true, 4)), [WAVE, " ", ""]);
+        assert_eq!(texts(input.spans(Style::default(), false, 5)), [format!("{}{}", WAVE, WAVE)]);
+    }
+}